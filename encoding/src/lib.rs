@@ -179,6 +179,8 @@ mod symbol;
 mod zdingbat;
 mod macroman;
 mod cp1252;
+mod core14;
+mod metrics;
 
 pub use stdenc::STANDARD;
 pub use macexpert::MACEXPERT;
@@ -186,6 +188,7 @@ pub use symbol::SYMBOL;
 pub use macroman::MACROMAN;
 pub use cp1252::WINANSI;
 pub use zdingbat::ZDINGBAT;
+pub use metrics::{standard_metrics, FontMetrics};
 
 
 #[test]
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use crate::core14::*;
+use crate::{glyphname_to_unicode, Encoding};
+
+/// Metrics of one of the standard 14 fonts.
+pub struct FontMetrics {
+    /// PostScript name, e.g. `Helvetica-Bold`
+    pub name: &'static str,
+    /// the built-in encoding of the font
+    pub encoding: Encoding,
+    pub(crate) widths: &'static [(&'static str, u16)],
+    pub(crate) kerning: &'static [(&'static str, &'static str, i16)],
+}
+impl FontMetrics {
+    /// Advance width of the glyph `name` in 1/1000 em.
+    pub fn glyph_width(&self, name: &str) -> Option<f32> {
+        self.widths
+            .binary_search_by(|&(n, _)| n.cmp(name))
+            .ok()
+            .map(|idx| self.widths[idx].1 as f32)
+    }
+
+    /// Advance width of the glyph for `c` in 1/1000 em.
+    pub fn char_width(&self, c: char) -> Option<f32> {
+        self.glyph_width(self.glyph_name(c)?)
+    }
+
+    /// Kerning between the glyphs `left` and `right` (by name) in 1/1000 em, 0 if the pair is not kerned.
+    pub fn glyph_kern(&self, left: &str, right: &str) -> f32 {
+        self.kerning
+            .binary_search_by(|&(l, r, _)| (l, r).cmp(&(left, right)))
+            .map(|idx| self.kerning[idx].2 as f32)
+            .unwrap_or(0.)
+    }
+
+    /// Kerning between the glyphs for `left` and `right` in 1/1000 em, to be added to the advance of `left`.
+    pub fn kern(&self, left: char, right: char) -> f32 {
+        match (self.glyph_name(left), self.glyph_name(right)) {
+            (Some(left), Some(right)) => self.glyph_kern(left, right),
+            _ => 0.,
+        }
+    }
+
+    fn glyph_name(&self, c: char) -> Option<&'static str> {
+        GLYPH_NAMES.get(self.name)?.get(&c).copied()
+    }
+
+    /// Advance width of `code` in the built-in encoding in 1/1000 em.
+    pub fn code_width(&self, code: u8) -> Option<f32> {
+        let c = self.encoding.forward_map()?.get(code)?;
+        self.char_width(c)
+    }
+}
+
+static STANDARD_14: &[&FontMetrics] = &[
+    &COURIER, &COURIER_BOLD, &COURIER_OBLIQUE, &COURIER_BOLD_OBLIQUE,
+    &HELVETICA, &HELVETICA_BOLD, &HELVETICA_OBLIQUE, &HELVETICA_BOLD_OBLIQUE,
+    &TIMES_ROMAN, &TIMES_BOLD, &TIMES_ITALIC, &TIMES_BOLD_ITALIC,
+    &SYMBOL, &ZAPF_DINGBATS,
+];
+
+// other names commonly used for the standard fonts (PDF 1.7, Annex H.5),
+// compared after removing everything but letters
+static ALIASES: &[(&str, &str)] = &[
+    ("arial", "Helvetica"),
+    ("arialmt", "Helvetica"),
+    ("arialbold", "Helvetica-Bold"),
+    ("arialboldmt", "Helvetica-Bold"),
+    ("arialitalic", "Helvetica-Oblique"),
+    ("arialitalicmt", "Helvetica-Oblique"),
+    ("arialbolditalic", "Helvetica-BoldOblique"),
+    ("arialbolditalicmt", "Helvetica-BoldOblique"),
+    ("helveticaitalic", "Helvetica-Oblique"),
+    ("helveticabolditalic", "Helvetica-BoldOblique"),
+    ("times", "Times-Roman"),
+    ("timesnewroman", "Times-Roman"),
+    ("timesnewromanps", "Times-Roman"),
+    ("timesnewromanpsmt", "Times-Roman"),
+    ("timesnewromanbold", "Times-Bold"),
+    ("timesnewromanpsbold", "Times-Bold"),
+    ("timesnewromanpsboldmt", "Times-Bold"),
+    ("timesnewromanitalic", "Times-Italic"),
+    ("timesnewromanpsitalic", "Times-Italic"),
+    ("timesnewromanpsitalicmt", "Times-Italic"),
+    ("timesnewromanbolditalic", "Times-BoldItalic"),
+    ("timesnewromanpsbolditalic", "Times-BoldItalic"),
+    ("timesnewromanpsbolditalicmt", "Times-BoldItalic"),
+    ("couriernew", "Courier"),
+    ("couriernewpsmt", "Courier"),
+    ("couriernewbold", "Courier-Bold"),
+    ("couriernewpsboldmt", "Courier-Bold"),
+    ("couriernewitalic", "Courier-Oblique"),
+    ("couriernewpsitalicmt", "Courier-Oblique"),
+    ("couriernewbolditalic", "Courier-BoldOblique"),
+    ("couriernewpsbolditalicmt", "Courier-BoldOblique"),
+    ("courieritalic", "Courier-Oblique"),
+    ("courierbolditalic", "Courier-BoldOblique"),
+    ("symbolmt", "Symbol"),
+    ("zapfdingbatsitc", "ZapfDingbats"),
+    ("dingbats", "ZapfDingbats"),
+];
+
+fn normalize(name: &str) -> String {
+    name.chars().filter(|c| c.is_ascii_alphabetic()).map(|c| c.to_ascii_lowercase()).collect()
+}
+
+/// The metrics of a standard 14 font, by PostScript name or one of its common aliases
+/// (e.g. `Arial,Bold` or `TimesNewRomanPSMT`). A subset prefix is ignored.
+pub fn standard_metrics(name: &str) -> Option<&'static FontMetrics> {
+    let name = match name.split_once('+') {
+        Some((tag, rest)) if tag.len() == 6 && tag.bytes().all(|b| b.is_ascii_uppercase()) => rest,
+        _ => name,
+    };
+    let key = normalize(name);
+    let name = ALIASES.iter().find(|&&(alias, _)| alias == key).map(|&(_, n)| n);
+    STANDARD_14.iter().copied().find(|m| match name {
+        Some(name) => m.name == name,
+        None => normalize(m.name) == key,
+    })
+}
+
+lazy_static! {
+    // font name -> char -> glyph name
+    static ref GLYPH_NAMES: HashMap<&'static str, HashMap<char, &'static str>> = {
+        STANDARD_14.iter().map(|m| {
+            let mut names = HashMap::new();
+            for &(name, _) in m.widths {
+                let c = glyphname_to_unicode(name).and_then(single_char)
+                    .or_else(|| zapf_dingbats_unicode(name));
+                if let Some(c) = c {
+                    names.entry(c).or_insert(name);
+                }
+            }
+            // StandardEncoding maps space and hyphen to these
+            for (c, name) in [('\u{A0}', "space"), ('\u{AD}', "hyphen")] {
+                if m.glyph_width(name).is_some() {
+                    names.entry(c).or_insert(name);
+                }
+            }
+            (m.name, names)
+        }).collect()
+    };
+}
+
+fn zapf_dingbats_unicode(name: &str) -> Option<char> {
+    ZAPF_DINGBATS_NAMES
+        .binary_search_by(|&(n, _)| n.cmp(name))
+        .ok()
+        .map(|idx| ZAPF_DINGBATS_NAMES[idx].1)
+}
+
+fn single_char(s: &str) -> Option<char> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_standard_metrics() {
+    assert_eq!(standard_metrics("Helvetica").unwrap().glyph_width("W"), Some(944.));
+    assert_eq!(standard_metrics("ABCDEF+Arial,Bold").unwrap().name, "Helvetica-Bold");
+    assert_eq!(standard_metrics("TimesNewRomanPSMT").unwrap().char_width('a'), Some(444.));
+    assert_eq!(standard_metrics("Courier-Oblique").unwrap().code_width(b'x'), Some(600.));
+    assert_eq!(standard_metrics("Symbol").unwrap().code_width(0x61), Some(631.));
+    assert!(standard_metrics("Calibri").is_none());
+}
+
+#[test]
+fn test_standard_metrics_complete() {
+    // number of glyphs in StartCharMetrics of the AFM files
+    for m in STANDARD_14 {
+        let count = match m.name {
+            "Symbol" => 190,
+            "ZapfDingbats" => 202,
+            _ => 315,
+        };
+        assert_eq!(m.widths.len(), count, "{}", m.name);
+        assert!(m.widths.windows(2).all(|w| w[0].0 < w[1].0), "{} is not sorted", m.name);
+    }
+    assert!(ZAPF_DINGBATS_NAMES.windows(2).all(|w| w[0].0 < w[1].0));
+    let zapf = standard_metrics("ZapfDingbats").unwrap();
+    assert_eq!(zapf.glyph_width("a191"), Some(918.));
+    assert_eq!(zapf.code_width(0xFE), Some(918.));
+    assert_eq!(standard_metrics("Courier").unwrap().glyph_width("Euro"), Some(600.));
+    assert_eq!(standard_metrics("Courier").unwrap().glyph_width("a1"), None);
+}
+
+#[test]
+fn test_standard_kerning() {
+    let helvetica = standard_metrics("Helvetica").unwrap();
+    assert_eq!(helvetica.glyph_kern("A", "V"), -70.);
+    assert_eq!(helvetica.kern('A', 'V'), -70.);
+    assert_eq!(helvetica.kern('V', 'A'), -80.);
+    assert_eq!(helvetica.kern('A', 'B'), 0.);
+    assert_eq!(standard_metrics("Arial,Italic").unwrap().kern('A', 'V'), -70.);
+    assert_eq!(standard_metrics("Times-Roman").unwrap().kern('A', 'V'), -135.);
+    assert_eq!(standard_metrics("Courier").unwrap().kern('A', 'V'), 0.);
+    for m in STANDARD_14 {
+        assert!(m.kerning.windows(2).all(|w| (w[0].0, w[0].1) < (w[1].0, w[1].1)), "{} is not sorted", m.name);
+    }
+}
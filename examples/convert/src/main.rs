@@ -65,11 +65,11 @@ fn main() -> Result<(), PdfError> {
     let file = FileOptions::cached().open(&opt.input)?;
     let resolver = file.resolver();
     
-    let mut cache = Cache::new();
+    let cache = Cache::new().expect("can't load standard fonts");
     for (i, page) in file.pages().enumerate().skip(opt.page as usize).take(opt.pages as usize) {
         println!("page {}", i);
         let p: &Page = &*page.unwrap();
-        let mut backend = SceneBackend::new(&mut cache);
+        let mut backend = SceneBackend::new(&cache);
         render_page(&mut backend, &resolver, p, transform)?;
         let output = if opt.pages > 1 {
             let replacement = format!("{page:0digits$}", page=i, digits=opt.digits);
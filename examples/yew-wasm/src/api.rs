@@ -1,5 +1,22 @@
+use std::rc::Rc;
+
+use pdf::error::PdfError;
+use pdf::file::FileOptions;
+use pdf::object::Resolve;
+use pdf_render::links::{Link, LinkResolver, LinkTarget};
+use pdf_render::search::{Search, SearchMatch, SearchOptions};
+use pdf_render::textlayout::PageText;
+use pdf_render::tracer::{TraceCache, Tracer};
+use pdf_render::{page_size, page_transform, render_page, PageBox};
 use url::Url;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
 use web_sys::js_sys::Error;
+use web_sys::Response;
+
+use crate::error::ApiError;
+use crate::pages::PdfDocument;
+use crate::types::DocumentText;
 
 
 
@@ -10,24 +27,116 @@ fn stream(url: Url) {
     todo!()
 }
 
-pub async fn find(
-    case_sensitive: bool,
-    find_previous: bool,
-    highlight_all: bool,
-    phrase_search: bool,
-    query: String,
-) -> Result<(), Error> {
-    todo!()
+/// A parsed document with the text and links of its pages.
+pub struct LoadedDocument {
+    pub pdf: PdfDocument,
+    pub text: DocumentText,
+    /// the link anchors of each page
+    pub links: Rc<Vec<Vec<LinkAnchor>>>,
 }
 
-pub async fn findagain(
-    case_sensitive: bool,
-    find_previous: bool,
-    highlight_all: bool,
-    phrase_search: bool,
-    query: String,
-) -> Result<(), Error> {
-    todo!()
+/// Download the PDF at `url`.
+pub async fn fetch(url: &str) -> Result<Vec<u8>, ApiError> {
+    let error = |e: JsValue| ApiError::PdfLoadError(format!("{:?}", e));
+    let window = web_sys::window().ok_or_else(|| ApiError::PdfLoadError("no window".into()))?;
+    let response: Response = JsFuture::from(window.fetch_with_str(url))
+        .await
+        .map_err(error)?
+        .dyn_into()
+        .map_err(error)?;
+    if !response.ok() {
+        return Err(ApiError::PdfLoadError(format!("{} returned {}", url, response.status())));
+    }
+    let buffer = JsFuture::from(response.array_buffer().map_err(error)?).await.map_err(error)?;
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+/// Parse a PDF and trace every page to extract its text and links.
+/// Pages that fail to render have no text.
+pub fn load(data: Vec<u8>) -> Result<LoadedDocument, ApiError> {
+    let error = |e: PdfError| ApiError::PdfLoadError(e.to_string());
+    let file = FileOptions::cached().load(data).map_err(error)?;
+    let resolver = file.resolver();
+    let cache = TraceCache::new().map_err(error)?;
+    let link_resolver = resolver
+        .resolve(file.trailer.root.get_ref().get_inner())
+        .and_then(|p| p.into_dictionary())
+        .and_then(|catalog| LinkResolver::new(&catalog, &resolver))
+        .map_err(|e| log::warn!("links are not available: {:?}", e))
+        .ok();
+
+    let mut page_sizes = vec![];
+    let mut text = vec![];
+    let mut links = vec![];
+    for (nr, page) in file.pages().enumerate() {
+        let page = page.map_err(error)?;
+        let size = page_size(&page, PageBox::MediaBox);
+        page_sizes.push((size.x(), size.y()));
+
+        let mut clip_paths = vec![];
+        let mut backend = Tracer::new(&cache, &mut clip_paths);
+        match render_page(&mut backend, &resolver, &page, Default::default()) {
+            Ok(_) => text.push(PageText::new(&backend.finish())),
+            Err(e) => {
+                log::warn!("can't trace page {}: {:?}", nr + 1, e);
+                text.push(PageText::new(&[]));
+            }
+        }
+
+        let page_links = match &link_resolver {
+            Some(link_resolver) => link_resolver
+                .page_links(page.get_ref().get_inner(), page_transform(&page), &resolver)
+                .unwrap_or_else(|e| {
+                    log::warn!("can't read the links of page {}: {:?}", nr + 1, e);
+                    vec![]
+                }),
+            None => vec![],
+        };
+        links.push(link_anchors(&page_links));
+    }
+    Ok(LoadedDocument {
+        pdf: PdfDocument { num_pages: page_sizes.len(), page_sizes },
+        text: DocumentText(Rc::new(text)),
+        links: Rc::new(links),
+    })
+}
+
+/// Search the text of all pages. Page numbers in the result start at 0.
+pub fn find(pages: &[PageText], query: &str, options: SearchOptions) -> Result<Vec<SearchMatch>, ApiError> {
+    let search = Search::new(query, options).map_err(|e| ApiError::SearchError(e.to_string()))?;
+    Ok(search.find(pages.iter().enumerate().map(|(nr, text)| (nr as u32, text))))
+}
+
+/// An `<a>` laid over a link of a page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkAnchor {
+    /// position and size in mm
+    pub left: f32,
+    pub top: f32,
+    pub width: f32,
+    pub height: f32,
+    pub href: String,
+    /// whether the link leaves the document
+    pub external: bool,
+}
+
+/// Anchors for the links of a page. Links without a usable target are skipped.
+pub fn link_anchors(links: &[Link]) -> Vec<LinkAnchor> {
+    links.iter().filter_map(|link| {
+        let (href, external) = match link.target.as_ref()? {
+            LinkTarget::Uri(uri) => (uri.clone(), true),
+            LinkTarget::Dest(dest) | LinkTarget::Named { dest: Some(dest), .. } => (format!("#page={}", dest.page + 1), false),
+            _ => return None,
+        };
+        Some(LinkAnchor {
+            left: link.rect.min_x(),
+            top: link.rect.min_y(),
+            width: link.rect.width(),
+            height: link.rect.height(),
+            href,
+            external,
+        })
+    }).collect()
 }
 
 pub async fn on_toggle_thumbnail(show_sidebar: bool) -> Result<(), Error> {
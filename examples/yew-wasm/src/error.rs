@@ -6,5 +6,8 @@ pub enum ApiError {
     PdfLoadError(String),
     
     #[error("Error in rendering PDF docs, {0}")]
-    PdfViewError(String)
+    PdfViewError(String),
+
+    #[error("Invalid search, {0}")]
+    SearchError(String),
 }
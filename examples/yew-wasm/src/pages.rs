@@ -1,5 +1,6 @@
 #![allow(unused)]
 
+use crate::api;
 use crate::error::ApiError;
 use crate::types::{to_jsvalue, PDFFindControllerOptions, PDFViewerOptions};
 use crate::view;
@@ -16,13 +17,15 @@ use yew_hooks::{use_async, use_async_with_options, UseAsyncOptions};
 #[derive(Debug, Clone, PartialEq)]
 pub struct PdfDocument {
     pub num_pages: usize,
+    /// width and height of each page in mm
+    pub page_sizes: Vec<(f32, f32)>,
 }
 
 #[derive(Properties, PartialEq, Debug)]
 pub struct PdfPagesProps {
     pub scale: f64,
     pub url: String,
-    pub set_pdf: Callback<PdfDocument>,
+    pub set_pdf: Callback<api::LoadedDocument>,
     pub set_progress: Callback<f64>,
     pub set_current_page: Callback<usize>,
     pub set_viewer: Callback<PDFViewer>,
@@ -108,7 +111,18 @@ pub fn PdfPages(
     let onscroll = { Callback::from(move |e| {}) };
 
     // This fetch the pdf docs from server
-    let stream = use_async({ async { Ok::<_, ApiError>(()) } });
+    let stream = {
+        let url = url.clone();
+        let set_pdf = set_pdf.clone();
+        use_async_with_options(
+            async move {
+                let data = api::fetch(&url).await?;
+                set_pdf.emit(api::load(data)?);
+                Ok::<_, ApiError>(())
+            },
+            UseAsyncOptions::enable_auto(),
+        )
+    };
 
     html! {
         <div id="viewer-container" {onscroll} >
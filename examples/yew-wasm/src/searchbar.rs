@@ -8,12 +8,20 @@ use stylist::{css, yew::styled_component};
 use wasm_bindgen::{JsValue, JsCast};
 use web_sys::{HtmlElement, HtmlInputElement};
 use yew::prelude::*;
-use crate::types::PDFFindController;
+use pdf_render::search::{SearchMatch, SearchOptions};
+
+use crate::api;
+use crate::types::DocumentText;
 
 
 #[derive(Properties, PartialEq)]
 pub struct SearchBarProps {
-    pub set_find_controller: Callback<PDFFindController>,
+    /// the text of the pages to search
+    pub text: DocumentText,
+    /// all matches of the current search, to highlight them
+    pub on_matches: Callback<Vec<SearchMatch>>,
+    /// the selected match, to scroll it into view
+    pub on_select_match: Callback<SearchMatch>,
     pub hide_search_bar: Callback<bool>,
 }
 
@@ -46,114 +54,119 @@ fn SearchInfo() -> Html {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default, Clone)]
 struct State {
     search_term: String,
-    current_match_index: u32,
-    match_count: u32,
+    options: SearchOptions,
+    matches: Vec<SearchMatch>,
+    current_match_index: usize,
     search_completed: bool,
 }
-
-#[derive(Debug, Serialize)]
-pub struct FindParams {
-    case_sensitive: bool,
-    find_previous: Option<bool>,
-    highlight_all: bool,
-    phrase_search: bool,
-    query: String,
-}
-
-#[derive(Serialize, Deserialize)]
-struct PDFControllerArgs {
-    // event: ControllerEvent
+impl State {
+    fn select(&mut self, index: usize, on_select_match: &Callback<SearchMatch>) {
+        if let Some(m) = self.matches.get(index) {
+            self.current_match_index = index;
+            on_select_match.emit(m.clone());
+        }
+    }
+    fn next(&mut self, on_select_match: &Callback<SearchMatch>) {
+        let n = self.matches.len();
+        if n > 0 {
+            self.select((self.current_match_index + 1) % n, on_select_match);
+        }
+    }
+    fn previous(&mut self, on_select_match: &Callback<SearchMatch>) {
+        let n = self.matches.len();
+        if n > 0 {
+            self.select((self.current_match_index + n - 1) % n, on_select_match);
+        }
+    }
 }
 
 #[function_component]
 pub fn SearchBar(props: &SearchBarProps) -> Html {
-    let data = PDFControllerArgs {};
-
     let search_input_ref = use_node_ref();
-    let controller_event: ControllerEvent;
-    let params = JsValue::from_serde(&data).unwrap();
-    let find_controller = PDFFindController::new(&params);
-    let state: UseStateHandle<Option<State>> = use_state(|| None::<State>);
-
-    let search_input = use_node_ref();
-
-    let on_search = {
-        let state = state.clone();
-        Callback::from(move |e: InputEvent| {
-            controller_event.dispatch("find".to_string(), todo!());
-        })
-    };
-
-    // TODO: Serialize and cast to JsValue object
-    // &FindParams {
-    //     case_sensitive: false,
-    //     find_previous: Some(find_previous),
-    //     highlight_all: true,
-    //     phrase_search: true,
-    //     query: self.search_term.clone(),
-    // },
-    let on_find_again = {
-        let search_term = state.clone();
-        Callback::from(move |e: InputEvent| {
-            controller_event.dispatch("findagain".to_string(), todo!());
-        })
-    };
+    let state = use_state(State::default);
 
     let on_search_term = {
         let state = state.clone();
-        Callback::from(move |e: Event| {
-            let term = "".to_string();
-            if let Some(mut state) = &*state {
-                state.search_term = term.clone();
-                state.search_completed = false;
-            }
-            controller_event.dispatch("findagain".to_string(), todo!())
+        let text = props.text.clone();
+        let search_input = search_input_ref.clone();
+        let on_matches = props.on_matches.clone();
+        let on_select_match = props.on_select_match.clone();
+        Callback::from(move |_: Event| {
+            let Some(input) = search_input.cast::<HtmlInputElement>() else {
+                return;
+            };
+            let mut data = (*state).clone();
+            data.search_term = input.value();
+            data.current_match_index = 0;
+            data.matches = if data.search_term.trim().is_empty() {
+                vec![]
+            } else {
+                match api::find(&text.0, &data.search_term, data.options) {
+                    Ok(matches) => matches,
+                    Err(e) => {
+                        log::warn!("{}", e);
+                        vec![]
+                    }
+                }
+            };
+            data.search_completed = true;
+            on_matches.emit(data.matches.clone());
+            data.select(0, &on_select_match);
+            state.set(data);
         })
     };
 
-    let on_search_next = { Callback::from(move |e: KeyboardEvent| {}) };
-
-    let on_search_complete = {
+    let on_search_next = {
         let state = state.clone();
-        let find_controller = find_controller.clone();
-        Callback::from(move |e: InputEvent| {
-            if let Some(mut data) = &*state {
-                // data.match_count = find_controller.match_count();
-                // data.current_match_index = find_controller.current_match_index();
-                data.search_completed = true;
+        let on_select_match = props.on_select_match.clone();
+        Callback::from(move |e: KeyboardEvent| {
+            if e.key() == "Enter" {
+                let mut data = (*state).clone();
+                if e.shift_key() {
+                    data.previous(&on_select_match);
+                } else {
+                    data.next(&on_select_match);
+                }
+                state.set(data);
             }
         })
     };
 
     let previous_match = {
-        Callback::from(move |e: MouseEvent| {
-
+        let state = state.clone();
+        let on_select_match = props.on_select_match.clone();
+        Callback::from(move |_: MouseEvent| {
+            let mut data = (*state).clone();
+            data.previous(&on_select_match);
+            state.set(data);
         })
     };
 
     let next_match = {
-        Callback::from(move |e: MouseEvent| {
-
+        let state = state.clone();
+        let on_select_match = props.on_select_match.clone();
+        Callback::from(move |_: MouseEvent| {
+            let mut data = (*state).clone();
+            data.next(&on_select_match);
+            state.set(data);
         })
     };
 
-    let on_exit_search = Callback::from({
+    let on_exit_search = {
         let state = state.clone();
-        move |e: MouseEvent| {
-            controller_event.dispatch("find".to_string(), todo!());
-            if let Some(mut data) = &*state {
-                data.search_completed = false;
-                data.match_count = 0;
-                props.hide_search_bar.emit(true);
-            }
-        }
-    });
-    let Some(data) = &*state else {
-        panic!("Error!!!");
+        let on_matches = props.on_matches.clone();
+        let hide_search_bar = props.hide_search_bar.clone();
+        Callback::from(move |_: MouseEvent| {
+            state.set(State::default());
+            on_matches.emit(vec![]);
+            hide_search_bar.emit(true);
+        })
     };
+    let data = &*state;
+    let match_count = data.matches.len();
 
     html! {
         <div id="pdfSearchbar" class={css!(r#"
@@ -184,13 +197,13 @@ pub fn SearchBar(props: &SearchBarProps) -> Html {
                     <span class="search-status">
 
                         if data.search_completed {
-                            { format!("{}/{}", data.current_match_index, data.match_count) }
+                            { format!("{}/{}", if match_count > 0 { data.current_match_index + 1 } else { 0 }, match_count) }
                         }
 
-                        if data.match_count > 0 {
+                        if match_count > 0 {
                             <>
-                                <PrevIcon onclick={previous_match} disabled={ true } />
-                                <NextIcon onclick={next_match} disabled={ true } />
+                                <PrevIcon onclick={previous_match} disabled={ match_count < 2 } />
+                                <NextIcon onclick={next_match} disabled={ match_count < 2 } />
                             </>
                         }
                     </span>
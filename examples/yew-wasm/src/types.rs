@@ -1,4 +1,7 @@
 use std::collections::HashMap;
+use std::rc::Rc;
+
+use pdf_render::textlayout::PageText;
 
 use serde::Serialize;
 use wasm_bindgen::{JsCast, JsValue};
@@ -10,6 +13,15 @@ pub struct  PDFLinkService;
 
 pub struct PDFViewer;
 
+/// The extracted text of every page of the open document, used for searching.
+#[derive(Clone, Default)]
+pub struct DocumentText(pub Rc<Vec<PageText>>);
+impl PartialEq for DocumentText {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
 
 
 #[derive(Debug, Serialize, Clone, PartialEq)]
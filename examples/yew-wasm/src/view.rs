@@ -6,10 +6,11 @@ use yew_hooks::prelude::*;
 use yew_hooks::use_async;
 
 use super::{
-    error::ApiError, pages::PdfPages, progressbar::ProgressBar, searchbar::SearchBar,
+    api::{LinkAnchor, LoadedDocument}, error::ApiError, pages::{PdfDocument, PdfPages}, progressbar::ProgressBar, searchbar::SearchBar,
     thumbnail::ThumbBar, toolbox::Toolbox,
-    types::{ PDFFindController, PDFLinkService, PDFViewer }
+    types::{ DocumentText, PDFFindController, PDFLinkService, PDFViewer }
 };
+use pdf_render::search::SearchMatch;
 
 const ZOOM_STEP: f64 = 0.2;
 
@@ -54,9 +55,23 @@ pub fn PdfViewerComponent(
         Callback::from( move |c: usize| current_page.set(c) )
     };
 
+    // filled with the text of each page once the document is loaded
+    let document_text = use_state(DocumentText::default);
+    let search_matches = use_state(Vec::<SearchMatch>::new);
+    let selected_match = use_state(|| None::<SearchMatch>);
+    let page_links = use_state(|| Rc::new(Vec::<Vec<LinkAnchor>>::new()));
+
     let set_pdf = {
         let doc = doc.clone();
-        Callback::from( move |pdf: PdfDocument| doc.set(Some(pdf)) )
+        let document_text = document_text.clone();
+        let search_matches = search_matches.clone();
+        let page_links = page_links.clone();
+        Callback::from(move |loaded: LoadedDocument| {
+            doc.set(Some(loaded.pdf));
+            document_text.set(loaded.text);
+            page_links.set(loaded.links);
+            search_matches.set(vec![]);
+        })
     };
 
     let set_viewer = {
@@ -140,9 +155,24 @@ pub fn PdfViewerComponent(
             "#)}
             >
                 if *show_search_bar {
-                    <SearchBar set_find_controller={set_find_controller.clone()} hide_search_bar={
-                        Callback::from(move |e| show_search_bar.set(false))
-                    } />
+                    <SearchBar
+                        text={(*document_text).clone()}
+                        on_matches={
+                            let search_matches = search_matches.clone();
+                            Callback::from(move |matches| search_matches.set(matches))
+                        }
+                        on_select_match={
+                            let scroll_to = scroll_to.clone();
+                            let selected_match = selected_match.clone();
+                            Callback::from(move |m: SearchMatch| {
+                                scroll_to.emit(m.page as usize);
+                                selected_match.set(Some(m));
+                            })
+                        }
+                        hide_search_bar={
+                            Callback::from(move |e| show_search_bar.set(false))
+                        }
+                    />
                 }
                 <ThumbBar
                     pdf={(*doc).clone()}
@@ -170,7 +200,24 @@ pub fn PdfViewerComponent(
                         { on_change_page }
                     />
 
-                    <div id="pdf-pages">
+                    <div id="pdf-pages" class={css!(r#"
+                        .page {
+                            position: relative;
+                            margin: 5px auto;
+                            background-color: white;
+                        }
+                        .highlight {
+                            position: absolute;
+                            background-color: #2078A9;
+                            opacity: 0.3;
+                        }
+                        .highlight.selected {
+                            background-color: #0094FF;
+                        }
+                        .link {
+                            position: absolute;
+                        }
+                    "#)}>
                         if !url.is_empty() {
                             <PdfPages
                                 url= {url.clone()}
@@ -182,9 +229,62 @@ pub fn PdfViewerComponent(
                                 { set_progress }
                             />
                         }
+                        if let Some(pdf) = &*doc {
+                            { for pdf.page_sizes.iter().enumerate().map(|(nr, &(width, height))| html! {
+                                <div
+                                    class="page"
+                                    key={nr}
+                                    id={format!("page={}", nr + 1)}
+                                    data-page-number={(nr + 1).to_string()}
+                                    style={format!("width: {}mm; height: {}mm; zoom: {}", width, height, 1.0 + *scale)}
+                                >
+                                    { for search_matches.iter()
+                                        .filter(|m| m.page as usize == nr)
+                                        .map(|m| match_highlights(m, is_same_match(m, (*selected_match).as_ref()))) }
+                                    { for page_links.get(nr).into_iter().flatten().map(link_anchor) }
+                                </div>
+                            }) }
+                        }
                     </div>
                 </div>
             </div>
         </div>
     }
 }
+
+fn is_same_match(m: &SearchMatch, other: Option<&SearchMatch>) -> bool {
+    matches!(other, Some(other) if other.page == m.page && other.range == m.range)
+}
+
+/// One highlight for each line of a search match, positioned in mm on its page.
+fn match_highlights(m: &SearchMatch, selected: bool) -> Html {
+    m.quads.iter().map(|quad| {
+        let (mut min_x, mut min_y) = (f32::INFINITY, f32::INFINITY);
+        let (mut max_x, mut max_y) = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for p in quad {
+            min_x = min_x.min(p.x());
+            min_y = min_y.min(p.y());
+            max_x = max_x.max(p.x());
+            max_y = max_y.max(p.y());
+        }
+        html! {
+            <div
+                class={classes!("highlight", selected.then_some("selected"))}
+                style={format!("left: {}mm; top: {}mm; width: {}mm; height: {}mm", min_x, min_y, max_x - min_x, max_y - min_y)}
+            />
+        }
+    }).collect()
+}
+
+/// An `<a>` over a link of a page. Internal links point to the `page=N` id of the target page.
+fn link_anchor(anchor: &LinkAnchor) -> Html {
+    html! {
+        <a
+            class="link"
+            href={anchor.href.clone()}
+            target={anchor.external.then_some("_blank")}
+            rel={anchor.external.then_some("noopener noreferrer")}
+            style={format!("left: {}mm; top: {}mm; width: {}mm; height: {}mm", anchor.left, anchor.top, anchor.width, anchor.height)}
+        />
+    }
+}
@@ -853,7 +853,7 @@ where
             total += n as usize + 1;
             input = i;
 
-            if total >= num_glyphs - 1 {
+            if total >= num_glyphs.saturating_sub(1) {
                 break;
             }
         }
@@ -864,14 +864,14 @@ fn charset(i: &[u8], num_glyphs: usize) -> R<Charset> {
     let (i, format) = be_u8(i)?;
 
     match format {
-        0 => map(count(be_u16, num_glyphs as usize - 1), |a| {
+        0 => map(count(be_u16, num_glyphs.saturating_sub(1)), |a| {
             Charset::Continous(a)
         })(i),
         1 => map(ranges(map(be_u8, |n| n as u16), num_glyphs), |r| {
             Charset::Ranges(r)
         })(i),
         2 => map(ranges(be_u16, num_glyphs), |r| Charset::Ranges(r))(i),
-        _ => panic!("invalid charset format"),
+        _ => Err(Failure(make_error(i, ErrorKind::Switch))),
     }
 }
 
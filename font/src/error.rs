@@ -17,7 +17,9 @@ pub enum FontError {
     Require(&'static str),
     Reserved(&'static str, u32, u8),
     Key(&'static str, u32, u8),
-    Context(&'static str, u32, Box<FontError>)
+    Context(&'static str, u32, Box<FontError>),
+    /// a size or count in the font exceeds what we are willing to allocate (what, requested size)
+    Limit(&'static str, usize),
 }
 
 impl<'a> From<Err<VerboseError<&'a [u8]>>> for FontError {
@@ -78,6 +80,14 @@ macro_rules! expect {
     };
 }
 #[macro_export]
+macro_rules! limit {
+    ($what:expr, $n:expr, $max:expr) => {
+        if $n > $max {
+            return Err(crate::FontError::Limit($what, $n))
+        }
+    };
+}
+#[macro_export]
 macro_rules! slice {
     ($s:expr, $range:expr) => {
         match $s.get($range) {
@@ -532,14 +532,29 @@ pub struct FontInfo {
     pub name: Name,
     pub typ: FontType,
     pub codepoints: Vec<RangeInclusive<u32>>,
+    /// usWeightClass or the /Weight of a Type1 font
+    pub weight: Option<u16>,
+    /// degrees counter-clockwise from the vertical, negative for fonts leaning right
+    pub italic_angle: Option<f32>,
+    pub italic: bool,
+    pub fixed_pitch: bool,
+    pub panose: Option<[u8; 10]>,
 }
 
+/// Read the names and style of a font without loading the glyphs (except for Type1 fonts).
 pub fn font_info(data: &[u8]) -> Option<FontInfo> {
-    let magic: &[u8; 4] = data[0 .. 4].try_into().ok()?;
+    let magic: &[u8; 4] = data.get(0 .. 4)?.try_into().ok()?;
     info!("font magic: {:?} ({:?})", magic, String::from_utf8_lossy(&*magic));
     match magic {
         #[cfg(feature="opentype")]
-        b"OTTO" | [0,1,0,0] => OpenTypeFont::info(data).ok(),
+        b"OTTO" | [0,1,0,0] | b"true" => OpenTypeFont::info(data).ok(),
+
+        #[cfg(feature="type1")]
+        &[0x80, 1, _, _] => Type1Font::parse_pfb(data).ok().map(|f| f.font_info(FontType::Type1Pfb)),
+
+        #[cfg(feature="type1")]
+        &[37, 33, _, _] => Type1Font::parse_pfa(data).ok().map(|f| f.font_info(FontType::Type1Pfa)),
+
         _ => None
     }
 }
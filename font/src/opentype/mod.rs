@@ -82,11 +82,18 @@ impl OpenTypeFont {
         let tables = parse_tables(data)?;
         let name = tables.get(b"name").map(|data| parse_name(data)).transpose()?.unwrap_or_default();
         let cmap = tables.get(b"cmap").map(|data| parse_cmap(data)).transpose()?;
+        let os2 = tables.get(b"OS/2").map(|data| os2::parse_os2(data)).transpose()?;
+        let post = tables.get(b"post").map(parse_post).transpose()?;
 
         Ok(FontInfo {
             name,
             typ: FontType::OpenType,
             codepoints: cmap.map(|cmap| cmap.0.codepoints(10)).unwrap_or_default(),
+            weight: os2.as_ref().map(|os2| os2.weight),
+            italic_angle: post.as_ref().map(|post| post.italic_angle),
+            italic: os2.as_ref().map(|os2| os2.italic).unwrap_or(false),
+            fixed_pitch: post.as_ref().map(|post| post.is_fixed_pitch).unwrap_or(false),
+            panose: os2.and_then(|os2| os2.panose),
         })
     }
     pub fn from_hmtx_glyf_and_tables(hmtx: Option<Hmtx>, glyf: Option<Vec<Shape>>, tables: Tables<impl Deref<Target=[u8]>>) -> Result<Self, FontError> {
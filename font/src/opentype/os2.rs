@@ -4,14 +4,23 @@ use nom::{
 use crate::{FontError};
 
 pub struct Os2 {
-    pub weight: u16
+    pub weight: u16,
+    /// missing if the table is truncated
+    pub panose: Option<[u8; 10]>,
+    /// fsSelection bit 0
+    pub italic: bool,
 }
 
 pub fn parse_os2(data: &[u8]) -> Result<Os2, FontError> {
     let (i, version) = be_u16(data)?;
     let (i, _x_avg_char_width) = be_i16(i)?;
     let (i, weight) = be_u16(i)?;
+
+    let panose = data.get(32 .. 42).map(|p| p.try_into().unwrap());
+    let italic = data.get(62 .. 64).map(|f| f[1] & 1 != 0).unwrap_or(false);
     Ok(Os2 {
-        weight
+        weight,
+        panose,
+        italic,
     })
 }
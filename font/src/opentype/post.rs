@@ -1,15 +1,19 @@
-use nom::number::complete::{be_u32, be_u16};
+use nom::number::complete::{be_i32, be_u32, be_u16};
 
 use crate::{parsers::{array, Offset, count, pascal_string, Array}, FontError};
 
 #[derive(Debug)]
 pub struct PostTable<'a> {
     pub names: Vec<&'a str>,
+    /// degrees counter-clockwise from the vertical
+    pub italic_angle: f32,
+    pub is_fixed_pitch: bool,
 }
 
 pub fn parse_post<'i>(input: &'i [u8]) -> Result<PostTable<'i>, FontError> {
     let (i, version) = be_u32(input)?;
-    let (i, italic_angle) = be_u32(i)?;
+    let (i, italic_angle) = be_i32(i)?;
+    let italic_angle = italic_angle as f32 / 65536.;
     let (i, underline_position) = be_u16(i)?;
     let (i, underline_thickness) = be_u16(i)?;
     let (i, is_fixed_pitch) = be_u32(i)?;
@@ -37,9 +41,9 @@ pub fn parse_post<'i>(input: &'i [u8]) -> Result<PostTable<'i>, FontError> {
                 };
                 names.push(name);
             }
-            Ok(PostTable { names })
+            Ok(PostTable { names, italic_angle, is_fixed_pitch: is_fixed_pitch != 0 })
         }
-        _ => Ok(PostTable { names: vec![] })
+        _ => Ok(PostTable { names: vec![], italic_angle, is_fixed_pitch: is_fixed_pitch != 0 })
     }
 }
 
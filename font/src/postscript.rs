@@ -218,6 +218,12 @@ impl<'a> RefItem<'a> {
             _ => None
         }
     }
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            RefItem::Bool(b) => Some(b),
+            _ => None
+        }
+    }
     
 }
 
@@ -392,6 +398,12 @@ impl<'a> Deref for Input<'a> {
         self.data
     }
 }
+// Upper bounds for objects created by `array`, `dict` and `string`.
+// Fonts never come close, but a hostile program could otherwise request gigabytes.
+const MAX_ARRAY_LEN: usize = 1 << 16;
+const MAX_DICT_LEN: usize = 1 << 16;
+const MAX_STRING_LEN: usize = 1 << 24;
+
 pub struct Vm {
     dicts:      SlotMap<DictKey, (Dictionary, Mode)>,
     arrays:     SlotMap<ArrayKey, (Array, Mode)>,
@@ -466,11 +478,12 @@ impl Vm {
     fn pop_tuple<T>(&mut self) -> Result<T, FontError> where
         T: TupleElements<Element=Item>
     {
-        let range = self.stack.len() - T::N ..;
-        Ok(expect!(T::from_iter(self.stack.drain(range)), "not enough data on the stack"))
+        let start = expect!(self.stack.len().checked_sub(T::N), "not enough data on the stack");
+        Ok(expect!(T::from_iter(self.stack.drain(start ..)), "not enough data on the stack"))
     }
     fn pop(&mut self) -> Item {
-        self.stack.pop().expect("empty stack")
+        // an empty stack is an error in the program, which the operator will report as invalid argument
+        self.stack.pop().unwrap_or(Item::Null)
     }
     fn push(&mut self, item: Item) {
         self.stack.push(item);
@@ -655,6 +668,7 @@ impl Vm {
             Operator::Array => {
                 match self.pop() {
                     Item::Int(i) if i >= 0 => {
+                        limit!("array length", i as usize, MAX_ARRAY_LEN);
                         let key = self.make_array(vec![Item::Null; i as usize], Mode::all());
                         self.push(Item::Array(key));
                     }
@@ -759,6 +773,7 @@ impl Vm {
             Operator::Dict => {
                 match self.pop() {
                     Item::Int(n) if n >= 0 => {
+                        limit!("dict capacity", n as usize, MAX_DICT_LEN);
                         let dict = self.make_dict(Dictionary::with_capacity(n as usize), Mode::all());
                         self.push(Item::Dict(dict));
                     }
@@ -778,6 +793,7 @@ impl Vm {
             Operator::String => {
                 match self.pop() {
                     Item::Int(n) if n >= 0 => {
+                        limit!("string length", n as usize, MAX_STRING_LEN);
                         let string = self.make_string(vec![0; n as usize]);
                         self.push(Item::String(string));
                     },
@@ -175,7 +175,7 @@ fn fraction_i16(i: &[u8]) -> R<f32> {
 }
 
 pub fn parse_shapes(loca: &[u32], data: &[u8]) -> Result<Vec<Shape>, FontError> {
-    let mut shapes = Vec::with_capacity(loca.len() - 1);
+    let mut shapes = Vec::with_capacity(loca.len().saturating_sub(1));
     for (i, (start, end)) in loca.iter().cloned().tuple_windows().enumerate() {
         let slice = expect!(data.get(start as usize .. end as usize), "out of bounds");
         //debug!("gid {} : data[{} .. {}]", i, start, end);
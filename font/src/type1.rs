@@ -3,7 +3,7 @@ use crate::eexec::Decoder;
 use crate::parsers::parse;
 use crate::postscript::{RefItem, Vm};
 use crate::{
-    v, Context, Font, FontError, Glyph, GlyphId, HMetrics, IResultExt, Info, Name, State, TryIndex,
+    v, Context, Font, FontError, FontInfo, FontType, Glyph, GlyphId, HMetrics, IResultExt, Info, Name, State, TryIndex,
     Value, R,
 };
 use indexmap::IndexMap;
@@ -27,6 +27,8 @@ pub struct Type1Font {
     bbox: Option<RectF>,
     name: Name,
     info: Info,
+    italic_angle: Option<f32>,
+    fixed_pitch: bool,
 }
 impl Font for Type1Font {
     fn num_glyphs(&self) -> u32 {
@@ -106,15 +108,25 @@ impl Type1Font {
         debug!("FontInfo: {:?}", font_dict.get("FontInfo"));
 
         let postscript_name = font_dict.get_str("FontName").map(|s| s.into());
-        let full_name = font_dict
-            .get_dict("FontInfo")
+        let full_name = font_info
             .and_then(|d| d.get_str("FullName"))
             .map(|s| s.into());
+        let family = font_info
+            .and_then(|d| d.get_str("FamilyName"))
+            .map(|s| s.into());
         let name = Name {
             full_name,
             postscript_name,
+            family,
             ..Name::default()
         };
+        let italic_angle = font_info
+            .and_then(|d| d.get("ItalicAngle"))
+            .and_then(|i| i.as_f32());
+        let fixed_pitch = font_info
+            .and_then(|d| d.get("isFixedPitch"))
+            .and_then(|i| i.as_bool())
+            .unwrap_or(false);
         let weight = font_info
             .and_then(|d| d.get_str("Weight"))
             .and_then(|s| match s {
@@ -245,9 +257,24 @@ impl Type1Font {
             bbox,
             name,
             info: Info { weight },
+            italic_angle,
+            fixed_pitch,
         })
     }
 
+    pub fn font_info(&self, typ: FontType) -> FontInfo {
+        FontInfo {
+            name: self.name.clone(),
+            typ,
+            codepoints: vec![],
+            weight: self.info.weight,
+            italic_angle: self.italic_angle,
+            italic: matches!(self.italic_angle, Some(a) if a != 0.),
+            fixed_pitch: self.fixed_pitch,
+            panose: None,
+        }
+    }
+
     pub fn unicode_names(&self) -> impl Iterator<Item = (GlyphId, &str)> + '_ {
         self.unicode_map
             .iter()
@@ -2,7 +2,7 @@ use std::convert::TryInto;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::io::{Read, Cursor};
-use inflate::inflate_bytes_zlib;
+use inflate::DeflateDecoder;
 use brotli_decompressor::{Decompressor};
 
 use crate::{
@@ -23,6 +23,9 @@ use nom::{
 };
 
 
+// decompressed size of a single table. the length is read from the file, so it must be bounded.
+const MAX_TABLE_SIZE: usize = 1 << 26;
+
 pub fn parse_woff(data: &[u8]) -> Result<OpenTypeFont, FontError> {
     let (i, _) = tag(b"wOFF")(data)?;
     let (i, flavor) = take(4usize)(i)?;
@@ -41,7 +44,7 @@ pub fn parse_woff(data: &[u8]) -> Result<OpenTypeFont, FontError> {
     let (mut i, tables_dir) = count(woff_dir_entry, num_tables as usize)(i)?;
     
     if flavor == b"ttcf" {
-        unimplemented!()
+        error!("FontCollections are not implemented yet");
     }
     
     let mut tables = HashMap::with_capacity(num_tables as usize);
@@ -49,9 +52,9 @@ pub fn parse_woff(data: &[u8]) -> Result<OpenTypeFont, FontError> {
         debug!("{}", String::from_utf8_lossy(&entry.tag));
         let data = if entry.comp_length < entry.orig_length {
             let compressed_data = parse(&mut i, take((entry.comp_length as usize + 3) & !3))?;
-            let orig_data = inflate_bytes_zlib(slice!(compressed_data, .. entry.comp_length as usize)).expect("can't decompress data");
-            require_eq!(orig_data.len(), entry.orig_length as usize);
-            Cow::Owned(orig_data)
+            limit!("woff table size", entry.orig_length as usize, MAX_TABLE_SIZE);
+            let compressed_data = slice!(compressed_data, .. entry.comp_length as usize);
+            Cow::Owned(inflate_table(compressed_data, entry.orig_length as usize)?)
         } else {
             let chunk = parse(&mut i, take((entry.orig_length as usize + 3) & !3))?;
             Cow::Borrowed(slice!(chunk, .. entry.orig_length as usize))
@@ -63,6 +66,17 @@ pub fn parse_woff(data: &[u8]) -> Result<OpenTypeFont, FontError> {
     OpenTypeFont::from_tables(tables)
 }
 
+fn inflate_table(compressed_data: &[u8], orig_length: usize) -> Result<Vec<u8>, FontError> {
+    // one byte more than declared, so a table that inflates to more is noticed without inflating all of it
+    let mut decoder = DeflateDecoder::from_zlib(compressed_data).take(orig_length as u64 + 1);
+    let mut orig_data = Vec::with_capacity(orig_length);
+    if let Err(e) = decoder.read_to_end(&mut orig_data) {
+        error!("can't decompress data: {}", e);
+    }
+    require_eq!(orig_data.len(), orig_length);
+    Ok(orig_data)
+}
+
 #[derive(Debug)]
 struct WoffDirEntry {
     tag: [u8; 4],
@@ -108,11 +122,19 @@ pub fn parse_woff2(i: &[u8]) -> Result<OpenTypeFont, FontError> {
     let mut entries = HashMap::new();
     for (&tag, entry) in &entry_tables {
         debug!("tag: {:?} ({:?}) {:?}", tag, std::str::from_utf8(&tag), entry);
+        limit!("woff2 table size", entry.length as usize, MAX_TABLE_SIZE);
         let mut buf = vec![0; entry.length as usize];
-        decompressor.read_exact(&mut buf).expect("decode failed");
+        if let Err(e) = decompressor.read_exact(&mut buf) {
+            error!("decode failed: {:?}", e);
+        }
         entries.insert(tag, buf);
     }
-    require_eq!(decompressor.bytes().count(), 0);
+    // the stream has to end after the last table
+    match decompressor.read(&mut [0]) {
+        Ok(0) => {}
+        Ok(_) => error!("data after the last table"),
+        Err(e) => error!("decode failed: {:?}", e),
+    }
     
     let tables = Tables { entries };
     
@@ -441,3 +463,24 @@ bytes   xbits   ybits   dx      dy      xsign   ysign */
 5	16	16	0	0	-	+;
 5	16	16	0	0	+	+;
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // zlib stream of 1000 zero bytes
+    const ZEROS: &[u8] = &[
+        0x78, 0xda, 0x63, 0x60, 0x18, 0x05, 0xa3, 0x60, 0x14, 0x0c, 0x77, 0x00, 0x00, 0x03, 0xe8, 0x00, 0x01
+    ];
+
+    #[test]
+    fn inflate_declared_length() {
+        assert_eq!(inflate_table(ZEROS, 1000).unwrap(), vec![0; 1000]);
+        assert!(inflate_table(ZEROS, 1001).is_err());
+    }
+
+    #[test]
+    fn inflate_stops_after_declared_length() {
+        assert!(inflate_table(ZEROS, 100).is_err());
+    }
+}
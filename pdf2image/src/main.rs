@@ -0,0 +1,330 @@
+//! Render pages of PDF files to images.
+//!
+//! Pages are rendered in parallel. A page that fails is reported on stderr and the others are
+//! still written; the exit code tells whether everything worked.
+
+mod output;
+mod pages;
+
+use std::any::Any;
+use std::error::Error;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use argh::FromArgs;
+use image::RgbaImage;
+use pathfinder_geometry::{transform2d::Transform2F, vector::Vector2F};
+use pathfinder_renderer::scene::Scene;
+use pdf::file::{CachedFile, FileOptions};
+use pdf_render::{page_size, render_page_with, Cache, PageBox, RenderOptions, RenderStatus, SceneBackend};
+use rasterize::CpuRasterizer;
+use rayon::prelude::*;
+
+use output::{Format, OutputNames};
+use pages::PageRanges;
+
+/// the arguments are invalid, or the file or the standard fonts can't be read
+const EXIT_ERROR: i32 = 1;
+/// at least one page could not be rendered or written
+const EXIT_PAGES_FAILED: i32 = 2;
+
+#[derive(FromArgs)]
+/// Render pages of a PDF file to images.
+#[argh(
+    example = "Render the first three pages at 300 DPI into page-1.png, page-2.png and page-3.png:\n$ {command_name} --pages 1-3 --dpi 300 input.pdf page.png",
+    note = "Fonts that are not embedded are loaded from the directory in STANDARD_FONTS (default: fonts/).",
+    error_code(1, "The arguments are invalid, or the file or the standard fonts can't be read."),
+    error_code(2, "At least one page could not be rendered or written.")
+)]
+struct Options {
+    /// pages to render, starting at 1, e.g. `1-3,7,10-` (default: all)
+    #[argh(option, short = 'p')]
+    pages: Option<PageRanges>,
+
+    /// resolution in pixels per inch (default: 150)
+    #[argh(option, short = 'r', default = "150.")]
+    dpi: f32,
+
+    /// width of the images in pixels, instead of --dpi. With --height, pages fit into both.
+    #[argh(option)]
+    width: Option<u32>,
+
+    /// height of the images in pixels, instead of --dpi. With --width, pages fit into both.
+    #[argh(option)]
+    height: Option<u32>,
+
+    /// image format: png, jpeg, webp, ppm or tiff (default: from the output name, or png)
+    #[argh(option, short = 'f')]
+    format: Option<Format>,
+
+    /// JPEG quality from 1 to 100 (default: 90)
+    #[argh(option, default = "90")]
+    quality: u8,
+
+    /// keep the background transparent instead of white (png, webp and tiff only)
+    #[argh(switch)]
+    transparent: bool,
+
+    /// page box to render: media, crop, bleed, trim or art (default: crop)
+    #[argh(option, from_str_fn(parse_page_box), default = "PageBox::CropBox")]
+    page_box: PageBox,
+
+    /// rasterizer: cpu, or gpu which needs EGL (default: cpu)
+    #[argh(option, default = "Backend::Cpu")]
+    backend: Backend,
+
+    /// number of pages rendered at the same time (default: one per CPU)
+    #[argh(option, short = 'j', default = "0")]
+    jobs: usize,
+
+    /// stop rendering a page after this many seconds; the partial page is written and reported
+    #[argh(option)]
+    timeout: Option<f32>,
+
+    /// input PDF file
+    #[argh(positional)]
+    pdf: PathBuf,
+
+    /// output file; `{}` is replaced by the page number, otherwise it is added before the extension
+    /// when rendering several pages (default: the name of the input with the extension of the format)
+    #[argh(positional)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Backend {
+    Cpu,
+    Gpu,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "cpu" => Ok(Backend::Cpu),
+            "gpu" if cfg!(feature = "gpu") => Ok(Backend::Gpu),
+            "gpu" => Err("this build has no GPU support (feature `gpu`)".into()),
+            _ => Err(format!("unknown backend {:?}, expected cpu or gpu", s)),
+        }
+    }
+}
+
+fn parse_page_box(s: &str) -> Result<PageBox, String> {
+    match s {
+        "media" => Ok(PageBox::MediaBox),
+        "crop" => Ok(PageBox::CropBox),
+        "bleed" => Ok(PageBox::BleedBox),
+        "trim" => Ok(PageBox::TrimBox),
+        "art" => Ok(PageBox::ArtBox),
+        _ => Err(format!("unknown page box {:?}, expected media, crop, bleed, trim or art", s)),
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let opt: Options = argh::from_env();
+
+    // panics are reported per page
+    std::panic::set_hook(Box::new(|info| log::debug!("{}", info)));
+
+    let code = match run(&opt) {
+        Ok(0) => 0,
+        Ok(failed) => {
+            eprintln!("{} pages failed", failed);
+            EXIT_PAGES_FAILED
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            EXIT_ERROR
+        }
+    };
+    std::process::exit(code);
+}
+
+/// Render the pages and return the number of pages that failed.
+fn run(opt: &Options) -> Result<usize, Box<dyn Error>> {
+    if !(opt.dpi > 0.) {
+        return Err("--dpi has to be positive".into());
+    }
+    if opt.width == Some(0) || opt.height == Some(0) {
+        return Err("--width and --height have to be positive".into());
+    }
+    if !(1..=100).contains(&opt.quality) {
+        return Err("--quality has to be between 1 and 100".into());
+    }
+    if opt.timeout.map_or(false, |seconds| Duration::try_from_secs_f32(seconds).is_err()) {
+        return Err("--timeout has to be a number of seconds that is not negative".into());
+    }
+    let format = opt.format
+        .or_else(|| opt.output.as_deref().and_then(Format::from_path))
+        .unwrap_or(Format::Png);
+    if opt.transparent && !format.has_alpha() {
+        return Err(format!("{:?} images can't be transparent", format).into());
+    }
+
+    let file = FileOptions::cached().open(&opt.pdf)
+        .map_err(|e| format!("can't open {}: {}", opt.pdf.display(), e))?;
+    let page_count = file.num_pages();
+    let pages = opt.pages.clone().unwrap_or_else(PageRanges::all).resolve(page_count)?;
+
+    let template = match opt.output {
+        Some(ref path) => path.clone(),
+        None => OutputNames::default_template(&opt.pdf, format),
+    };
+    let cache = Cache::new().map_err(|e| format!("can't load the standard fonts: {}", e))?;
+    let job = Job {
+        file: &file,
+        cache: &cache,
+        opt,
+        format,
+        names: OutputNames::new(template, page_count, pages.len() > 1),
+    };
+
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(opt.jobs).build()?;
+    // bounded, so that finished scenes don't pile up while the GPU is busy
+    let (sender, receiver) = mpsc::sync_channel(pool.current_num_threads());
+    let mut failed = 0;
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            pool.install(|| {
+                pages.par_iter().for_each_with(sender, |sender, &page_nr| {
+                    let _ = sender.send((page_nr, job.page(page_nr)));
+                });
+            })
+        });
+
+        let mut gpu = Gpu::default();
+        for (page_nr, result) in receiver {
+            let result = result.and_then(|rendered| job.finish(page_nr, rendered, &mut gpu));
+            if let Err(e) = result {
+                eprintln!("page {}: {}", page_nr + 1, e);
+                failed += 1;
+            }
+        }
+    });
+    Ok(failed)
+}
+
+/// A page rendered on a worker thread.
+enum Rendered {
+    /// the image was written
+    Written(RenderStatus),
+    /// still has to be rasterized on the GPU
+    Scene(Scene, RenderStatus),
+}
+
+struct Job<'a> {
+    file: &'a CachedFile<Vec<u8>>,
+    cache: &'a Cache,
+    opt: &'a Options,
+    format: Format,
+    names: OutputNames,
+}
+
+impl Job<'_> {
+    /// Render page `page_nr` (starting at 0) on a worker thread.
+    fn page(&self, page_nr: u32) -> Result<Rendered, String> {
+        catch_panic(|| self.render(page_nr).map_err(|e| e.to_string()))
+    }
+
+    fn render(&self, page_nr: u32) -> Result<Rendered, Box<dyn Error>> {
+        let page = self.file.get_page(page_nr)?;
+        let resolver = self.file.resolver();
+
+        let mut options = RenderOptions::default().page_box(self.opt.page_box);
+        if let Some(seconds) = self.opt.timeout {
+            options = options.timeout(Duration::from_secs_f32(seconds));
+        }
+        let scale = self.scale(page_size(&page, self.opt.page_box));
+        let mut backend = SceneBackend::new(self.cache);
+        if self.opt.transparent {
+            backend.set_background(None);
+        }
+        let result = render_page_with(&mut backend, &resolver, &page, Transform2F::from_scale(scale), &options)?;
+        let scene = backend.finish();
+
+        match self.opt.backend {
+            Backend::Cpu => {
+                let image = CpuRasterizer::new().rasterize(scene, None);
+                self.save(page_nr, image)?;
+                Ok(Rendered::Written(result.status))
+            }
+            Backend::Gpu => Ok(Rendered::Scene(scene, result.status)),
+        }
+    }
+
+    /// Finish a page on the main thread.
+    fn finish(&self, page_nr: u32, rendered: Rendered, gpu: &mut Gpu) -> Result<(), String> {
+        let status = match rendered {
+            Rendered::Written(status) => status,
+            Rendered::Scene(scene, status) => {
+                catch_panic(|| {
+                    let image = gpu.rasterize(scene);
+                    self.save(page_nr, image).map_err(|e| e.to_string())
+                })?;
+                status
+            }
+        };
+        match status {
+            RenderStatus::Complete => Ok(()),
+            RenderStatus::Cancelled => Err("cancelled, the image is incomplete".into()),
+            RenderStatus::Truncated(reason) => Err(format!("stopped early ({:?}), the image is incomplete", reason)),
+        }
+    }
+
+    /// Pixels per millimeter (the unit of `page_size`).
+    fn scale(&self, size: Vector2F) -> f32 {
+        let fit = |pixels: u32, length: f32| pixels as f32 / length;
+        let scale = match (self.opt.width, self.opt.height) {
+            (None, None) => return self.opt.dpi / 25.4,
+            (Some(width), None) => fit(width, size.x()),
+            (None, Some(height)) => fit(height, size.y()),
+            (Some(width), Some(height)) => fit(width, size.x()).min(fit(height, size.y())),
+        };
+        // the rasterizers round the size up, so a rounding error must not add a pixel
+        scale * (1. - 1e-5)
+    }
+
+    fn save(&self, page_nr: u32, image: RgbaImage) -> Result<(), String> {
+        let path = self.names.path(page_nr + 1);
+        self.format.save(image, &path, self.opt.quality)
+            .map_err(|e| format!("can't write {}: {}", path.display(), e))?;
+        log::info!("page {} written to {}", page_nr + 1, path.display());
+        Ok(())
+    }
+}
+
+/// Run `f`, turning a panic into an error.
+fn catch_panic<T>(f: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|panic| Err(format!("crashed: {}", panic_message(&*panic))))
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    match panic.downcast_ref::<&str>() {
+        Some(s) => s,
+        None => panic.downcast_ref::<String>().map(|s| s.as_str()).unwrap_or("unknown error"),
+    }
+}
+
+/// The GPU rasterizer lives on the main thread, as its EGL context can't be shared between threads.
+#[derive(Default)]
+struct Gpu {
+    #[cfg(feature = "gpu")]
+    rasterizer: Option<rasterize::Rasterizer>,
+}
+
+impl Gpu {
+    #[cfg(feature = "gpu")]
+    fn rasterize(&mut self, scene: Scene) -> RgbaImage {
+        self.rasterizer.get_or_insert_with(rasterize::Rasterizer::new).rasterize(scene, None)
+    }
+
+    #[cfg(not(feature = "gpu"))]
+    fn rasterize(&mut self, _scene: Scene) -> RgbaImage {
+        unreachable!("`--backend gpu` is rejected without the gpu feature")
+    }
+}
@@ -0,0 +1,158 @@
+//! Image formats and file names of the rendered pages.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::pnm::{PnmEncoder, PnmSubtype, SampleEncoding};
+use image::{DynamicImage, ExtendedColorType, ImageEncoder, ImageFormat, ImageResult, RgbImage, RgbaImage};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Png,
+    Jpeg,
+    /// lossless
+    WebP,
+    /// binary PPM (P6)
+    Ppm,
+    Tiff,
+}
+
+impl Format {
+    /// The format for the extension of `path`, if it is known.
+    pub fn from_path(path: &Path) -> Option<Format> {
+        path.extension()?.to_str()?.to_ascii_lowercase().parse().ok()
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Jpeg => "jpg",
+            Format::WebP => "webp",
+            Format::Ppm => "ppm",
+            Format::Tiff => "tiff",
+        }
+    }
+
+    pub fn has_alpha(self) -> bool {
+        matches!(self, Format::Png | Format::WebP | Format::Tiff)
+    }
+
+    /// Write `image` to `path`. `quality` (1 to 100) is used for JPEG.
+    pub fn save(self, image: RgbaImage, path: &Path, quality: u8) -> ImageResult<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let result = match self {
+            Format::Png => image.write_to(&mut writer, ImageFormat::Png),
+            Format::WebP => image.write_to(&mut writer, ImageFormat::WebP),
+            Format::Tiff => image.write_to(&mut writer, ImageFormat::Tiff),
+            Format::Jpeg => JpegEncoder::new_with_quality(&mut writer, quality).encode_image(&rgb(image)),
+            Format::Ppm => {
+                let image = rgb(image);
+                PnmEncoder::new(&mut writer)
+                    .with_subtype(PnmSubtype::Pixmap(SampleEncoding::Binary))
+                    .write_image(image.as_raw(), image.width(), image.height(), ExtendedColorType::Rgb8)
+            }
+        };
+        result?;
+        // errors on drop would go unnoticed
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "png" => Ok(Format::Png),
+            "jpg" | "jpeg" => Ok(Format::Jpeg),
+            "webp" => Ok(Format::WebP),
+            "ppm" | "pnm" => Ok(Format::Ppm),
+            "tif" | "tiff" => Ok(Format::Tiff),
+            _ => Err(format!("unknown format {:?}, expected png, jpeg, webp, ppm or tiff", s)),
+        }
+    }
+}
+
+// the page is drawn on white unless the background is transparent, which is rejected for these formats
+fn rgb(image: RgbaImage) -> RgbImage {
+    DynamicImage::ImageRgba8(image).into_rgb8()
+}
+
+/// File names for the pages, from a template given by the user.
+///
+/// `{}` in the file name is replaced by the page number. Without it, the page number is added
+/// before the extension if there are several pages. Page numbers are padded with zeros to the
+/// length of the last page number of the document, so the files sort in page order.
+pub struct OutputNames {
+    template: PathBuf,
+    digits: usize,
+    several: bool,
+}
+
+impl OutputNames {
+    pub fn new(template: PathBuf, page_count: u32, several: bool) -> Self {
+        OutputNames {
+            template,
+            digits: page_count.to_string().len(),
+            several,
+        }
+    }
+
+    /// The name for `input` rendered into `format`, in the current directory.
+    pub fn default_template(input: &Path, format: Format) -> PathBuf {
+        let stem = input.file_stem().unwrap_or(input.as_os_str());
+        Path::new(stem).with_extension(format.extension())
+    }
+
+    /// The path of page `page_nr`, starting at 1.
+    pub fn path(&self, page_nr: u32) -> PathBuf {
+        let number = format!("{:0width$}", page_nr, width = self.digits);
+        let name = match self.template.file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => return self.template.join(number),
+        };
+        let name = if name.contains("{}") {
+            name.replace("{}", &number)
+        } else if self.several {
+            match name.rsplit_once('.') {
+                Some((stem, extension)) if !stem.is_empty() => format!("{}-{}.{}", stem, number, extension),
+                _ => format!("{}-{}", name, number),
+            }
+        } else {
+            return self.template.clone();
+        };
+        self.template.with_file_name(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        let names = OutputNames::new("out/page-{}.png".into(), 120, true);
+        assert_eq!(names.path(7), Path::new("out/page-007.png"));
+
+        let names = OutputNames::new("out/doc.png".into(), 12, true);
+        assert_eq!(names.path(3), Path::new("out/doc-03.png"));
+
+        let names = OutputNames::new("doc.png".into(), 12, false);
+        assert_eq!(names.path(3), Path::new("doc.png"));
+
+        let template = OutputNames::default_template(Path::new("in/report.pdf"), Format::Jpeg);
+        assert_eq!(template, Path::new("report.jpg"));
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(Format::from_path(Path::new("a/b.JPEG")), Some(Format::Jpeg));
+        assert_eq!(Format::from_path(Path::new("a/b.tif")), Some(Format::Tiff));
+        assert_eq!(Format::from_path(Path::new("a/b")), None);
+        assert!("gif".parse::<Format>().is_err());
+    }
+}
@@ -0,0 +1,91 @@
+//! Page ranges like `1-3,7,10-`.
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// Inclusive ranges of page numbers, starting at 1. An open end means the first or last page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRanges(Vec<(Option<u32>, Option<u32>)>);
+
+impl PageRanges {
+    /// every page
+    pub fn all() -> Self {
+        PageRanges(vec![(None, None)])
+    }
+
+    /// Indices (starting at 0) of the selected pages of a document with `count` pages,
+    /// in the order given and without duplicates.
+    pub fn resolve(&self, count: u32) -> Result<Vec<u32>, String> {
+        let mut pages = vec![];
+        let mut seen = HashSet::new();
+        for &(start, end) in &self.0 {
+            let start = start.unwrap_or(1);
+            let end = end.unwrap_or(count);
+            if start > count || end > count {
+                return Err(format!("page {} does not exist, the document has {} pages", start.max(end), count));
+            }
+            for page in start..=end {
+                if seen.insert(page) {
+                    pages.push(page - 1);
+                }
+            }
+        }
+        Ok(pages)
+    }
+}
+
+impl FromStr for PageRanges {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let number = |s: &str| -> Result<Option<u32>, String> {
+            let s = s.trim();
+            if s.is_empty() {
+                return Ok(None);
+            }
+            match s.parse() {
+                Ok(0) | Err(_) => Err(format!("invalid page number {:?}", s)),
+                Ok(n) => Ok(Some(n)),
+            }
+        };
+        let mut ranges = vec![];
+        for part in s.split(',') {
+            let range = match part.split_once('-') {
+                Some((start, end)) => (number(start)?, number(end)?),
+                None => match number(part)? {
+                    Some(n) => (Some(n), Some(n)),
+                    None => return Err(format!("empty page range in {:?}", s)),
+                },
+            };
+            if let (Some(start), Some(end)) = range {
+                if start > end {
+                    return Err(format!("page range {:?} ends before it starts", part.trim()));
+                }
+            }
+            ranges.push(range);
+        }
+        Ok(PageRanges(ranges))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        let ranges: PageRanges = "3-4, 1,7-,2-3".parse().unwrap();
+        assert_eq!(ranges.resolve(8).unwrap(), [2, 3, 0, 6, 7, 1]);
+        assert_eq!("-2".parse::<PageRanges>().unwrap().resolve(5).unwrap(), [0, 1]);
+        assert_eq!(PageRanges::all().resolve(3).unwrap(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!("0".parse::<PageRanges>().is_err());
+        assert!("3-2".parse::<PageRanges>().is_err());
+        assert!("1,,2".parse::<PageRanges>().is_err());
+        assert!("a-b".parse::<PageRanges>().is_err());
+        assert!("4".parse::<PageRanges>().unwrap().resolve(3).is_err());
+    }
+}
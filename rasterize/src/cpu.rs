@@ -0,0 +1,707 @@
+//! A rasterizer that runs on the CPU.
+//!
+//! Paths are flattened into lines and accumulated as signed areas into a coverage buffer
+//! covering their bounds, one path at a time. The coverage, limited by the clip mask, is then
+//! used to composite the paint of the path onto the image with its blend mode.
+//! The image is kept as premultiplied floats until the end.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use pathfinder_color::{ColorF, ColorU};
+use pathfinder_content::{
+    effects::BlendMode,
+    fill::FillRule,
+    outline::{ContourIterFlags, Outline},
+    pattern::{Image, PatternSource},
+    segment::SegmentKind,
+};
+use pathfinder_geometry::{
+    rect::RectI,
+    transform2d::Transform2F,
+    vector::{Vector2F, Vector2I},
+};
+use pathfinder_renderer::{
+    paint::Paint,
+    scene::{ClipPathId, DrawPathId, Scene},
+};
+use image::RgbaImage;
+
+// maximum distance of the flattened lines from the curve, in pixels
+const TOLERANCE: f32 = 0.2;
+const MAX_CURVE_LINES: u32 = 256;
+
+type Rgba = [f32; 4];
+
+/// Renders scenes on the CPU. Works like `Rasterizer`, but does not need a GPU, EGL or a display.
+#[derive(Default)]
+pub struct CpuRasterizer {}
+
+impl CpuRasterizer {
+    pub fn new() -> Self {
+        CpuRasterizer {}
+    }
+
+    /// Render the view box of `scene`, which becomes the size of the image (rounded up).
+    /// Without a `background`, the image starts out transparent.
+    pub fn rasterize(&mut self, scene: Scene, background: Option<ColorF>) -> RgbaImage {
+        let view_box = scene.view_box();
+        let size = view_box.size().ceil().to_i32();
+        let size = Vector2I::new(size.x().max(0), size.y().max(0));
+        let transform = Transform2F::from_translation(-view_box.origin());
+
+        let background = match background {
+            Some(c) => premultiply([c.r(), c.g(), c.b(), c.a()]),
+            None => [0.; 4],
+        };
+        let mut canvas = Canvas {
+            size,
+            pixels: vec![background; (size.x() * size.y()) as usize],
+        };
+        let mut clips = ClipMasks {
+            scene: &scene,
+            transform,
+            size,
+            masks: HashMap::new(),
+        };
+
+        for idx in 0..scene.draw_path_count() {
+            let draw_path = scene.get_draw_path(DrawPathId(idx));
+            let mask = match fill(draw_path.outline(), draw_path.fill_rule(), transform, size) {
+                Some(mask) => mask,
+                None => continue,
+            };
+            let mask = match draw_path.clip_path() {
+                Some(clip) => match clips.get(clip) {
+                    Some(clip) => match mask.intersect(&clip) {
+                        Some(mask) => mask,
+                        None => continue,
+                    },
+                    None => continue,
+                },
+                None => mask,
+            };
+            let source = Source::new(scene.get_paint(draw_path.paint()), transform);
+            canvas.composite(&mask, &source, draw_path.blend_mode());
+        }
+
+        canvas.into_image()
+    }
+}
+
+/// The image being drawn, premultiplied.
+struct Canvas {
+    size: Vector2I,
+    pixels: Vec<Rgba>,
+}
+
+impl Canvas {
+    fn composite(&mut self, mask: &Mask, source: &Source, mode: BlendMode) {
+        let width = mask.rect.width() as usize;
+        for (row_idx, row) in mask.coverage.chunks(width).enumerate() {
+            let y = mask.rect.min_y() + row_idx as i32;
+            let start = (y * self.size.x() + mask.rect.min_x()) as usize;
+            let pixels = &mut self.pixels[start..start + width];
+            for (col, (&cov, dst)) in row.iter().zip(pixels.iter_mut()).enumerate() {
+                if cov <= 0. {
+                    continue;
+                }
+                let x = mask.rect.min_x() + col as i32;
+                let src = source.sample(x, y);
+                let out = blend(mode, src, *dst);
+                for (d, o) in dst.iter_mut().zip(out) {
+                    *d += (o - *d) * cov;
+                }
+            }
+        }
+    }
+
+    fn into_image(self) -> RgbaImage {
+        let mut data = Vec::with_capacity(self.pixels.len() * 4);
+        for p in self.pixels {
+            let a = p[3].clamp(0., 1.);
+            let unmul = |c: f32| if a > 0. { (c / a).clamp(0., 1.) } else { 0. };
+            let byte = |v: f32| (v * 255. + 0.5) as u8;
+            data.extend_from_slice(&[byte(unmul(p[0])), byte(unmul(p[1])), byte(unmul(p[2])), byte(a)]);
+        }
+        RgbaImage::from_raw(self.size.x() as u32, self.size.y() as u32, data).unwrap()
+    }
+}
+
+/// Coverage (0 to 1) of the pixels in `rect`. Pixels outside of it are not covered.
+struct Mask {
+    rect: RectI,
+    coverage: Vec<f32>,
+}
+
+impl Mask {
+    fn get(&self, p: Vector2I) -> f32 {
+        if !self.rect.contains_point(p) {
+            return 0.;
+        }
+        let offset = p - self.rect.origin();
+        self.coverage[(offset.y() * self.rect.width() + offset.x()) as usize]
+    }
+
+    fn intersect(&self, other: &Mask) -> Option<Mask> {
+        let rect = self.rect.intersection(other.rect)?;
+        let mut coverage = Vec::with_capacity((rect.width() * rect.height()) as usize);
+        for y in rect.min_y()..rect.max_y() {
+            for x in rect.min_x()..rect.max_x() {
+                let p = Vector2I::new(x, y);
+                coverage.push(self.get(p) * other.get(p));
+            }
+        }
+        Some(Mask { rect, coverage })
+    }
+}
+
+/// Clip masks of a scene, computed when first used.
+struct ClipMasks<'a> {
+    scene: &'a Scene,
+    transform: Transform2F,
+    size: Vector2I,
+    // `None` if nothing is visible through the clip path
+    masks: HashMap<u32, Option<Rc<Mask>>>,
+}
+
+impl ClipMasks<'_> {
+    fn get(&mut self, id: ClipPathId) -> Option<Rc<Mask>> {
+        if let Some(mask) = self.masks.get(&id.0) {
+            return mask.clone();
+        }
+        let clip = self.scene.get_clip_path(id);
+        let mask = fill(clip.outline(), clip.fill_rule(), self.transform, self.size);
+        let mask = match (mask, clip.clip_path()) {
+            (Some(mask), Some(parent)) => self.get(parent).and_then(|parent| mask.intersect(&parent)),
+            (mask, None) => mask,
+            (None, Some(_)) => None,
+        };
+        let mask = mask.map(Rc::new);
+        self.masks.insert(id.0, mask.clone());
+        mask
+    }
+}
+
+/// Coverage of `outline` after `transform` within an image of `size`.
+fn fill(outline: &Outline, fill_rule: FillRule, transform: Transform2F, size: Vector2I) -> Option<Mask> {
+    let bounds = (transform * outline.bounds()).round_out().to_i32();
+    let rect = bounds.intersection(RectI::new(Vector2I::zero(), size))?;
+    if rect.width() <= 0 || rect.height() <= 0 {
+        return None;
+    }
+
+    let mut acc = Accumulator::new(rect.size());
+    let transform = Transform2F::from_translation(-rect.origin().to_f32()) * transform;
+    for contour in outline.contours() {
+        let mut segments = contour.iter(ContourIterFlags::IGNORE_CLOSE_SEGMENT).peekable();
+        let start = match segments.peek() {
+            Some(segment) => transform * segment.baseline.from(),
+            None => continue,
+        };
+        let mut last = start;
+        for segment in segments {
+            let from = transform * segment.baseline.from();
+            let to = transform * segment.baseline.to();
+            match segment.kind {
+                SegmentKind::None => continue,
+                SegmentKind::Line => acc.line(from, to),
+                SegmentKind::Quadratic => {
+                    let ctrl = transform * segment.ctrl.from();
+                    let c0 = from + (ctrl - from) * (2. / 3.);
+                    let c1 = to + (ctrl - to) * (2. / 3.);
+                    acc.cubic(from, c0, c1, to);
+                }
+                SegmentKind::Cubic => {
+                    acc.cubic(from, transform * segment.ctrl.from(), transform * segment.ctrl.to(), to);
+                }
+            }
+            last = to;
+        }
+        // filling always closes the contour
+        acc.line(last, start);
+    }
+    Some(acc.into_mask(rect, fill_rule))
+}
+
+/// Signed areas of the lines, summed along each row to get the winding number.
+struct Accumulator {
+    width: usize,
+    height: usize,
+    // two extra columns per row, as lines on the right edge spill into them
+    stride: usize,
+    area: Vec<f32>,
+}
+
+impl Accumulator {
+    fn new(size: Vector2I) -> Self {
+        let (width, height) = (size.x() as usize, size.y() as usize);
+        Accumulator {
+            width,
+            height,
+            stride: width + 2,
+            area: vec![0.; (width + 2) * height],
+        }
+    }
+
+    fn cubic(&mut self, p0: Vector2F, c0: Vector2F, c1: Vector2F, p1: Vector2F) {
+        // the second differences bound the distance of the lines from the curve
+        let dd = (p0 - c0 * 2. + c1).length().max((c0 - c1 * 2. + p1).length());
+        let n = ((0.75 * dd / TOLERANCE).sqrt().ceil() as u32).clamp(1, MAX_CURVE_LINES);
+        let mut last = p0;
+        for i in 1..=n {
+            let t = i as f32 / n as f32;
+            let s = 1. - t;
+            let p = p0 * (s * s * s) + c0 * (3. * s * s * t) + c1 * (3. * s * t * t) + p1 * (t * t * t);
+            self.line(last, p);
+            last = p;
+        }
+    }
+
+    /// Parts of the line left or right of the buffer still change the winding number,
+    /// so they are moved onto the edge.
+    fn line(&mut self, a: Vector2F, b: Vector2F) {
+        for edge in [0., self.width as f32] {
+            if (a.x() - edge) * (b.x() - edge) < 0. {
+                let t = (edge - a.x()) / (b.x() - a.x());
+                let m = Vector2F::new(edge, a.y() + (b.y() - a.y()) * t);
+                self.line(a, m);
+                self.line(m, b);
+                return;
+            }
+        }
+        let clamp = |p: Vector2F| Vector2F::new(p.x().clamp(0., self.width as f32), p.y());
+        self.segment(clamp(a), clamp(b));
+    }
+
+    fn segment(&mut self, p0: Vector2F, p1: Vector2F) {
+        if p0.y() == p1.y() {
+            return;
+        }
+        let (dir, p0, p1) = if p0.y() < p1.y() { (1.0, p0, p1) } else { (-1.0, p1, p0) };
+        let y_start = p0.y().max(0.);
+        let y_end = p1.y().min(self.height as f32);
+        if y_start >= y_end {
+            return;
+        }
+        let dxdy = (p1.x() - p0.x()) / (p1.y() - p0.y());
+        let max_x = self.width as f32;
+        let mut x = p0.x() + (y_start - p0.y()) * dxdy;
+
+        for y in y_start as usize..y_end.ceil() as usize {
+            let row = &mut self.area[y * self.stride..][..self.stride];
+            let dy = ((y + 1) as f32).min(y_end) - (y as f32).max(y_start);
+            let x_next = x + dxdy * dy;
+            let d = dy * dir;
+            let (x0, x1) = if x < x_next { (x, x_next) } else { (x_next, x) };
+            let (x0, x1) = (x0.clamp(0., max_x), x1.clamp(0., max_x));
+            let x0_floor = x0.floor();
+            let x0i = x0_floor as usize;
+            let x1_ceil = x1.ceil();
+            let x1i = x1_ceil as usize;
+
+            if x1i <= x0i + 1 {
+                // within one pixel
+                let xm = 0.5 * (x0 + x1) - x0_floor;
+                row[x0i] += d - d * xm;
+                row[x0i + 1] += d * xm;
+            } else {
+                let s = (x1 - x0).recip();
+                let x0f = x0 - x0_floor;
+                let a0 = 0.5 * s * (1. - x0f) * (1. - x0f);
+                let x1f = x1 - x1_ceil + 1.;
+                let am = 0.5 * s * x1f * x1f;
+                row[x0i] += d * a0;
+                if x1i == x0i + 2 {
+                    row[x0i + 1] += d * (1. - a0 - am);
+                } else {
+                    let a1 = s * (1.5 - x0f);
+                    row[x0i + 1] += d * (a1 - a0);
+                    for cell in &mut row[x0i + 2..x1i - 1] {
+                        *cell += d * s;
+                    }
+                    let a2 = a1 + (x1i - x0i - 3) as f32 * s;
+                    row[x1i - 1] += d * (1. - a2 - am);
+                }
+                row[x1i] += d * am;
+            }
+            x = x_next;
+        }
+    }
+
+    fn into_mask(self, rect: RectI, fill_rule: FillRule) -> Mask {
+        let mut coverage = Vec::with_capacity(self.width * self.height);
+        for row in self.area.chunks(self.stride) {
+            let mut winding = 0.;
+            for &area in &row[..self.width] {
+                winding += area;
+                let w = winding.abs();
+                coverage.push(match fill_rule {
+                    FillRule::Winding => w.min(1.),
+                    FillRule::EvenOdd => {
+                        let w = w % 2.;
+                        if w > 1. { 2. - w } else { w }
+                    }
+                });
+            }
+        }
+        Mask { rect, coverage }
+    }
+}
+
+/// Where the color of a pixel comes from.
+enum Source<'a> {
+    Solid(Rgba),
+    Image {
+        image: &'a Image,
+        // from image pixels to output pixels
+        inverse: Transform2F,
+        tint: Rgba,
+        smooth: bool,
+        repeat: (bool, bool),
+    },
+}
+
+impl<'a> Source<'a> {
+    fn new(paint: &'a Paint, transform: Transform2F) -> Self {
+        let base = color(paint.base_color());
+        match paint.pattern() {
+            Some(pattern) => match *pattern.source() {
+                PatternSource::Image(ref image) => Source::Image {
+                    image,
+                    inverse: (transform * pattern.transform()).inverse(),
+                    tint: base,
+                    smooth: pattern.smoothing_enabled(),
+                    repeat: (pattern.repeat_x(), pattern.repeat_y()),
+                },
+                // render targets are only available on the GPU
+                _ => Source::Solid(premultiply(base)),
+            },
+            // gradients are drawn with their base color
+            None => Source::Solid(premultiply(base)),
+        }
+    }
+
+    /// Premultiplied color at the center of pixel `(x, y)`.
+    fn sample(&self, x: i32, y: i32) -> Rgba {
+        match *self {
+            Source::Solid(c) => c,
+            Source::Image { image, inverse, tint, smooth, repeat } => {
+                let p = inverse * Vector2F::new(x as f32 + 0.5, y as f32 + 0.5);
+                let c = if smooth {
+                    bilinear(image, p, repeat)
+                } else {
+                    texel(image, p.floor().to_i32(), repeat, false)
+                };
+                let c = [c[0] * tint[0], c[1] * tint[1], c[2] * tint[2], c[3] * tint[3]];
+                premultiply(c)
+            }
+        }
+    }
+}
+
+/// The pixel at `p`, straight alpha. Outside of the image, pixels are transparent
+/// unless it repeats or `clamp` is set.
+fn texel(image: &Image, p: Vector2I, repeat: (bool, bool), clamp: bool) -> Rgba {
+    let size = image.size();
+    let coord = |v: i32, n: i32, repeat: bool| {
+        if repeat {
+            Some(v.rem_euclid(n))
+        } else if (0..n).contains(&v) {
+            Some(v)
+        } else if clamp {
+            Some(v.clamp(0, n - 1))
+        } else {
+            None
+        }
+    };
+    match (coord(p.x(), size.x(), repeat.0), coord(p.y(), size.y(), repeat.1)) {
+        (Some(x), Some(y)) => color(image.pixels()[(y * size.x() + x) as usize]),
+        _ => [0.; 4],
+    }
+}
+
+fn bilinear(image: &Image, p: Vector2F, repeat: (bool, bool)) -> Rgba {
+    let size = image.size().to_f32();
+    let outside = |v: f32, n: f32, repeat: bool| !repeat && (v < 0. || v >= n);
+    if outside(p.x(), size.x(), repeat.0) || outside(p.y(), size.y(), repeat.1) {
+        return [0.; 4];
+    }
+    // interpolate between the centers of the four nearest pixels, in premultiplied space
+    let q = p - Vector2F::splat(0.5);
+    let base = q.floor();
+    let f = q - base;
+    let base = base.to_i32();
+    let px = |dx: i32, dy: i32| premultiply(texel(image, base + Vector2I::new(dx, dy), repeat, true));
+    let (c00, c10, c01, c11) = (px(0, 0), px(1, 0), px(0, 1), px(1, 1));
+    let out: Rgba = std::array::from_fn(|c| {
+        let top = c00[c] + (c10[c] - c00[c]) * f.x();
+        let bottom = c01[c] + (c11[c] - c01[c]) * f.x();
+        top + (bottom - top) * f.y()
+    });
+    // back to straight alpha; the caller applies the tint before premultiplying again
+    let a = out[3];
+    if a > 0. {
+        [out[0] / a, out[1] / a, out[2] / a, a]
+    } else {
+        [0.; 4]
+    }
+}
+
+fn color(c: ColorU) -> Rgba {
+    [c.r as f32 / 255., c.g as f32 / 255., c.b as f32 / 255., c.a as f32 / 255.]
+}
+
+fn premultiply(c: Rgba) -> Rgba {
+    [c[0] * c[3], c[1] * c[3], c[2] * c[3], c[3]]
+}
+
+/// Composite premultiplied `src` onto premultiplied `dst`.
+fn blend(mode: BlendMode, s: Rgba, d: Rgba) -> Rgba {
+    let (sa, da) = (s[3], d[3]);
+    let porter_duff = |fs: f32, fd: f32| [
+        s[0] * fs + d[0] * fd,
+        s[1] * fs + d[1] * fd,
+        s[2] * fs + d[2] * fd,
+        sa * fs + da * fd,
+    ];
+    match mode {
+        BlendMode::Clear => [0.; 4],
+        BlendMode::Copy => s,
+        BlendMode::SrcIn => porter_duff(da, 0.),
+        BlendMode::SrcOut => porter_duff(1. - da, 0.),
+        BlendMode::SrcOver => porter_duff(1., 1. - sa),
+        BlendMode::SrcAtop => porter_duff(da, 1. - sa),
+        BlendMode::DestIn => porter_duff(0., sa),
+        BlendMode::DestOut => porter_duff(0., 1. - sa),
+        BlendMode::DestOver => porter_duff(1. - da, 1.),
+        BlendMode::DestAtop => porter_duff(1. - da, sa),
+        BlendMode::Xor => porter_duff(1. - da, 1. - sa),
+        BlendMode::Lighter => {
+            let c = porter_duff(1., 1.);
+            [c[0].min(1.), c[1].min(1.), c[2].min(1.), c[3].min(1.)]
+        }
+        BlendMode::Multiply => separable(s, d, |b, s| b * s),
+        BlendMode::Screen => separable(s, d, screen),
+        BlendMode::Overlay => separable(s, d, |b, s| hard_light(s, b)),
+        BlendMode::Darken => separable(s, d, f32::min),
+        BlendMode::Lighten => separable(s, d, f32::max),
+        BlendMode::ColorDodge => separable(s, d, |b, s| {
+            if b <= 0. {
+                0.
+            } else if s >= 1. {
+                1.
+            } else {
+                (b / (1. - s)).min(1.)
+            }
+        }),
+        BlendMode::ColorBurn => separable(s, d, |b, s| {
+            if b >= 1. {
+                1.
+            } else if s <= 0. {
+                0.
+            } else {
+                1. - ((1. - b) / s).min(1.)
+            }
+        }),
+        BlendMode::HardLight => separable(s, d, hard_light),
+        BlendMode::SoftLight => separable(s, d, |b, s| {
+            if s <= 0.5 {
+                b - (1. - 2. * s) * b * (1. - b)
+            } else {
+                let d = if b <= 0.25 { ((16. * b - 12.) * b + 4.) * b } else { b.sqrt() };
+                b + (2. * s - 1.) * (d - b)
+            }
+        }),
+        BlendMode::Difference => separable(s, d, |b, s| (b - s).abs()),
+        BlendMode::Exclusion => separable(s, d, |b, s| b + s - 2. * b * s),
+        BlendMode::Hue => non_separable(s, d, |b, s| set_lum(set_sat(s, sat(b)), lum(b))),
+        BlendMode::Saturation => non_separable(s, d, |b, s| set_lum(set_sat(b, sat(s)), lum(b))),
+        BlendMode::Color => non_separable(s, d, |b, s| set_lum(s, lum(b))),
+        BlendMode::Luminosity => non_separable(s, d, |b, s| set_lum(b, lum(s))),
+    }
+}
+
+fn screen(b: f32, s: f32) -> f32 {
+    b + s - b * s
+}
+
+fn hard_light(b: f32, s: f32) -> f32 {
+    if s <= 0.5 {
+        b * 2. * s
+    } else {
+        screen(b, 2. * s - 1.)
+    }
+}
+
+fn unpremultiply(c: Rgba) -> [f32; 3] {
+    match c[3] {
+        a if a > 0. => [c[0] / a, c[1] / a, c[2] / a],
+        _ => [0.; 3],
+    }
+}
+
+/// Blend with a function of the straight backdrop and source colors, as in the PDF and CSS specifications.
+fn mix(s: Rgba, d: Rgba, mixed: [f32; 3]) -> Rgba {
+    let (sa, da) = (s[3], d[3]);
+    let c = |i: usize| s[i] * (1. - da) + d[i] * (1. - sa) + sa * da * mixed[i];
+    [c(0), c(1), c(2), sa + da - sa * da]
+}
+
+fn separable(s: Rgba, d: Rgba, f: impl Fn(f32, f32) -> f32) -> Rgba {
+    let (cs, cb) = (unpremultiply(s), unpremultiply(d));
+    mix(s, d, [f(cb[0], cs[0]), f(cb[1], cs[1]), f(cb[2], cs[2])])
+}
+
+fn non_separable(s: Rgba, d: Rgba, f: impl Fn([f32; 3], [f32; 3]) -> [f32; 3]) -> Rgba {
+    mix(s, d, f(unpremultiply(d), unpremultiply(s)))
+}
+
+fn lum(c: [f32; 3]) -> f32 {
+    0.3 * c[0] + 0.59 * c[1] + 0.11 * c[2]
+}
+
+fn set_lum(c: [f32; 3], l: f32) -> [f32; 3] {
+    let d = l - lum(c);
+    let c = [c[0] + d, c[1] + d, c[2] + d];
+    // clip into gamut
+    let l = lum(c);
+    let n = c[0].min(c[1]).min(c[2]);
+    let x = c[0].max(c[1]).max(c[2]);
+    c.map(|v| {
+        let mut v = v;
+        if n < 0. {
+            v = l + (v - l) * l / (l - n);
+        }
+        if x > 1. {
+            v = l + (v - l) * (1. - l) / (x - l);
+        }
+        v
+    })
+}
+
+fn sat(c: [f32; 3]) -> f32 {
+    c[0].max(c[1]).max(c[2]) - c[0].min(c[1]).min(c[2])
+}
+
+fn set_sat(c: [f32; 3], s: f32) -> [f32; 3] {
+    let max = c[0].max(c[1]).max(c[2]);
+    let min = c[0].min(c[1]).min(c[2]);
+    if max > min {
+        c.map(|v| (v - min) * s / (max - min))
+    } else {
+        [0.; 3]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_renderer::scene::{ClipPath, DrawPath};
+
+    fn scene(size: f32) -> Scene {
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::zero(), Vector2F::splat(size)));
+        scene
+    }
+
+    fn square(origin: f32, size: f32) -> Outline {
+        Outline::from_rect(RectF::new(Vector2F::splat(origin), Vector2F::splat(size)))
+    }
+
+    #[test]
+    fn fill_rect() {
+        let mut scene = scene(10.);
+        let red = scene.push_paint(&Paint::from_color(ColorU::new(255, 0, 0, 255)));
+        scene.push_draw_path(DrawPath::new(square(2., 4.), red));
+        let image = CpuRasterizer::new().rasterize(scene, None);
+
+        assert_eq!(image.dimensions(), (10, 10));
+        assert_eq!(image.get_pixel(3, 3).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0, 0]);
+        assert_eq!(image.get_pixel(6, 3).0, [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn antialiased_edge() {
+        let mut scene = scene(4.);
+        let black = scene.push_paint(&Paint::black());
+        scene.push_draw_path(DrawPath::new(square(0.5, 2.), black));
+        let image = CpuRasterizer::new().rasterize(scene, Some(ColorF::white()));
+
+        // half covered
+        assert!((126..=129).contains(&image.get_pixel(0, 1).0[0]));
+        // a quarter covered
+        assert!((190..=192).contains(&image.get_pixel(0, 0).0[0]));
+        assert_eq!(image.get_pixel(1, 1).0, [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn fill_rules() {
+        for (fill_rule, hole) in [(FillRule::Winding, false), (FillRule::EvenOdd, true)] {
+            let mut scene = scene(10.);
+            let black = scene.push_paint(&Paint::black());
+            let mut outline = square(1., 8.);
+            outline.push_contour(square(3., 4.).contours()[0].clone());
+            let mut draw_path = DrawPath::new(outline, black);
+            draw_path.set_fill_rule(fill_rule);
+            scene.push_draw_path(draw_path);
+            let image = CpuRasterizer::new().rasterize(scene, None);
+
+            assert_eq!(image.get_pixel(2, 2).0[3], 255);
+            assert_eq!(image.get_pixel(5, 5).0[3] == 0, hole, "{:?}", fill_rule);
+        }
+    }
+
+    #[test]
+    fn nested_clip_paths() {
+        let mut scene = scene(10.);
+        let outer = scene.push_clip_path(ClipPath::new(square(0., 6.)));
+        let mut inner = ClipPath::new(square(4., 6.));
+        inner.set_clip_path(Some(outer));
+        let inner = scene.push_clip_path(inner);
+        let black = scene.push_paint(&Paint::black());
+        let mut draw_path = DrawPath::new(square(0., 10.), black);
+        draw_path.set_clip_path(Some(inner));
+        scene.push_draw_path(draw_path);
+        let image = CpuRasterizer::new().rasterize(scene, None);
+
+        assert_eq!(image.get_pixel(5, 5).0[3], 255);
+        assert_eq!(image.get_pixel(2, 2).0[3], 0);
+        assert_eq!(image.get_pixel(8, 8).0[3], 0);
+    }
+
+    #[test]
+    fn multiply() {
+        let mut scene = scene(2.);
+        let yellow = scene.push_paint(&Paint::from_color(ColorU::new(255, 255, 0, 255)));
+        let cyan = scene.push_paint(&Paint::from_color(ColorU::new(0, 255, 255, 255)));
+        scene.push_draw_path(DrawPath::new(square(0., 2.), yellow));
+        let mut draw_path = DrawPath::new(square(0., 2.), cyan);
+        draw_path.set_blend_mode(BlendMode::Multiply);
+        scene.push_draw_path(draw_path);
+        let image = CpuRasterizer::new().rasterize(scene, None);
+
+        assert_eq!(image.get_pixel(1, 1).0, [0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn image_pattern() {
+        use pathfinder_content::pattern::Pattern;
+        use std::sync::Arc;
+
+        let pixels = vec![ColorU::new(255, 0, 0, 255), ColorU::new(0, 0, 255, 255)];
+        let mut pattern = Pattern::from_image(Image::new(Vector2I::new(2, 1), Arc::new(pixels)));
+        pattern.apply_transform(Transform2F::from_scale(Vector2F::new(4., 8.)));
+        pattern.set_smoothing_enabled(false);
+
+        let mut scene = scene(8.);
+        let paint = scene.push_paint(&Paint::from_pattern(pattern));
+        scene.push_draw_path(DrawPath::new(square(0., 8.), paint));
+        let image = CpuRasterizer::new().rasterize(scene, None);
+
+        assert_eq!(image.get_pixel(1, 4).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(6, 4).0, [0, 0, 255, 255]);
+    }
+}
@@ -12,8 +12,8 @@ fn bench_render_page(c: &mut Criterion) {
     group.sample_size(50);
     group.warm_up_time(Duration::from_secs(1));
 
-    let mut cache = Cache::new();
-    let mut backend = SceneBackend::new(&mut cache);
+    let cache = Cache::new().expect("can't load standard fonts");
+    let mut backend = SceneBackend::new(&cache);
     for (i, page) in file.pages().enumerate() {
         if let Ok(page) = page {
             group.bench_function(&format!("page {}", i), |b| b.iter(|| render_page(&mut backend, &resolver, &page, Default::default()).unwrap()));
@@ -0,0 +1,67 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use argh::FromArgs;
+use pdf::file::FileOptions;
+use pdf_render::dump::{PageDump, TraceDump};
+use pdf_render::render_page;
+use pdf_render::tracer::{TraceCache, Tracer};
+
+#[derive(FromArgs)]
+/// Dump the trace of a range of pages as JSON or CBOR.
+struct Options {
+    /// first page, starting at 1
+    #[argh(option, default = "1")]
+    first: u32,
+
+    /// last page, inclusive (defaults to the last page of the document)
+    #[argh(option)]
+    last: Option<u32>,
+
+    /// output format: json or cbor
+    #[argh(option, default = "String::from(\"json\")")]
+    format: String,
+
+    /// output file (defaults to stdout)
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+
+    /// input PDF
+    #[argh(positional)]
+    input: PathBuf,
+}
+
+fn main() {
+    env_logger::init();
+    let opt: Options = argh::from_env();
+
+    let file = FileOptions::cached().open(&opt.input).expect("can't open file");
+    let resolver = file.resolver();
+    let cache = TraceCache::new().expect("can't load standard fonts");
+
+    let last = opt.last.unwrap_or(file.num_pages()).min(file.num_pages());
+    let mut pages = vec![];
+    for nr in opt.first.max(1) - 1..last {
+        let page = file.get_page(nr).expect("can't read page");
+        let mut clip_paths = vec![];
+        let mut backend = Tracer::new(&cache, &mut clip_paths);
+        render_page(&mut backend, &resolver, &page, Default::default()).expect("can't render page");
+        let view_box = backend.view_box();
+        let items = backend.finish();
+        pages.push(PageDump::new(nr, view_box, &items, &clip_paths));
+    }
+    let dump = TraceDump::new(pages);
+
+    let out: Box<dyn Write> = match opt.output {
+        Some(ref path) => Box::new(File::create(path).expect("can't create output file")),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut out = BufWriter::new(out);
+    match opt.format.as_str() {
+        "json" => serde_json::to_writer(&mut out, &dump).expect("can't write JSON"),
+        "cbor" => ciborium::into_writer(&dump, &mut out).expect("can't write CBOR"),
+        f => panic!("unknown format {:?}, expected json or cbor", f),
+    }
+    out.flush().unwrap();
+}
@@ -0,0 +1,44 @@
+use pdf::file::FileOptions;
+use pdf_render::html::{html, HtmlPage};
+use pdf_render::render_page;
+use pdf_render::tracer::{TraceCache, Tracer};
+use pdf_render::Cache;
+
+fn main() {
+    env_logger::init();
+    let mut args = std::env::args().skip(1);
+    let input = args.next().expect("no input file given");
+    let output = args.next().unwrap_or_else(|| "out.html".into());
+
+    let file = FileOptions::cached().open(&input).unwrap();
+    let resolver = file.resolver();
+
+    let trace_cache = TraceCache::new().expect("can't load standard fonts");
+    let cache = Cache::new().expect("can't load standard fonts");
+
+    let pages: Vec<_> = file.pages().map(|page| page.unwrap()).collect();
+    let mut traces = vec![];
+    for p in &pages {
+        let mut clip_paths = vec![];
+        let mut backend = Tracer::new(&trace_cache, &mut clip_paths);
+        render_page(&mut backend, &resolver, p, Default::default()).unwrap();
+        let view_box = backend.view_box();
+        let items = backend.finish();
+        traces.push((items, clip_paths, view_box));
+    }
+    let resources: Vec<_> = pages.iter().map(|p| p.resources().unwrap()).collect();
+    let html_pages: Vec<_> = traces
+        .iter()
+        .zip(&resources)
+        .enumerate()
+        .map(|(nr, ((items, clip_paths, view_box), resources))| HtmlPage {
+            items,
+            clip_paths,
+            view_box: *view_box,
+            resources,
+            number: nr as u32 + 1,
+        })
+        .collect();
+
+    std::fs::write(&output, html(&html_pages, &cache, &resolver, &input)).unwrap();
+}
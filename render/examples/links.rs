@@ -0,0 +1,38 @@
+use pdf::file::FileOptions;
+use pdf::object::Resolve;
+use pdf_render::links::LinkResolver;
+use pdf_render::render_page;
+use pdf_render::tracer::{TraceCache, Tracer};
+
+fn main() {
+    env_logger::init();
+    let arg = std::env::args().nth(1).unwrap();
+
+    let file = FileOptions::cached().open(&arg).unwrap();
+    let resolver = file.resolver();
+
+    let catalog = resolver
+        .resolve(file.trailer.root.get_ref().get_inner())
+        .and_then(|p| p.into_dictionary())
+        .unwrap();
+    let links = LinkResolver::new(&catalog, &resolver).unwrap();
+
+    let cache = TraceCache::new().expect("can't load standard fonts");
+    for (nr, page) in file.pages().enumerate() {
+        let page = page.unwrap();
+        let mut clip_paths = vec![];
+        let mut backend = Tracer::new(&cache, &mut clip_paths);
+        let transform = render_page(&mut backend, &resolver, &page, Default::default()).unwrap();
+        for link in links.page_links(page.get_ref().get_inner(), transform, &resolver).unwrap() {
+            println!(
+                "page {} [{:.1} {:.1} {:.1} {:.1}] {:?}",
+                nr + 1,
+                link.rect.min_x(),
+                link.rect.min_y(),
+                link.rect.max_x(),
+                link.rect.max_y(),
+                link.target
+            );
+        }
+    }
+}
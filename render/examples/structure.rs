@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use pdf::file::FileOptions;
+use pdf::object::Resolve;
+use pdf_render::render_page;
+use pdf_render::structure::StructTree;
+use pdf_render::tracer::{TraceCache, Tracer};
+
+fn main() {
+    env_logger::init();
+    let arg = std::env::args().nth(1).unwrap();
+
+    let file = FileOptions::cached().open(&arg).unwrap();
+    let resolver = file.resolver();
+
+    let catalog = resolver
+        .resolve(file.trailer.root.get_ref().get_inner())
+        .and_then(|p| p.into_dictionary())
+        .unwrap();
+    let tree = match StructTree::from_catalog(&catalog, &resolver).unwrap() {
+        Some(tree) => tree,
+        None => {
+            println!("not tagged");
+            return;
+        }
+    };
+
+    let cache = TraceCache::new().expect("can't load standard fonts");
+    let mut traces = vec![];
+    for page in file.pages() {
+        let p = page.unwrap();
+        let mut clip_paths = vec![];
+        let mut backend = Tracer::new(&cache, &mut clip_paths);
+        render_page(&mut backend, &resolver, &p, Default::default()).unwrap();
+        traces.push((p.get_ref().get_inner(), backend.finish()));
+    }
+    let pages: HashMap<_, _> = traces.iter().map(|(r, items)| (*r, items.as_slice())).collect();
+
+    let mut last_heading = 0;
+    for idx in tree.reading_order() {
+        let elem = &tree.elements[idx];
+        let mut depth = 0;
+        let mut parent = elem.parent;
+        while let Some(p) = parent {
+            depth += 1;
+            parent = tree.elements[p].parent;
+        }
+        let text = tree.text(idx, &pages);
+        println!("{:indent$}{} ({}) {:?}", "", elem.role, elem.standard_role, text, indent = 2 * depth);
+
+        if elem.standard_role == "Figure" && elem.alt.is_none() && elem.actual_text.is_none() {
+            println!("{:indent$}! figure without /Alt", "", indent = 2 * depth);
+        }
+        if let Some(level) = elem.heading_level() {
+            if level > last_heading + 1 {
+                println!("{:indent$}! H{} follows H{}", "", level, last_heading, indent = 2 * depth);
+            }
+            last_heading = level;
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use pdf::file::FileOptions;
+use pdf_render::{render_page, Cache, SvgBackend};
+
+fn main() {
+    env_logger::init();
+    let mut args = std::env::args().skip(1);
+    let input = args.next().expect("no input file given");
+    let prefix = args.next().unwrap_or_else(|| "page".into());
+
+    let file = FileOptions::cached().open(&input).unwrap();
+    let resolver = file.resolver();
+
+    let cache = Cache::new().expect("can't load standard fonts");
+
+    for (nr, page) in file.pages().enumerate() {
+        let p = page.unwrap();
+        let mut backend = SvgBackend::new(&cache);
+        render_page(&mut backend, &resolver, &p, Default::default()).unwrap();
+        let path = format!("{}_{}.svg", prefix, nr + 1);
+        std::fs::write(&path, backend.finish()).unwrap();
+        println!("{}", path);
+    }
+}
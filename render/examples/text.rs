@@ -0,0 +1,25 @@
+use pdf::file::FileOptions;
+use pdf_render::render_page;
+use pdf_render::textlayout::PageText;
+use pdf_render::tracer::{TraceCache, Tracer};
+
+fn main() {
+    env_logger::init();
+    let arg = std::env::args().nth(1).unwrap();
+
+    let file = FileOptions::cached().open(&arg).unwrap();
+    let resolver = file.resolver();
+
+    let cache = TraceCache::new().expect("can't load standard fonts");
+
+    for (nr, page) in file.pages().enumerate() {
+        let p = page.unwrap();
+        let mut clip_paths = vec![];
+        let mut backend = Tracer::new(&cache, &mut clip_paths);
+        render_page(&mut backend, &resolver, &p, Default::default()).unwrap();
+        let items = backend.finish();
+        let text = PageText::new(&items);
+        println!("--- page {} ---", nr + 1);
+        println!("{}", text.text());
+    }
+}
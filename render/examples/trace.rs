@@ -9,12 +9,12 @@ fn main() {
     let file = FileOptions::cached().open(&arg).unwrap();
     let resolver = file.resolver();
 
-    let mut cache = TraceCache::new();
+    let cache = TraceCache::new().expect("can't load standard fonts");
 
     for page in file.pages() {
         let p = page.unwrap();
         let mut clip_paths = vec![];
-        let mut backend = Tracer::new(&mut cache, &mut clip_paths);
+        let mut backend = Tracer::new(&cache, &mut clip_paths);
         render_page(&mut backend, &resolver, &p, Default::default()).unwrap();
         let items = backend.finish();
         for i in items {
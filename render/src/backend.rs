@@ -2,12 +2,12 @@ use pathfinder_content::{fill::FillRule, outline::Outline, stroke::StrokeStyle};
 use pathfinder_geometry::{rect::RectF, transform2d::Transform2F};
 
 use super::{Fill, FontEntry, TextSpan};
+use crate::limits::{Limits, RenderError};
 use font::Glyph;
-use pdf::error::PdfError;
 use pdf::font::Font as PdfFont;
 use pdf::{
     content::Op,
-    object::{ImageXObject, MaybeRef, Ref, Resolve, Resources, XObject},
+    object::{ImageXObject, MaybeRef, PlainRef, Ref, Resolve, Resources, XObject},
 };
 use std::sync::Arc;
 
@@ -69,13 +69,20 @@ pub trait Backend {
         self.draw(&glyph.path, mode, FillRule::Winding, transform, clip);
     }
     
+    /// Load a font. Embedded fonts that exceed `limits` are an error.
     fn get_font(
         &mut self,
         font_ref: &MaybeRef<PdfFont>,
         resolve: &impl Resolve,
-    ) -> Result<Option<Arc<FontEntry>>, PdfError>;
+        limits: &Limits,
+    ) -> Result<Option<Arc<FontEntry>>, RenderError>;
     fn add_text(&mut self, span: TextSpan, clip: Option<Self::ClipPathId>);
 
+    /// Called when the marked-content identifier of the following content changes.
+    /// `stream` is the form xobject whose content stream the MCID belongs to, or `None` for the page.
+    /// Text gets both in `TextSpan::mcid` and `TextSpan::mcid_stream`.
+    fn set_mcid(&mut self, mcid: Option<u32>, stream: Option<PlainRef>) {}
+
     /// The following functions are for debugging PDF files and not relevant for rendering them.
     fn bug_text_no_font(&mut self, data: &[u8]) {}
     fn bug_text_invisible(&mut self, text: &str) {}
@@ -0,0 +1,364 @@
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use instant::Instant;
+use pathfinder_geometry::{rect::RectF, vector::Vector2F};
+use pdf::error::PdfError;
+use crate::limits::{LimitError, Limits, RenderError};
+use crate::PageBox;
+
+/// How often (in operators) the deadline is compared against the clock.
+const DEADLINE_CHECK_INTERVAL: usize = 64;
+
+/// A handle that can be used to abort a running render from another thread.
+///
+/// Clones share the same flag, so the UI can keep one copy and pass another into `RenderOptions`.
+#[derive(Clone, Default, Debug)]
+pub struct CancelToken(Arc<AtomicBool>);
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Options for a single call to `render_page_with`.
+///
+/// All budgets are optional. When one runs out, rendering stops after the current operator
+/// and whatever has been drawn so far stays in the backend.
+/// Exceeding one of the `limits` on the other hand is an error.
+#[derive(Clone, Default, Debug)]
+pub struct RenderOptions {
+    /// maximum number of content stream operators (including those of form xobjects)
+    pub max_ops: Option<usize>,
+    /// maximum number of path segments (move, line, curve and rect operators)
+    pub max_path_segments: Option<usize>,
+    /// stop rendering once this point in time has passed
+    pub deadline: Option<Instant>,
+    pub cancel: Option<CancelToken>,
+    pub limits: Limits,
+    /// scale the glyphs of substituted fonts horizontally to the widths given in the PDF
+    pub fit_substituted_widths: bool,
+    /// only draw paths, glyphs and images that intersect this rectangle of the output and use it as the view box
+    pub region: Option<RectF>,
+    /// the page boundary that becomes the view box (unless `region` is set)
+    pub page_box: PageBox,
+}
+impl RenderOptions {
+    pub fn new() -> Self {
+        RenderOptions::default()
+    }
+    pub fn max_ops(mut self, max_ops: usize) -> Self {
+        self.max_ops = Some(max_ops);
+        self
+    }
+    pub fn max_path_segments(mut self, max_path_segments: usize) -> Self {
+        self.max_path_segments = Some(max_path_segments);
+        self
+    }
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+    /// sets the deadline `timeout` from now
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.deadline(Instant::now() + timeout)
+    }
+    pub fn cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+    pub fn fit_substituted_widths(mut self, fit: bool) -> Self {
+        self.fit_substituted_widths = fit;
+        self
+    }
+    pub fn region(mut self, region: RectF) -> Self {
+        self.region = Some(region);
+        self
+    }
+    pub fn page_box(mut self, page_box: PageBox) -> Self {
+        self.page_box = page_box;
+        self
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Truncation {
+    Ops,
+    PathSegments,
+    Deadline,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RenderStatus {
+    /// all operators were processed
+    Complete,
+    /// the `CancelToken` was triggered
+    Cancelled,
+    /// a budget ran out
+    Truncated(Truncation),
+}
+impl RenderStatus {
+    pub fn is_complete(self) -> bool {
+        self == RenderStatus::Complete
+    }
+}
+
+/// Counts the work done by a `RenderState` (and the nested states of form xobjects).
+pub(crate) struct BudgetTracker {
+    max_ops: Option<usize>,
+    max_path_segments: Option<usize>,
+    deadline: Option<Instant>,
+    cancel: Option<CancelToken>,
+    pub limits: Limits,
+    pub fit_substituted_widths: bool,
+    pub region: Option<RectF>,
+    ops: Cell<usize>,
+    path_segments: Cell<usize>,
+    form_depth: Cell<usize>,
+    status: Cell<RenderStatus>,
+    limit_error: Cell<Option<LimitError>>,
+}
+impl BudgetTracker {
+    pub fn new(options: &RenderOptions) -> Self {
+        BudgetTracker {
+            max_ops: options.max_ops,
+            max_path_segments: options.max_path_segments,
+            deadline: options.deadline,
+            cancel: options.cancel.clone(),
+            limits: options.limits.clone(),
+            fit_substituted_widths: options.fit_substituted_widths,
+            region: options.region,
+            ops: Cell::new(0),
+            path_segments: Cell::new(0),
+            form_depth: Cell::new(0),
+            status: Cell::new(RenderStatus::Complete),
+            limit_error: Cell::new(None),
+        }
+    }
+    pub fn unlimited() -> Self {
+        BudgetTracker::new(&RenderOptions::default())
+    }
+
+    /// Account for the next operator. Returns false if rendering has to stop.
+    pub fn next_op(&self) -> bool {
+        if !self.status.get().is_complete() {
+            return false;
+        }
+        let ops = self.ops.get() + 1;
+        self.ops.set(ops);
+
+        if matches!(self.max_ops, Some(max) if ops > max) {
+            return self.stop(RenderStatus::Truncated(Truncation::Ops));
+        }
+        if matches!(self.cancel, Some(ref token) if token.is_cancelled()) {
+            return self.stop(RenderStatus::Cancelled);
+        }
+        if ops % DEADLINE_CHECK_INTERVAL == 0 {
+            if matches!(self.deadline, Some(deadline) if Instant::now() >= deadline) {
+                return self.stop(RenderStatus::Truncated(Truncation::Deadline));
+            }
+        }
+        true
+    }
+
+    /// Account for `n` path segments. Fails if the budget ran out, so the current operator is
+    /// aborted right away; `render_page_with` reports this as a truncated page, not as an error.
+    pub fn add_path_segments(&self, n: usize) -> Result<(), PdfError> {
+        let segments = self.path_segments.get() + n;
+        self.path_segments.set(segments);
+
+        match self.max_path_segments {
+            Some(max) if segments > max => {
+                self.stop(RenderStatus::Truncated(Truncation::PathSegments));
+                Err(PdfError::Other { msg: format!("path segment budget of {} exhausted", max) })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether something with the output bounds `bounds` has to be drawn.
+    pub fn visible(&self, bounds: RectF) -> bool {
+        visible(self.region, bounds)
+    }
+
+    pub fn status(&self) -> RenderStatus {
+        self.status.get()
+    }
+    pub fn ops(&self) -> usize {
+        self.ops.get()
+    }
+
+    /// Record that a limit was exceeded and turn it into an error that aborts rendering.
+    pub fn limit_exceeded(&self, e: LimitError) -> PdfError {
+        warn!("{}", e);
+        let err = e.clone().into();
+        self.limit_error.set(Some(e));
+        err
+    }
+    /// Turn an error of the backend into a `PdfError`, recording exceeded limits.
+    pub fn error(&self, e: RenderError) -> PdfError {
+        match e {
+            RenderError::Limit(e) => self.limit_exceeded(e),
+            RenderError::Pdf(e) => e,
+        }
+    }
+    /// The first limit that was exceeded, if any.
+    pub fn take_limit_error(&self) -> Option<LimitError> {
+        self.limit_error.take()
+    }
+
+    pub fn enter_form(&self) -> Result<(), PdfError> {
+        let depth = self.form_depth.get() + 1;
+        if depth > self.limits.max_form_depth {
+            return Err(self.limit_exceeded(LimitError::FormDepth(depth)));
+        }
+        self.form_depth.set(depth);
+        Ok(())
+    }
+    pub fn leave_form(&self) {
+        self.form_depth.set(self.form_depth.get() - 1);
+    }
+
+    fn stop(&self, status: RenderStatus) -> bool {
+        debug!("stopping render after {} ops: {:?}", self.ops.get(), status);
+        self.status.set(status);
+        false
+    }
+}
+
+/// Whether `bounds` intersects `region`. Everything is visible without a region.
+pub(crate) fn visible(region: Option<RectF>, bounds: RectF) -> bool {
+    match region {
+        // one pixel of margin for anti-aliasing
+        Some(region) => region.intersects(bounds.dilate(Vector2F::splat(1.0))),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_stops_at_the_next_op() {
+        let token = CancelToken::new();
+        let budget = BudgetTracker::new(&RenderOptions::new().cancel_token(token.clone()));
+        assert!(budget.next_op());
+        token.cancel();
+        assert!(!budget.next_op());
+        std::assert_eq!(budget.status(), RenderStatus::Cancelled);
+        assert!(!budget.next_op());
+        std::assert_eq!(budget.ops(), 2);
+    }
+
+    #[test]
+    fn deadline_is_checked_every_interval() {
+        let budget = BudgetTracker::new(&RenderOptions::new().deadline(Instant::now()));
+        for _ in 1..DEADLINE_CHECK_INTERVAL {
+            assert!(budget.next_op());
+        }
+        assert!(!budget.next_op());
+        std::assert_eq!(budget.status(), RenderStatus::Truncated(Truncation::Deadline));
+    }
+
+    #[test]
+    fn op_and_segment_budgets() {
+        let budget = BudgetTracker::new(&RenderOptions::new().max_ops(3));
+        assert!((0..3).all(|_| budget.next_op()));
+        assert!(!budget.next_op());
+        std::assert_eq!(budget.status(), RenderStatus::Truncated(Truncation::Ops));
+
+        let budget = BudgetTracker::new(&RenderOptions::new().max_path_segments(10));
+        assert!(budget.add_path_segments(6).is_ok());
+        assert!(budget.add_path_segments(4).is_ok());
+        assert!(budget.add_path_segments(1).is_err());
+        std::assert_eq!(budget.status(), RenderStatus::Truncated(Truncation::PathSegments));
+        assert!(!budget.next_op());
+    }
+
+    #[test]
+    fn unlimited_never_stops() {
+        let budget = BudgetTracker::unlimited();
+        assert!((0..10 * DEADLINE_CHECK_INTERVAL).all(|_| budget.next_op()));
+        assert!(budget.add_path_segments(usize::MAX / 2).is_ok());
+        assert!(budget.status().is_complete());
+    }
+
+    #[test]
+    fn visible_in_region() {
+        let rect = |x: f32, y: f32, w: f32, h: f32| RectF::new(Vector2F::new(x, y), Vector2F::new(w, h));
+        let region = Some(rect(0., 0., 100., 100.));
+        assert!(visible(None, rect(-500., -500., 1., 1.)));
+        assert!(visible(region, rect(50., 50., 10., 10.)));
+        assert!(visible(region, rect(-20., 40., 200., 1.)));
+        // within the margin
+        assert!(visible(region, rect(100.5, 0., 10., 10.)));
+        assert!(!visible(region, rect(102., 0., 10., 10.)));
+        assert!(!visible(region, rect(0., -20., 10., 10.)));
+    }
+
+    #[test]
+    fn region_skips_content_outside() {
+        use pathfinder_geometry::transform2d::Transform2F;
+        use pdf::file::FileOptions;
+        use crate::testutil::PdfBuilder;
+        use crate::tracer::{DrawItem, TraceCache, Tracer};
+        use crate::{render_region, StaticFontProvider};
+
+        // one square at the bottom left and one at the top right of the page
+        let mut pdf = PdfBuilder::new();
+        pdf.page((100., 100.), "", "0 0 10 10 re f 80 80 10 10 re f");
+        let file = FileOptions::cached().load(pdf.finish()).unwrap();
+        let page = file.get_page(0).unwrap();
+        let cache = TraceCache::with_font_provider(Arc::new(StaticFontProvider::new(&[])));
+        let mut clip_paths = vec![];
+        let mut tracer = Tracer::new(&cache, &mut clip_paths);
+        // the top right quarter of the output, which has y pointing down
+        let half = 50. * 25.4 / 72.;
+        let region = RectF::new(Vector2F::new(half, 0.), Vector2F::splat(half));
+        render_region(&mut tracer, &file.resolver(), &page, Transform2F::default(), region).unwrap();
+        std::assert_eq!(tracer.view_box(), region);
+        let items = tracer.finish();
+        let vectors: Vec<_> = items.iter().filter_map(|item| match item {
+            DrawItem::Vector(v) => Some(v.transform * v.outline.bounds()),
+            _ => None,
+        }).collect();
+        std::assert_eq!(vectors.len(), 1);
+        assert!(region.contains_rect(vectors[0]));
+    }
+
+    #[test]
+    fn segment_budget_stops_within_an_operator() {
+        use pathfinder_geometry::transform2d::Transform2F;
+        use pdf::file::FileOptions;
+        use crate::testutil::PdfBuilder;
+        use crate::tracer::{DrawItem, TraceCache, Tracer};
+        use crate::{render_page_with, StaticFontProvider};
+
+        let mut pdf = PdfBuilder::new();
+        pdf.page((100., 100.), "", "0 0 10 10 re f 20 20 m 30 20 l 30 30 l 20 30 l 20 20 l f");
+        let file = FileOptions::cached().load(pdf.finish()).unwrap();
+        let page = file.get_page(0).unwrap();
+        let cache = TraceCache::with_font_provider(Arc::new(StaticFontProvider::new(&[])));
+        let mut clip_paths = vec![];
+        let mut tracer = Tracer::new(&cache, &mut clip_paths);
+        let options = RenderOptions::new().max_path_segments(6);
+        let result = render_page_with(&mut tracer, &file.resolver(), &page, Transform2F::default(), &options).unwrap();
+        std::assert_eq!(result.status, RenderStatus::Truncated(Truncation::PathSegments));
+        // the second path was aborted at its second `l`, before `f`
+        let items = tracer.finish();
+        std::assert_eq!(items.iter().filter(|item| matches!(item, DrawItem::Vector(_))).count(), 1);
+        std::assert_eq!(result.ops, 5);
+    }
+}
@@ -1,10 +1,9 @@
-use std::path::{PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use pdf::object::*;
 use pdf::primitive::Name;
 use pdf::font::{Font as PdfFont};
-use pdf::error::{Result};
+use pdf::error::Result;
 
 use pathfinder_geometry::{
     vector::{Vector2I},
@@ -12,87 +11,178 @@ use pathfinder_geometry::{
 use pathfinder_content::{
     pattern::{Image},
 };
+use pathfinder_color::ColorU;
 
 use crate::BlendMode;
+use crate::displaylist::DisplayList;
+use crate::limits::{Limits, RenderError};
 
 use super::{fontentry::FontEntry};
-use super::image::load_image;
-use super::font::{load_font, StandardCache};
-use globalcache::{sync::SyncCache, ValueSize};
+use super::image::load_image_with_limits;
+use super::font::{load_font, FontRc, StandardCache};
+use super::fontprovider::{DirectoryFontProvider, FontProvider, FontSubstitution};
+use super::lru::{CacheStats, LruCache};
+use globalcache::ValueSize;
+
+/// Default memory limit of `Cache` and `TraceCache` in bytes.
+pub const DEFAULT_MEMORY_LIMIT: usize = 512 << 20;
 
 #[derive(Clone)]
 pub struct ImageResult(pub Arc<Result<Image>>);
 impl ValueSize for ImageResult {
     fn size(&self) -> usize {
-        match *self.0 {
-            Ok(ref im) => im.pixels().len() * 4,
-            Err(_) => 1,
+        let pixels = match *self.0 {
+            Ok(ref im) => im.pixels().len() * std::mem::size_of::<ColorU>(),
+            Err(_) => 0,
+        };
+        std::mem::size_of::<Result<Image>>() + pixels
+    }
+}
+
+#[derive(Clone, Hash, PartialEq, Eq)]
+enum CacheKey {
+    Font(usize),
+    /// a font of the provider, by key
+    StandardFont(String),
+    Image(Ref<XObject>, BlendMode),
+    DisplayList(PlainRef),
+}
+
+#[derive(Clone)]
+enum CacheValue {
+    Font(Option<Arc<FontEntry>>),
+    StandardFont(Option<FontRc>),
+    Image(ImageResult),
+    DisplayList(Arc<DisplayList>),
+}
+impl ValueSize for CacheValue {
+    fn size(&self) -> usize {
+        match self {
+            CacheValue::Font(Some(f)) => f.size(),
+            CacheValue::Font(None) => std::mem::size_of::<Self>(),
+            CacheValue::StandardFont(Some(f)) => f.size(),
+            CacheValue::StandardFont(None) => std::mem::size_of::<Self>(),
+            CacheValue::Image(im) => im.size(),
+            CacheValue::DisplayList(list) => list.size(),
         }
     }
 }
 
+/// Fonts and images shared between renders.
+///
+/// All lookups take `&self`, so one `Cache` can be shared between threads (e.g. in an `Arc`).
+/// Fonts and images share one memory limit; when it is exceeded, the least recently used
+/// entries are dropped.
 pub struct Cache {
-    // shared mapping of fontname -> font
-    fonts: Arc<SyncCache<usize, Option<Arc<FontEntry>>>>,
-    images: Arc<SyncCache<(Ref<XObject>, BlendMode), ImageResult>>,
+    entries: LruCache<CacheKey, CacheValue>,
     std: StandardCache,
-    missing_fonts: Vec<Name>,
+    missing_fonts: Mutex<Vec<Name>>,
 }
 impl Cache {
-    pub fn new() -> Cache {
-        let standard_fonts;
-        if let Some(path) = std::env::var_os("STANDARD_FONTS") {
-            standard_fonts = PathBuf::from(path);
-        } else {
-            eprintln!("PDF: STANDARD_FONTS not set. using fonts/ instead.");
-            standard_fonts = PathBuf::from("fonts");
-        }
-        if !standard_fonts.is_dir() {
-            panic!("STANDARD_FONTS (or fonts/) is not directory.");
-        }
+    /// Use the fonts in `STANDARD_FONTS` (or `fonts/`) for fonts that are not embedded.
+    pub fn new() -> Result<Cache> {
+        Ok(Cache::with_font_provider(Arc::new(DirectoryFontProvider::from_env()?)))
+    }
+    pub fn with_font_provider(provider: Arc<dyn FontProvider>) -> Cache {
         Cache {
-            fonts: SyncCache::new(),
-            images: SyncCache::new(),
-            std: StandardCache::new(standard_fonts),
-            missing_fonts: Vec::new(),
+            entries: LruCache::new(DEFAULT_MEMORY_LIMIT),
+            std: StandardCache::new(provider),
+            missing_fonts: Mutex::new(Vec::new()),
+        }
+    }
+    /// Set the memory limit in bytes and evict entries until the cache fits.
+    pub fn set_memory_limit(&self, bytes: usize) {
+        self.entries.set_capacity(bytes);
+    }
+    pub fn stats(&self) -> CacheStats {
+        self.entries.stats()
+    }
+    /// Fonts that were not embedded and replaced by a different font.
+    pub fn substitutions(&self) -> Vec<FontSubstitution> {
+        self.std.substitutions()
+    }
+    /// Drop all cached fonts, images and display lists of the document.
+    /// The standard fonts and the statistics are kept.
+    pub fn clear(&self) {
+        self.entries.retain(|key| matches!(key, CacheKey::StandardFont(_)));
+    }
+    /// The font `key` of the font provider, loaded on first use.
+    fn standard_font(&self, key: &str) -> Option<FontRc> {
+        let val = self.entries.get(CacheKey::StandardFont(key.into()), || CacheValue::StandardFont(self.std.load(key)));
+        match val {
+            CacheValue::StandardFont(f) => f,
+            _ => unreachable!(),
         }
     }
-    pub fn get_font(&mut self, pdf_font: &MaybeRef<PdfFont>, resolve: &impl Resolve) -> Result<Option<Arc<FontEntry>>, > {
+    /// The font for `pdf_font`, which has to be within `limits`.
+    pub fn get_font(&self, pdf_font: &MaybeRef<PdfFont>, resolve: &impl Resolve, limits: &Limits) -> Result<Option<Arc<FontEntry>>, RenderError> {
         let mut error = None;
-        let val = self.fonts.get(&**pdf_font as *const PdfFont as usize, |_| 
-            match load_font(pdf_font, resolve, &mut self.std) {
+        let key = CacheKey::Font(&**pdf_font as *const PdfFont as usize);
+        let val = self.entries.try_get(key, || Ok(CacheValue::Font(
+            match load_font(pdf_font, resolve, &self.std, |key| self.standard_font(key), limits) {
                 Ok(Some(f)) => Some(Arc::new(f)),
                 Ok(None) => {
                     if let Some(ref name) = pdf_font.name {
-                        self.missing_fonts.push(name.clone());
+                        self.missing_fonts.lock().unwrap().push(name.clone());
                     }
                     None
                 },
-                Err(e) => {
+                // not cached, another render may allow it
+                Err(RenderError::Limit(e)) => return Err(e),
+                Err(RenderError::Pdf(e)) => {
                     error = Some(e);
                     None
                 }
             }
-        );
+        )))?;
+        let val = match val {
+            CacheValue::Font(f) => f,
+            _ => unreachable!(),
+        };
+        if let Some(ref entry) = val {
+            limits.check_font(entry)?;
+        }
         match error {
             None => Ok(val),
-            Some(e) => Err(e)
+            Some(e) => Err(e.into())
         }
     }
 
-    pub fn get_image(&mut self, xobject_ref: Ref<XObject>, im: &ImageXObject, resources: &Resources, resolve: &impl Resolve, mode: BlendMode) -> ImageResult {
-        self.images.get((xobject_ref, mode), |_|
-            ImageResult(Arc::new(load_image(im, resources, resolve, mode).map(|image|
+    /// The image `im`. Its size has to be checked against the limits before.
+    pub fn get_image(&self, xobject_ref: Ref<XObject>, im: &ImageXObject, resources: &Resources, resolve: &impl Resolve, mode: BlendMode) -> ImageResult {
+        let val = self.entries.get(CacheKey::Image(xobject_ref, mode), ||
+            CacheValue::Image(ImageResult(Arc::new(load_image_with_limits(im, resources, resolve, mode, &Limits::unlimited()).map(|image|
                 Image::new(Vector2I::new(im.width as i32, im.height as i32), Arc::new(image.into_data().into()))
-            )))
-        )
+            ))))
+        );
+        match val {
+            CacheValue::Image(im) => im,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The display list of `page`, recorded on first use.
+    ///
+    /// Entries are identified by the page object, so call `clear` before using the cache for another document.
+    /// If recording fails, nothing is cached and the next call tries again.
+    pub fn get_display_list(&self, page: &PageRc, resolve: &impl Resolve) -> Result<Arc<DisplayList>> {
+        let key = CacheKey::DisplayList(page.get_ref().get_inner());
+        let val = self.entries.try_get(key, || {
+            DisplayList::record(page, resolve, self).map(|list| CacheValue::DisplayList(Arc::new(list)))
+        })?;
+        match val {
+            CacheValue::DisplayList(list) => Ok(list),
+            _ => unreachable!(),
+        }
     }
 }
 impl Drop for Cache {
     fn drop(&mut self) {
-        info!("missing fonts:");
-        for name in self.missing_fonts.iter() {
-            info!("{}", name.as_str());
+        if let Ok(missing_fonts) = self.missing_fonts.get_mut() {
+            info!("missing fonts:");
+            for name in missing_fonts.iter() {
+                info!("{}", name.as_str());
+            }
         }
     }
 }
@@ -0,0 +1,301 @@
+//! Pages recorded once and replayed into scenes under any transform.
+//!
+//! Interpreting the content stream (parsing operators, loading fonts and images, stroking paths)
+//! happens while recording. Replaying only transforms the recorded paths, so zooming and panning
+//! a heavy page stays cheap.
+//!
+//! ```ignore
+//! let list = cache.get_display_list(&page, &resolver)?;
+//! let scene = list.scene(view_transform);
+//! ```
+
+use std::sync::Arc;
+
+use globalcache::ValueSize;
+use pathfinder_color::ColorU;
+use pathfinder_content::{
+    effects::BlendMode as SceneBlendMode,
+    fill::FillRule,
+    outline::Outline,
+    pattern::{Image, Pattern},
+};
+use pathfinder_geometry::{rect::RectF, transform2d::Transform2F, vector::Vector2F};
+use pathfinder_renderer::{
+    paint::Paint,
+    scene::{ClipPath, DrawPath, Scene},
+};
+use pdf::error::PdfError;
+use pdf::font::Font as PdfFont;
+use pdf::object::{ImageXObject, MaybeRef, Ref, Resolve, Resources, XObject};
+
+use crate::backend::{self, Backend, DrawMode};
+use crate::scene::{blend_mode, paint, stroke_outline};
+use crate::{Cache, FontEntry, Limits, RenderError, TextSpan};
+
+struct RecordedClip {
+    outline: Outline,
+    fill_rule: FillRule,
+    parent: Option<usize>,
+}
+
+enum Item {
+    Path {
+        outline: Outline,
+        paint: Paint,
+        fill_rule: FillRule,
+        blend_mode: SceneBlendMode,
+        clip: Option<usize>,
+    },
+    Image {
+        // the unit square, transformed
+        outline: Outline,
+        image: Image,
+        // from image pixels
+        transform: Transform2F,
+        blend_mode: SceneBlendMode,
+        clip: Option<usize>,
+    },
+}
+impl Item {
+    fn bounds(&self) -> RectF {
+        match *self {
+            Item::Path { ref outline, .. } | Item::Image { ref outline, .. } => outline.bounds(),
+        }
+    }
+}
+
+/// The drawing commands of one page, in the output space of `render_page` with an identity transform.
+pub struct DisplayList {
+    view_box: RectF,
+    transform: Transform2F,
+    clip_paths: Vec<RecordedClip>,
+    items: Vec<Item>,
+}
+
+impl DisplayList {
+    /// Record `page`. Fonts and images are loaded through `cache`.
+    pub fn record(page: &pdf::object::Page, resolve: &impl Resolve, cache: &Cache) -> Result<DisplayList, PdfError> {
+        let mut recorder = Recorder {
+            cache,
+            list: DisplayList {
+                view_box: RectF::default(),
+                transform: Transform2F::default(),
+                clip_paths: vec![],
+                items: vec![],
+            },
+        };
+        let transform = crate::render_page(&mut recorder, resolve, page, Transform2F::default())?;
+        recorder.list.transform = transform;
+        Ok(recorder.list)
+    }
+
+    /// The view box without a transform.
+    pub fn view_box(&self) -> RectF {
+        self.view_box
+    }
+
+    /// The transform from PDF user space into the recorded space, as returned by `render_page`.
+    /// Replaying with `transform` maps user space with `transform * self.transform()`.
+    pub fn transform(&self) -> Transform2F {
+        self.transform
+    }
+
+    /// Replay everything into a new scene.
+    pub fn scene(&self, transform: Transform2F) -> Scene {
+        self.scene_region(transform, None)
+    }
+
+    /// Replay into a new scene, skipping items that are outside of `region` (in the transformed space).
+    /// The view box is `region` if set, like with `render_region`.
+    pub fn scene_region(&self, transform: Transform2F, region: Option<RectF>) -> Scene {
+        let mut scene = Scene::new();
+        let view_box = region.unwrap_or(transform * self.view_box);
+        scene.set_view_box(view_box);
+        let white = scene.push_paint(&Paint::from_color(ColorU::white()));
+        scene.push_draw_path(DrawPath::new(Outline::from_rect(view_box), white));
+
+        // parents always come before their children
+        let mut clips = Vec::with_capacity(self.clip_paths.len());
+        for clip in &self.clip_paths {
+            let mut path = ClipPath::new(clip.outline.clone().transformed(&transform));
+            path.set_fill_rule(clip.fill_rule);
+            path.set_clip_path(clip.parent.map(|idx| clips[idx]));
+            clips.push(scene.push_clip_path(path));
+        }
+
+        for item in &self.items {
+            if !crate::budget::visible(region, transform * item.bounds()) {
+                continue;
+            }
+            match *item {
+                Item::Path { ref outline, ref paint, fill_rule, blend_mode, clip } => {
+                    let paint_id = scene.push_paint(paint);
+                    let mut draw_path = DrawPath::new(outline.clone().transformed(&transform), paint_id);
+                    draw_path.set_clip_path(clip.map(|idx| clips[idx]));
+                    draw_path.set_fill_rule(fill_rule);
+                    draw_path.set_blend_mode(blend_mode);
+                    scene.push_draw_path(draw_path);
+                }
+                Item::Image { ref outline, ref image, transform: image_transform, blend_mode, clip } => {
+                    let mut pattern = Pattern::from_image(image.clone());
+                    pattern.apply_transform(transform * image_transform);
+                    let paint_id = scene.push_paint(&Paint::from_pattern(pattern));
+                    let mut draw_path = DrawPath::new(outline.clone().transformed(&transform), paint_id);
+                    draw_path.set_clip_path(clip.map(|idx| clips[idx]));
+                    draw_path.set_blend_mode(blend_mode);
+                    scene.push_draw_path(draw_path);
+                }
+            }
+        }
+        scene
+    }
+}
+
+/// Approximate memory used by the points of `outline` in bytes.
+pub(crate) fn outline_size(outline: &Outline) -> usize {
+    let points: usize = outline.contours().iter().map(|c| c.len() as usize).sum();
+    points * (std::mem::size_of::<Vector2F>() + 1)
+}
+
+impl ValueSize for DisplayList {
+    /// Includes the images, even though they are shared with the image cache.
+    fn size(&self) -> usize {
+        let clips: usize = self.clip_paths.iter().map(|c| outline_size(&c.outline)).sum();
+        let items: usize = self.items.iter().map(|item| match *item {
+            Item::Path { ref outline, .. } => outline_size(outline),
+            Item::Image { ref outline, ref image, .. } => {
+                outline_size(outline) + image.pixels().len() * std::mem::size_of::<ColorU>()
+            }
+        }).sum();
+        std::mem::size_of::<Self>() + self.items.len() * std::mem::size_of::<Item>() + clips + items
+    }
+}
+
+/// Records into a `DisplayList`, like `SceneBackend` draws into a `Scene`.
+struct Recorder<'a> {
+    cache: &'a Cache,
+    list: DisplayList,
+}
+
+impl<'a> Recorder<'a> {
+    fn push_path(&mut self, outline: Outline, fill: &backend::FillMode, fill_rule: FillRule, clip: Option<usize>) {
+        self.list.items.push(Item::Path {
+            outline,
+            paint: paint(fill.color, fill.alpha),
+            fill_rule,
+            blend_mode: blend_mode(fill.mode),
+            clip,
+        });
+    }
+}
+
+impl<'a> Backend for Recorder<'a> {
+    type ClipPathId = usize;
+
+    fn create_clip_path(&mut self, path: Outline, fill_rule: FillRule, parent: Option<usize>) -> usize {
+        self.list.clip_paths.push(RecordedClip { outline: path, fill_rule, parent });
+        self.list.clip_paths.len() - 1
+    }
+    fn draw(&mut self, outline: &Outline, mode: &DrawMode, fill_rule: FillRule, transform: Transform2F, clip: Option<usize>) {
+        match mode {
+            DrawMode::Fill { fill } | DrawMode::FillStroke { fill, .. } => {
+                self.push_path(outline.clone().transformed(&transform), fill, fill_rule, clip);
+            }
+            _ => {}
+        }
+        match mode {
+            DrawMode::Stroke { stroke, stroke_mode } | DrawMode::FillStroke { stroke, stroke_mode, .. } => {
+                let contour = stroke_outline(outline, stroke_mode).transformed(&transform);
+                self.push_path(contour, stroke, fill_rule, clip);
+            }
+            _ => {}
+        }
+    }
+    fn set_view_box(&mut self, view_box: RectF) {
+        self.list.view_box = view_box;
+    }
+    fn draw_image(&mut self, xobject_ref: Ref<XObject>, im: &ImageXObject, resources: &Resources, transform: Transform2F, mode: backend::BlendMode, clip: Option<usize>, resolve: &impl Resolve) {
+        if let Ok(ref image) = *self.cache.get_image(xobject_ref, im, resources, resolve, mode).0 {
+            let size = image.size().to_f32();
+            let outline = Outline::from_rect(transform * RectF::new(Vector2F::default(), Vector2F::new(1.0, 1.0)));
+            let image_transform = transform
+                * Transform2F::from_scale(Vector2F::new(1.0 / size.x(), -1.0 / size.y()))
+                * Transform2F::from_translation(Vector2F::new(0.0, -size.y()));
+            self.list.items.push(Item::Image {
+                outline,
+                image: image.clone(),
+                transform: image_transform,
+                blend_mode: blend_mode(mode),
+                clip,
+            });
+        }
+    }
+    fn draw_inline_image(&mut self, _im: &Arc<ImageXObject>, _resources: &Resources, _transform: Transform2F, _mode: backend::BlendMode, _clip: Option<usize>, _resolve: &impl Resolve) {
+    }
+    fn get_font(&mut self, font_ref: &MaybeRef<PdfFont>, resolve: &impl Resolve, limits: &Limits) -> Result<Option<Arc<FontEntry>>, RenderError> {
+        self.cache.get_font(font_ref, resolve, limits)
+    }
+    fn add_text(&mut self, _span: TextSpan, _clip: Option<usize>) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pdf::file::FileOptions;
+
+    use crate::testutil::PdfBuilder;
+    use crate::StaticFontProvider;
+
+    /// A 100×100 pt page with a clip path, one square at the bottom left and one at the top right.
+    fn record() -> DisplayList {
+        let mut pdf = PdfBuilder::new();
+        pdf.page((100., 100.), "", "q 0 0 100 50 re W n 0 0 10 10 re f Q 80 80 10 10 re f");
+        let file = FileOptions::cached().load(pdf.finish()).unwrap();
+        let page = file.get_page(0).unwrap();
+        let cache = Cache::with_font_provider(Arc::new(StaticFontProvider::new(&[])));
+        DisplayList::record(&page, &file.resolver(), &cache).unwrap()
+    }
+
+    #[test]
+    fn records_once() {
+        let list = record();
+        std::assert_eq!(list.items.len(), 2);
+        std::assert_eq!(list.clip_paths.len(), 1);
+        std::assert_eq!(list.items.iter().filter(|item| matches!(item, Item::Path { clip: Some(0), .. })).count(), 1);
+        let size = 100. * 25.4 / 72.;
+        assert!((list.view_box().size() - Vector2F::splat(size)).length() < 1e-3);
+        assert!(list.size() > std::mem::size_of::<DisplayList>());
+    }
+
+    #[test]
+    fn replay() {
+        let list = record();
+        let zoom = Transform2F::from_scale(Vector2F::splat(2.));
+        let scene = list.scene(zoom);
+        std::assert_eq!(scene.view_box(), zoom * list.view_box());
+        // the white background and both squares
+        std::assert_eq!(scene.draw_path_count(), 3);
+
+        // the top right quarter only has the second square
+        let size = scene.view_box().size();
+        let region = RectF::new(Vector2F::new(size.x() / 2., 0.), size / 2.);
+        let scene = list.scene_region(zoom, Some(region));
+        std::assert_eq!(scene.view_box(), region);
+        std::assert_eq!(scene.draw_path_count(), 2);
+    }
+
+    #[test]
+    fn failures_are_not_cached() {
+        let mut pdf = PdfBuilder::new();
+        // the pattern does not exist
+        pdf.page((100., 100.), "", "/Pattern cs /P0 scn 0 0 10 10 re f");
+        let file = FileOptions::cached().load(pdf.finish()).unwrap();
+        let page = file.get_page(0).unwrap();
+        let cache = Cache::with_font_provider(Arc::new(StaticFontProvider::new(&[])));
+        assert!(cache.get_display_list(&page, &file.resolver()).is_err());
+        assert!(cache.get_display_list(&page, &file.resolver()).is_err());
+        // recorded twice, and nothing was kept
+        let stats = cache.stats();
+        std::assert_eq!((stats.misses, stats.entries), (2, 0));
+    }
+}
@@ -0,0 +1,399 @@
+//! A serializable form of a trace, for tools outside of Rust.
+//!
+//! The types here mirror `tracer::DrawItem` and friends with plain data: outlines become
+//! path commands, transforms are `[a, b, c, d, e, f]` (PDF order), clip paths are listed
+//! once per page and referenced by index, fonts are referenced by name.
+//! `SCHEMA_VERSION` is bumped whenever the format changes incompatibly.
+
+use pathfinder_content::outline::{ContourIterFlags, Outline};
+use pathfinder_content::stroke::{LineCap, LineJoin};
+use pathfinder_content::fill::FillRule;
+use pathfinder_geometry::{rect::RectF, transform2d::Transform2F, vector::Vector2F};
+use pdf::content::TextMode;
+use pdf::object::PlainRef;
+use serde::{Deserialize, Serialize};
+
+use crate::backend::{BlendMode, FillMode, Stroke};
+use crate::tracer::{ClipPath, DrawItem};
+use crate::{Fill, TextSpan};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceDump {
+    pub version: u32,
+    pub pages: Vec<PageDump>,
+}
+impl TraceDump {
+    pub fn new(pages: Vec<PageDump>) -> Self {
+        TraceDump {
+            version: SCHEMA_VERSION,
+            pages,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageDump {
+    /// page number, starting at 0
+    pub page: u32,
+    /// `[x, y, width, height]`
+    pub view_box: [f32; 4],
+    pub clip_paths: Vec<ClipPathDump>,
+    pub items: Vec<ItemDump>,
+}
+impl PageDump {
+    pub fn new(page: u32, view_box: RectF, items: &[DrawItem], clip_paths: &[ClipPath]) -> Self {
+        PageDump {
+            page,
+            view_box: rect(view_box),
+            clip_paths: clip_paths.iter().map(ClipPathDump::from).collect(),
+            items: items.iter().map(ItemDump::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PathCommand {
+    MoveTo { x: f32, y: f32 },
+    LineTo { x: f32, y: f32 },
+    QuadTo { x1: f32, y1: f32, x: f32, y: f32 },
+    CubicTo { x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32 },
+    Close,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FillRuleDump {
+    Winding,
+    EvenOdd,
+}
+impl From<FillRule> for FillRuleDump {
+    fn from(rule: FillRule) -> Self {
+        match rule {
+            FillRule::Winding => FillRuleDump::Winding,
+            FillRule::EvenOdd => FillRuleDump::EvenOdd,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipPathDump {
+    pub path: Vec<PathCommand>,
+    pub fill_rule: FillRuleDump,
+    /// index of the enclosing clip path
+    pub parent: Option<usize>,
+}
+impl From<&ClipPath> for ClipPathDump {
+    fn from(c: &ClipPath) -> Self {
+        ClipPathDump {
+            path: path(&c.path),
+            fill_rule: c.fill_rule.into(),
+            parent: c.parent.map(|id| id.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PaintDump {
+    /// rgb in 0..1
+    Solid { color: [f32; 3], alpha: f32, overprint: bool },
+    /// a pattern, by object number and generation
+    Pattern { id: u64, gen: u64, alpha: f32, overprint: bool },
+}
+impl From<&FillMode> for PaintDump {
+    fn from(f: &FillMode) -> Self {
+        let overprint = matches!(f.mode, BlendMode::Darken);
+        match f.color {
+            Fill::Solid(r, g, b) => PaintDump::Solid { color: [r, g, b], alpha: f.alpha, overprint },
+            Fill::Pattern(re) => {
+                let re = re.get_inner();
+                PaintDump::Pattern { id: re.id as u64, gen: re.gen as u64, alpha: f.alpha, overprint }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrokeDump {
+    pub paint: PaintDump,
+    pub line_width: f32,
+    /// "butt", "square" or "round"
+    pub line_cap: String,
+    /// "miter", "bevel" or "round"
+    pub line_join: String,
+    pub miter_limit: Option<f32>,
+    /// dash lengths and phase
+    pub dash: Option<(Vec<f32>, f32)>,
+}
+impl StrokeDump {
+    fn new(paint: &FillMode, stroke: &Stroke) -> Self {
+        let style = &stroke.style;
+        let (line_join, miter_limit) = match style.line_join {
+            LineJoin::Miter(limit) => ("miter", Some(limit)),
+            LineJoin::Bevel => ("bevel", None),
+            LineJoin::Round => ("round", None),
+        };
+        StrokeDump {
+            paint: paint.into(),
+            line_width: style.line_width,
+            line_cap: match style.line_cap {
+                LineCap::Butt => "butt",
+                LineCap::Square => "square",
+                LineCap::Round => "round",
+            }
+            .into(),
+            line_join: line_join.into(),
+            miter_limit,
+            dash: stroke.dash_pattern.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharDump {
+    /// byte offset in the text
+    pub offset: usize,
+    /// position along the baseline in text space
+    pub pos: f32,
+    pub width: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ItemDump {
+    Vector {
+        path: Vec<PathCommand>,
+        fill: Option<PaintDump>,
+        stroke: Option<StrokeDump>,
+        fill_rule: FillRuleDump,
+        transform: [f32; 6],
+        clip: Option<usize>,
+        op_nr: usize,
+        /// marked-content identifier, links the item to the structure tree
+        mcid: Option<u32>,
+        /// object number and generation of the form xobject `mcid` belongs to, `None` for the page
+        mcid_stream: Option<(u64, u64)>,
+    },
+    Image {
+        rect: [f32; 4],
+        /// object number and generation of the image XObject
+        id: u64,
+        gen: u64,
+        transform: [f32; 6],
+        overprint: bool,
+        clip: Option<usize>,
+        op_nr: usize,
+        /// marked-content identifier, links the item to the structure tree
+        mcid: Option<u32>,
+        /// object number and generation of the form xobject `mcid` belongs to, `None` for the page
+        mcid_stream: Option<(u64, u64)>,
+    },
+    InlineImage {
+        rect: [f32; 4],
+        width: u32,
+        height: u32,
+        transform: [f32; 6],
+        overprint: bool,
+        clip: Option<usize>,
+        op_nr: usize,
+        /// marked-content identifier, links the item to the structure tree
+        mcid: Option<u32>,
+        /// object number and generation of the form xobject `mcid` belongs to, `None` for the page
+        mcid_stream: Option<(u64, u64)>,
+    },
+    Text {
+        text: String,
+        chars: Vec<CharDump>,
+        rect: [f32; 4],
+        bbox: Option<[f32; 4]>,
+        /// PostScript name of the font
+        font: Option<String>,
+        font_size: f32,
+        /// width in text space
+        width: f32,
+        color: PaintDump,
+        /// "fill", "stroke", "fill_then_stroke", "invisible", "fill_and_clip" or "stroke_and_clip"
+        mode: String,
+        transform: [f32; 6],
+        clip: Option<usize>,
+        op_nr: usize,
+        /// marked-content identifier, links the item to the structure tree
+        mcid: Option<u32>,
+        /// object number and generation of the form xobject `mcid` belongs to, `None` for the page
+        mcid_stream: Option<(u64, u64)>,
+    },
+}
+impl From<&DrawItem> for ItemDump {
+    fn from(item: &DrawItem) -> Self {
+        match item {
+            DrawItem::Vector(v) => ItemDump::Vector {
+                path: path(&v.outline),
+                fill: v.fill.as_ref().map(PaintDump::from),
+                stroke: v.stroke.as_ref().map(|(paint, stroke)| StrokeDump::new(paint, stroke)),
+                fill_rule: v.fill_rule.into(),
+                transform: transform(v.transform),
+                clip: v.clip.map(|id| id.0),
+                op_nr: v.op_nr,
+                mcid: v.mcid,
+                mcid_stream: v.mcid_stream.map(object_ref),
+            },
+            DrawItem::Image(im) => {
+                let re = im.id.get_inner();
+                ItemDump::Image {
+                    rect: rect(im.rect),
+                    id: re.id as u64,
+                    gen: re.gen as u64,
+                    transform: transform(im.transform),
+                    overprint: matches!(im.mode, BlendMode::Darken),
+                    clip: im.clip.map(|id| id.0),
+                    op_nr: im.op_nr,
+                    mcid: im.mcid,
+                    mcid_stream: im.mcid_stream.map(object_ref),
+                }
+            }
+            DrawItem::InlineImage(im) => ItemDump::InlineImage {
+                rect: rect(im.rect),
+                width: im.im.width,
+                height: im.im.height,
+                transform: transform(im.transform),
+                overprint: matches!(im.mode, BlendMode::Darken),
+                clip: im.clip.map(|id| id.0),
+                op_nr: im.op_nr,
+                mcid: im.mcid,
+                mcid_stream: im.mcid_stream.map(object_ref),
+            },
+            DrawItem::Text(span, clip) => text(span, clip.map(|id| id.0)),
+        }
+    }
+}
+
+fn text(span: &TextSpan, clip: Option<usize>) -> ItemDump {
+    ItemDump::Text {
+        text: span.text.clone(),
+        chars: span.chars.iter().map(|c| CharDump { offset: c.offset, pos: c.pos, width: c.width }).collect(),
+        rect: rect(span.rect),
+        bbox: span.bbox.map(rect),
+        font: span.font.as_ref().map(|e| e.name.clone()),
+        font_size: span.font_size,
+        width: span.width,
+        color: PaintDump::from(&FillMode {
+            color: span.color,
+            alpha: span.alpha,
+            mode: BlendMode::Overlay,
+        }),
+        mode: match span.mode {
+            TextMode::Fill => "fill",
+            TextMode::Stroke => "stroke",
+            TextMode::FillThenStroke => "fill_then_stroke",
+            TextMode::Invisible => "invisible",
+            TextMode::FillAndClip => "fill_and_clip",
+            TextMode::StrokeAndClip => "stroke_and_clip",
+        }
+        .into(),
+        transform: transform(span.transform),
+        clip,
+        op_nr: span.op_nr,
+        mcid: span.mcid,
+        mcid_stream: span.mcid_stream.map(object_ref),
+    }
+}
+
+fn object_ref(r: PlainRef) -> (u64, u64) {
+    (r.id as u64, r.gen as u64)
+}
+
+fn rect(r: RectF) -> [f32; 4] {
+    [r.min_x(), r.min_y(), r.width(), r.height()]
+}
+
+fn transform(t: Transform2F) -> [f32; 6] {
+    [t.m11(), t.m21(), t.m12(), t.m22(), t.m13(), t.m23()]
+}
+
+fn point(p: Vector2F) -> (f32, f32) {
+    (p.x(), p.y())
+}
+
+/// The outline as path commands.
+pub fn path(outline: &Outline) -> Vec<PathCommand> {
+    let mut commands = vec![];
+    for contour in outline.contours() {
+        if contour.is_empty() {
+            continue;
+        }
+        let (x, y) = point(contour.position_of(0));
+        commands.push(PathCommand::MoveTo { x, y });
+        for segment in contour.iter(ContourIterFlags::empty()) {
+            let (x, y) = point(segment.baseline.to());
+            if segment.is_cubic() {
+                let (x1, y1) = point(segment.ctrl.from());
+                let (x2, y2) = point(segment.ctrl.to());
+                commands.push(PathCommand::CubicTo { x1, y1, x2, y2, x, y });
+            } else if segment.is_quadratic() {
+                let (x1, y1) = point(segment.ctrl.from());
+                commands.push(PathCommand::QuadTo { x1, y1, x, y });
+            } else {
+                commands.push(PathCommand::LineTo { x, y });
+            }
+        }
+        if contour.is_closed() {
+            commands.push(PathCommand::Close);
+        }
+    }
+    commands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::span;
+
+    #[test]
+    fn round_trip() {
+        let items = vec![span("Hi", 10., 30., 10.)];
+        let view_box = RectF::new(Vector2F::zero(), Vector2F::new(200., 100.));
+        let dump = TraceDump::new(vec![PageDump::new(4, view_box, &items, &[])]);
+        let json = serde_json::to_string(&dump).unwrap();
+        assert!(json.contains(r#""type":"text""#));
+        assert!(json.contains(r#""mode":"fill""#));
+
+        let dump: TraceDump = serde_json::from_str(&json).unwrap();
+        std::assert_eq!(dump.version, SCHEMA_VERSION);
+        let page = &dump.pages[0];
+        std::assert_eq!(page.page, 4);
+        std::assert_eq!(page.view_box, [0., 0., 200., 100.]);
+        match &page.items[..] {
+            [ItemDump::Text { text, chars, rect, font, transform, color, clip, .. }] => {
+                std::assert_eq!(text, "Hi");
+                std::assert_eq!(chars.iter().map(|c| (c.offset, c.pos)).collect::<Vec<_>>(), [(0, 0.), (1, 5.)]);
+                std::assert_eq!(*rect, [10., 20., 10., 10.]);
+                std::assert_eq!(*font, None);
+                std::assert_eq!(*transform, [1., 0., 0., 1., 10., 30.]);
+                assert!(matches!(color, PaintDump::Solid { color: [0., 0., 0.], alpha, overprint: false } if *alpha == 1.0));
+                std::assert_eq!(*clip, None);
+            }
+            items => panic!("unexpected items {:?}", items),
+        }
+    }
+
+    #[test]
+    fn path_commands() {
+        let outline = Outline::from_rect(RectF::new(Vector2F::new(1., 2.), Vector2F::new(3., 4.)));
+        let commands = path(&outline);
+        assert!(matches!(commands.first(), Some(PathCommand::MoveTo { x, y }) if (*x, *y) == (1., 2.)));
+        assert!(commands[1..commands.len() - 1].iter().all(|c| matches!(c, PathCommand::LineTo { .. })));
+        assert!(matches!(commands.last(), Some(PathCommand::Close)));
+        std::assert_eq!(serde_json::to_string(&commands[0]).unwrap(), r#"{"op":"move_to","x":1.0,"y":2.0}"#);
+        assert!(path(&Outline::new()).is_empty());
+    }
+
+    #[test]
+    fn transform_order() {
+        let t = Transform2F::row_major(1., 2., 3., 4., 5., 6.);
+        // PDF order is a b c d e f, with x' = a x + c y + e
+        std::assert_eq!(transform(t), [1., 4., 2., 5., 3., 6.]);
+    }
+}
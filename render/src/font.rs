@@ -1,86 +1,136 @@
-use glyphmatcher::FontDb;
 use pdf::error::{PdfError, Result};
-use pdf::font::Font as PdfFont;
+use pdf::font::{Font as PdfFont, FontData, FontDescriptor};
 use pdf::object::*;
-use std::collections::HashMap;
 use std::ops::Deref;
-use std::path::PathBuf;
 
 use super::FontEntry;
-use font::{self};
-use globalcache::{sync::SyncCache, ValueSize};
+use crate::fontprovider::{FontMatch, FontProvider, FontQuery, FontSubstitution, MatchKind};
+use crate::limits::{LimitError, Limits, RenderError};
+use font::{self, FontError};
+use globalcache::ValueSize;
+use crate::displaylist::outline_size;
 use std::hash::{Hash, Hasher};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 #[derive(Clone)]
-pub struct FontRc(Arc<dyn font::Font + Send + Sync + 'static>);
+pub struct FontRc {
+    font: Arc<dyn font::Font + Send + Sync + 'static>,
+    size: usize,
+}
 impl ValueSize for FontRc {
+    /// Approximate memory used by the font in bytes.
     #[inline]
     fn size(&self) -> usize {
-        1 // TODO
+        self.size
     }
 }
-impl From<Box<dyn font::Font + Send + Sync + 'static>> for FontRc {
-    #[inline]
-    fn from(f: Box<dyn font::Font + Send + Sync + 'static>) -> Self {
-        FontRc(f.into())
+impl FontRc {
+    /// Wrap a font parsed from `data_len` bytes.
+    ///
+    /// The size includes the outlines the parser keeps. Decoding all of them would be slow,
+    /// so it is the glyph count times the average size of a few sampled glyphs.
+    pub fn new(f: Box<dyn font::Font + Send + Sync + 'static>, data_len: usize) -> Self {
+        FontRc {
+            size: std::mem::size_of_val(&*f) + data_len + outlines_size(&*f),
+            font: f.into(),
+        }
+    }
+}
+
+/// Number of glyphs decoded to estimate the average outline size.
+const SAMPLE_GLYPHS: u32 = 16;
+
+/// Approximate memory used by all glyph outlines of `font` in bytes.
+fn outlines_size(font: &(dyn font::Font + Send + Sync)) -> usize {
+    let num_glyphs = font.num_glyphs();
+    let step = (num_glyphs / SAMPLE_GLYPHS).max(1);
+    let (sampled, total) = (0..num_glyphs)
+        .step_by(step as usize)
+        .take(SAMPLE_GLYPHS as usize)
+        .filter_map(|gid| font.glyph(font::GlyphId(gid)))
+        .fold((0, 0), |(n, total), glyph| (n + 1, total + outline_size(&glyph.path)));
+    match sampled {
+        0 => 0,
+        n => total / n * num_glyphs as usize,
     }
 }
+
 impl Deref for FontRc {
     type Target = dyn font::Font + Send + Sync + 'static;
     #[inline]
     fn deref(&self) -> &Self::Target {
-        &*self.0
+        &*self.font
     }
 }
 impl PartialEq for FontRc {
     #[inline]
     fn eq(&self, rhs: &Self) -> bool {
-        Arc::as_ptr(&self.0) == Arc::as_ptr(&rhs.0)
+        Arc::as_ptr(&self.font) == Arc::as_ptr(&rhs.font)
     }
 }
 impl Eq for FontRc {}
 impl Hash for FontRc {
     #[inline]
     fn hash<H: Hasher>(&self, state: &mut H) {
-        Arc::as_ptr(&self.0).hash(state)
+        Arc::as_ptr(&self.font).hash(state)
     }
 }
+/// Loads fonts from a `FontProvider` for fonts that are not embedded.
+///
+/// The parsed fonts are kept by the `Cache` or `TraceCache` that uses this, so they count
+/// against its memory limit like all other fonts.
 pub struct StandardCache {
-    inner: Arc<SyncCache<String, Option<FontRc>>>,
-    dir: PathBuf,
-    fonts: HashMap<String, String>,
+    provider: Arc<dyn FontProvider>,
     dump: Dump,
-    font_db: Option<FontDb>,
     require_unique_unicode: bool,
+    substitutions: Mutex<Vec<FontSubstitution>>,
 }
 impl StandardCache {
-    pub fn new(dir: PathBuf) -> Self {
-        let data = std::fs::read_to_string(dir.join("fonts.json")).expect("can't read fonts.json");
-        let fonts: HashMap<String, String> =
-            serde_json::from_str(&data).expect("fonts.json is invalid");
-
+    pub fn new(provider: Arc<dyn FontProvider>) -> Self {
         let dump = match std::env::var("DUMP_FONT").as_deref() {
             Err(_) => Dump::Never,
             Ok("always") => Dump::Always,
             Ok("error") => Dump::OnError,
             Ok(_) => Dump::Never,
         };
-        let db_path = dir.join("db");
-        let font_db = db_path.is_dir().then(|| FontDb::new(db_path));
 
         StandardCache {
-            inner: SyncCache::new(),
-            dir,
-            fonts,
+            provider,
             dump,
-            font_db,
             require_unique_unicode: false,
+            substitutions: Mutex::new(Vec::new()),
+        }
+    }
+    /// Load and parse the font `key` of the provider.
+    pub fn load(&self, key: &str) -> Option<FontRc> {
+        let data = match self.provider.load(key) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("can't load {}: {:?}", key, e);
+                return None;
+            }
+        };
+        match font::parse(&data) {
+            Ok(f) => Some(FontRc::new(f, data.len())),
+            Err(e) => {
+                warn!("Font Error in {}: {:?}", key, e);
+                None
+            }
         }
     }
     pub fn require_unique_unicode(&mut self, r: bool) {
         self.require_unique_unicode = r;
     }
+    fn record_substitution(&self, s: &FontSubstitution) {
+        let mut substitutions = self.substitutions.lock().unwrap();
+        if !substitutions.iter().any(|t| t.requested == s.requested) {
+            substitutions.push(s.clone());
+        }
+    }
+    /// Fonts that were replaced by a different font, once per requested name.
+    pub fn substitutions(&self) -> Vec<FontSubstitution> {
+        self.substitutions.lock().unwrap().clone()
+    }
 }
 
 #[derive(Debug)]
@@ -90,20 +140,37 @@ enum Dump {
     Always,
 }
 
+/// The limit behind a parser error, also when it was wrapped in a context.
+fn font_limit(e: &FontError) -> Option<LimitError> {
+    match *e {
+        FontError::Limit(what, n) if what.contains("glyph") => Some(LimitError::Glyphs(u32::try_from(n).unwrap_or(u32::MAX))),
+        FontError::Limit(_, n) => Some(LimitError::FontData(n)),
+        FontError::Context(_, _, ref inner) => font_limit(inner),
+        _ => None,
+    }
+}
+
+/// Load `font_ref`. Fonts that are not embedded are looked up with `standard_font`,
+/// which returns the font for a key of the provider of `cache`.
 pub fn load_font(
     font_ref: &MaybeRef<PdfFont>,
     resolve: &impl Resolve,
     cache: &StandardCache,
-) -> Result<Option<FontEntry>> {
+    standard_font: impl Fn(&str) -> Option<FontRc>,
+    limits: &Limits,
+) -> Result<Option<FontEntry>, RenderError> {
     let pdf_font = font_ref.clone();
     debug!("loading {:?}", pdf_font);
+    let mut substitution = None;
+    let mut embedded_size = None;
 
     let font: FontRc = match pdf_font.embedded_data(resolve) {
         Some(Ok(data)) => {
             debug!("loading embedded font");
-            let font = font::parse(&data).map_err(|e| PdfError::Other {
-                msg: format!("Font Error: {:?}", e),
-            });
+            if data.len() > limits.max_font_data {
+                return Err(LimitError::FontData(data.len()).into());
+            }
+            let font = font::parse(&data);
             if matches!(cache.dump, Dump::Always)
                 || (matches!(cache.dump, Dump::OnError) && font.is_err())
             {
@@ -118,9 +185,17 @@ pub fn load_font(
                 std::fs::write(&name, &data).unwrap();
                 println!("font dumped in {}", name);
             }
-            FontRc::from(font?)
+            let font = font.map_err(|e| match font_limit(&e) {
+                Some(limit) => RenderError::Limit(limit),
+                None => PdfError::Other { msg: format!("Font Error: {:?}", e) }.into(),
+            })?;
+            if font.num_glyphs() > limits.max_glyphs {
+                return Err(LimitError::Glyphs(font.num_glyphs()).into());
+            }
+            embedded_size = Some(data.len());
+            FontRc::new(font, data.len())
         }
-        Some(Err(e)) => return Err(e),
+        Some(Err(e)) => return Err(e.into()),
         None => {
             debug!("no embedded font.");
             let name = match pdf_font.name {
@@ -128,24 +203,27 @@ pub fn load_font(
                 None => return Ok(None),
             };
             debug!("loading {name} instead");
-            match cache.fonts.get(name).or_else(|| cache.fonts.get("Arial")) {
-                Some(file_name) => {
-                    let val = cache.inner.get(file_name.clone(), |_| {
-                        let data = match std::fs::read(cache.dir.join(file_name)) {
-                            Ok(data) => data,
-                            Err(e) => {
-                                warn!("can't open {} for {:?} {:?}", file_name, pdf_font.name, e);
-                                return None;
-                            }
-                        };
-                        match font::parse(&data) {
-                            Ok(f) => Some(f.into()),
-                            Err(e) => {
-                                warn!("Font Error: {:?}", e);
-                                return None;
-                            }
-                        }
+            let provider = &cache.provider;
+            let query = font_query(font_ref, name, resolve);
+            let found = provider.find(&query).or_else(|| {
+                provider.resolve("Arial").map(|key| FontMatch { key, kind: MatchKind::Fallback })
+            });
+            if let Some(ref found) = found {
+                if found.kind != MatchKind::Exact {
+                    info!("using {} for {} ({:?})", found.key, query.name, found.kind);
+                    // cleared below if the substitute has the style already
+                    substitution = Some(FontSubstitution {
+                        requested: query.name.clone(),
+                        key: found.key.clone(),
+                        kind: found.kind,
+                        synthetic_bold: wants_bold(&query),
+                        synthetic_oblique: wants_italic(&query),
                     });
+                }
+            }
+            match found.map(|m| m.key) {
+                Some(key) => {
+                    let val = standard_font(&key);
                     match val {
                         Some(f) => f,
                         None => {
@@ -161,11 +239,127 @@ pub fn load_font(
         }
     };
 
-    Ok(Some(FontEntry::build(
+    if let Some(ref mut s) = substitution {
+        s.synthetic_bold &= !is_bold(&font);
+        s.synthetic_oblique &= !is_italic(&font);
+        cache.record_substitution(s);
+    }
+
+    let mut entry = FontEntry::build(
         font,
         pdf_font,
-        cache.font_db.as_ref(),
+        cache.provider.font_db(),
         resolve,
         cache.require_unique_unicode,
-    )?))
+    )?;
+    entry.substitution = substitution;
+    entry.embedded_size = embedded_size;
+    if embedded_size.is_none() && entry.widths.is_none() && !entry.is_cid {
+        entry.metrics = pdf_encoding::standard_metrics(&entry.name);
+    }
+    Ok(Some(entry))
+}
+
+fn font_descriptor(font: &PdfFont) -> Option<&FontDescriptor> {
+    match font.data {
+        FontData::Type1(ref t) | FontData::TrueType(ref t) => t.font_descriptor.as_ref(),
+        FontData::CIDFontType0(ref c) | FontData::CIDFontType2(ref c) => Some(&c.font_descriptor),
+        FontData::Type0(ref t) => t.descendant_fonts.get(0).and_then(|f| font_descriptor(f)),
+        _ => None,
+    }
+}
+
+/// Panose classification from the /Style dictionary of the font descriptor.
+///
+/// `FontDescriptor` does not keep /Style, so this reads the raw dictionaries.
+fn panose(font_ref: &MaybeRef<PdfFont>, resolve: &impl Resolve) -> Option<[u8; 10]> {
+    let plain_ref = match font_ref {
+        MaybeRef::Indirect(re) => re.get_ref().get_inner(),
+        MaybeRef::Direct(_) => return None,
+    };
+    let dict = resolve.resolve(plain_ref).ok()?.into_dictionary().ok()?;
+    let dict = match dict.get("DescendantFonts") {
+        Some(fonts) => fonts.clone().resolve(resolve).ok()?
+            .into_array().ok()?
+            .into_iter().next()?
+            .resolve(resolve).ok()?
+            .into_dictionary().ok()?,
+        None => dict,
+    };
+    let descriptor = dict.get("FontDescriptor")?.clone().resolve(resolve).ok()?.into_dictionary().ok()?;
+    let style = descriptor.get("Style")?.clone().resolve(resolve).ok()?.into_dictionary().ok()?;
+    let panose = style.get("Panose")?.as_string().ok()?.as_bytes();
+    // sFamilyClass followed by the ten Panose digits
+    panose.get(2..12)?.try_into().ok()
+}
+
+fn wants_bold(query: &FontQuery) -> bool {
+    const FORCE_BOLD: u32 = 1 << 18;
+    let name = query.name.to_ascii_lowercase();
+    query.weight.map(|w| w >= 600.).unwrap_or(false)
+        || query.flags & FORCE_BOLD != 0
+        || ["bold", "black", "heavy", "demi"].iter().any(|s| name.contains(s))
+}
+fn wants_italic(query: &FontQuery) -> bool {
+    let name = query.name.to_ascii_lowercase();
+    query.italic() || name.contains("italic") || name.contains("oblique")
+}
+fn style_name(font: &FontRc) -> String {
+    let name = font.name();
+    let mut s = String::new();
+    for part in [&name.subfamily, &name.postscript_name, &name.full_name].iter().copied().flatten() {
+        s.push_str(&part.to_ascii_lowercase());
+        s.push(' ');
+    }
+    s
+}
+fn is_bold(font: &FontRc) -> bool {
+    let name = style_name(font);
+    font.info().weight.map(|w| w >= 600).unwrap_or(false)
+        || ["bold", "black", "heavy", "demi"].iter().any(|s| name.contains(s))
+}
+fn is_italic(font: &FontRc) -> bool {
+    let name = style_name(font);
+    name.contains("italic") || name.contains("oblique")
+}
+
+fn font_query(font_ref: &MaybeRef<PdfFont>, name: &str, resolve: &impl Resolve) -> FontQuery {
+    let mut query = FontQuery::new(name);
+    if let Some(desc) = font_descriptor(font_ref) {
+        query.family = desc.font_family.as_ref().map(|s| s.to_string_lossy());
+        query.weight = desc.font_weight;
+        query.italic_angle = desc.italic_angle;
+        query.flags = desc.flags;
+    }
+    query.panose = panose(font_ref, resolve);
+    query
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requested_style() {
+        assert!(wants_bold(&FontQuery::new("Arial,Bold")));
+        assert!(wants_bold(&FontQuery::new("Helvetica-Black")));
+        assert!(wants_bold(&FontQuery { weight: Some(600.), ..FontQuery::new("F1") }));
+        assert!(wants_bold(&FontQuery { flags: 1 << 18, ..FontQuery::new("F1") }));
+        assert!(!wants_bold(&FontQuery { weight: Some(500.), ..FontQuery::new("Arial") }));
+
+        assert!(wants_italic(&FontQuery::new("Arial,Italic")));
+        assert!(wants_italic(&FontQuery::new("Helvetica-BoldOblique")));
+        assert!(wants_italic(&FontQuery { italic_angle: -12., ..FontQuery::new("F1") }));
+        assert!(wants_italic(&FontQuery { flags: 1 << 6, ..FontQuery::new("F1") }));
+        assert!(!wants_italic(&FontQuery::new("Arial-Bold")));
+    }
+
+    #[test]
+    fn parser_limits() {
+        std::assert_eq!(font_limit(&FontError::Limit("woff table size", 1 << 30)), Some(LimitError::FontData(1 << 30)));
+        std::assert_eq!(font_limit(&FontError::Limit("glyph count", 70_000)), Some(LimitError::Glyphs(70_000)));
+        let wrapped = FontError::Context("cff", 12, Box::new(FontError::Limit("string length", 5_000_000)));
+        std::assert_eq!(font_limit(&wrapped), Some(LimitError::FontData(5_000_000)));
+        std::assert_eq!(font_limit(&FontError::Require("head")), None);
+    }
 }
@@ -1,13 +1,15 @@
 use crate::font::FontRc;
+use crate::fontprovider::FontSubstitution;
 use font::{self, CffFont, GlyphId, OpenTypeFont, TrueTypeFont, Type1Font};
 use glyphmatcher::FontDb;
+use globalcache::ValueSize;
 use istring::SmallString;
 use itertools::Itertools;
 use pdf::encoding::BaseEncoding;
 use pdf::error::PdfError;
 use pdf::font::{CidToGidMap, Font as PdfFont, Widths};
 use pdf::object::{MaybeRef, Resolve};
-use pdf_encoding::{glyphname_to_unicode, Encoding};
+use pdf_encoding::{glyphname_to_unicode, Encoding, FontMetrics};
 use std::collections::{HashMap, HashSet};
 
 pub struct FontEntry {
@@ -17,6 +19,12 @@ pub struct FontEntry {
     pub widths: Option<Widths>,
     pub is_cid: bool,
     pub name: String,
+    /// set if the font is not embedded and a different font is used in its place
+    pub substitution: Option<FontSubstitution>,
+    /// built-in metrics of a standard 14 font that is not embedded and has no /Widths
+    pub metrics: Option<&'static FontMetrics>,
+    /// size of the embedded font program in bytes, `None` if the font is not embedded
+    pub embedded_size: Option<usize>,
 }
 
 impl FontEntry {
@@ -388,12 +396,22 @@ impl FontEntry {
             is_cid,
             widths,
             name,
+            substitution: None,
+            metrics: None,
+            embedded_size: None,
         })
     }
 }
 
-impl globalcache::ValueSize for FontEntry {
+impl ValueSize for FontEntry {
+    /// Includes the font itself only if it is embedded; standard fonts are shared and counted once by the cache.
     fn size(&self) -> usize {
-        1 // TODO
+        let cmap = self.cmap.capacity()
+            * (std::mem::size_of::<u16>() + std::mem::size_of::<(GlyphId, Option<SmallString>)>());
+        let font = match self.embedded_size {
+            Some(_) => self.font.size(),
+            None => 0,
+        };
+        std::mem::size_of::<Self>() + font + cmap + self.name.len()
     }
 }
@@ -0,0 +1,316 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use font::FontInfo;
+use pdf::error::PdfError;
+
+use crate::fontprovider::{FontMatch, FontProvider, FontQuery, MatchKind};
+
+/// How deep `FontIndex::scan` descends into subdirectories.
+const MAX_SCAN_DEPTH: usize = 16;
+
+pub struct IndexedFont {
+    pub path: PathBuf,
+    pub info: FontInfo,
+}
+impl IndexedFont {
+    fn italic(&self) -> bool {
+        let subfamily = self.info.name.subfamily.as_deref().map(normalize).unwrap_or_default();
+        self.info.italic
+            || matches!(self.info.italic_angle, Some(a) if a != 0.)
+            || subfamily.contains("italic")
+            || subfamily.contains("oblique")
+    }
+    fn serif(&self) -> Option<bool> {
+        // Panose family "Latin Text", serif styles 11-13 are sans serif
+        match self.info.panose {
+            Some(p) if p[0] == 2 => Some(!(11..=13).contains(&p[1])),
+            _ => None,
+        }
+    }
+    fn symbolic(&self) -> bool {
+        let family = self.info.name.family.as_deref().map(normalize).unwrap_or_default();
+        matches!(self.info.panose, Some(p) if p[0] == 5)
+            || family.contains("symbol")
+            || family.contains("dingbat")
+    }
+}
+
+/// Fonts found in directories on disk, e.g. the fonts installed on the system.
+///
+/// Non-embedded fonts are matched by PostScript name first. If there is no such font,
+/// the closest one by family, weight, slant, the descriptor flags and Panose is used.
+#[derive(Default)]
+pub struct FontIndex {
+    fonts: Vec<IndexedFont>,
+    // normalized postscript name -> index into fonts
+    by_name: HashMap<String, usize>,
+}
+impl FontIndex {
+    pub fn new() -> Self {
+        FontIndex::default()
+    }
+
+    /// Index the fonts in the usual font directories of the operating system.
+    pub fn system() -> Self {
+        let mut index = FontIndex::new();
+        for dir in system_font_dirs() {
+            index.scan(dir);
+        }
+        info!("indexed {} system fonts", index.fonts.len());
+        index
+    }
+
+    /// Add all TrueType, OpenType and PFB fonts in `dir` and its subdirectories.
+    /// Returns the number of fonts added.
+    pub fn scan(&mut self, dir: impl AsRef<Path>) -> usize {
+        let before = self.fonts.len();
+        self.scan_dir(dir.as_ref(), 0);
+        self.fonts.len() - before
+    }
+
+    fn scan_dir(&mut self, dir: &Path, depth: usize) {
+        if depth > MAX_SCAN_DEPTH {
+            return;
+        }
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                debug!("can't read {}: {}", dir.display(), e);
+                return;
+            }
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.is_dir() {
+                self.scan_dir(&path, depth + 1);
+            } else if is_font_file(&path) {
+                self.add_file(path);
+            }
+        }
+    }
+
+    /// Add a single font file. Returns false if it could not be read.
+    pub fn add_file(&mut self, path: PathBuf) -> bool {
+        let info = match std::fs::read(&path).ok().and_then(|data| font::font_info(&data)) {
+            Some(info) => info,
+            None => {
+                debug!("not a usable font: {}", path.display());
+                return false;
+            }
+        };
+        if let Some(ref ps_name) = info.name.postscript_name {
+            self.by_name.entry(normalize(ps_name)).or_insert(self.fonts.len());
+        }
+        self.fonts.push(IndexedFont { path, info });
+        true
+    }
+
+    pub fn fonts(&self) -> &[IndexedFont] {
+        &self.fonts
+    }
+
+    /// The font to use for `query` and how well it matches.
+    pub fn find_font(&self, query: &FontQuery) -> Option<(&IndexedFont, MatchKind)> {
+        if let Some(&idx) = self.by_name.get(&normalize(&query.name)) {
+            return Some((&self.fonts[idx], MatchKind::Exact));
+        }
+        self.fonts
+            .iter()
+            .map(|font| (font, score(query, font)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(font, _)| (font, MatchKind::Similar))
+    }
+}
+impl FontProvider for FontIndex {
+    fn resolve(&self, name: &str) -> Option<String> {
+        let &idx = self.by_name.get(&normalize(name))?;
+        Some(self.fonts[idx].path.to_string_lossy().into())
+    }
+    fn load(&self, key: &str) -> Result<Cow<'static, [u8]>, PdfError> {
+        std::fs::read(key).map(Cow::Owned).map_err(|e| PdfError::Other {
+            msg: format!("can't open {}: {}", key, e),
+        })
+    }
+    fn find(&self, query: &FontQuery) -> Option<FontMatch> {
+        self.find_font(query).map(|(font, kind)| FontMatch {
+            key: font.path.to_string_lossy().into(),
+            kind,
+        })
+    }
+}
+
+fn is_font_file(path: &Path) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => ["ttf", "otf", "pfb"].iter().any(|e| ext.eq_ignore_ascii_case(e)),
+        None => false,
+    }
+}
+
+fn system_font_dirs() -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let mut dirs = vec![];
+    if cfg!(target_os = "windows") {
+        let windir = std::env::var_os("WINDIR").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("C:\\Windows"));
+        dirs.push(windir.join("Fonts"));
+        if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+            dirs.push(PathBuf::from(local).join("Microsoft\\Windows\\Fonts"));
+        }
+    } else if cfg!(target_os = "macos") {
+        dirs.push("/System/Library/Fonts".into());
+        dirs.push("/Library/Fonts".into());
+        dirs.extend(home.map(|h| h.join("Library/Fonts")));
+    } else {
+        dirs.push("/usr/share/fonts".into());
+        dirs.push("/usr/local/share/fonts".into());
+        if let Some(home) = home {
+            dirs.push(home.join(".fonts"));
+            dirs.push(home.join(".local/share/fonts"));
+        }
+    }
+    dirs
+}
+
+/// lowercase ascii letters and digits only, so `Times New Roman,Bold` and `TimesNewRoman-Bold` compare equal
+fn normalize(name: &str) -> String {
+    name.chars().filter(|c| c.is_ascii_alphanumeric()).map(|c| c.to_ascii_lowercase()).collect()
+}
+
+/// The family part of a PostScript name like `Arial-BoldMT` or `Arial,Bold`.
+fn family_from_name(name: &str) -> &str {
+    name.split(|c| c == '-' || c == ',').next().unwrap_or(name)
+}
+
+fn weight_from_name(name: &str) -> f32 {
+    let name = normalize(name);
+    let weights = [
+        ("thin", 100.), ("extralight", 200.), ("ultralight", 200.), ("light", 300.),
+        ("medium", 500.), ("semibold", 600.), ("demibold", 600.), ("demi", 600.),
+        ("extrabold", 800.), ("ultrabold", 800.), ("black", 900.), ("heavy", 900.), ("bold", 700.),
+    ];
+    weights.iter().find(|(w, _)| name.contains(w)).map(|&(_, w)| w).unwrap_or(400.)
+}
+
+fn score(query: &FontQuery, font: &IndexedFont) -> f32 {
+    let mut score = 0.;
+
+    let family = normalize(query.family.as_deref().unwrap_or_else(|| family_from_name(&query.name)));
+    let font_family = font.info.name.family.as_deref()
+        .or_else(|| font.info.name.postscript_name.as_deref().map(family_from_name))
+        .map(normalize)
+        .unwrap_or_default();
+    if !family.is_empty() && !font_family.is_empty() {
+        if family == font_family {
+            score += 100.;
+        } else if family.contains(&font_family) || font_family.contains(&family) {
+            score += 50.;
+        }
+    }
+
+    let weight = query.weight.unwrap_or_else(|| weight_from_name(&query.name));
+    let font_weight = font.info.weight.map(|w| w as f32).unwrap_or(400.);
+    score -= (weight - font_weight).abs() / 10.;
+
+    let italic = query.italic() || normalize(&query.name).contains("italic") || normalize(&query.name).contains("oblique");
+    if italic != font.italic() {
+        score -= 30.;
+    }
+    if query.fixed_pitch() != font.info.fixed_pitch {
+        score -= 40.;
+    }
+    if matches!(font.serif(), Some(serif) if serif != query.serif()) {
+        score -= 20.;
+    }
+    if query.symbolic() != font.symbolic() {
+        score -= 50.;
+    }
+    if let (Some(a), Some(b)) = (query.panose, font.info.panose) {
+        // 0 means "any"
+        score += a.iter().zip(b.iter()).filter(|&(a, b)| *a != 0 && a == b).count() as f32 * 2.;
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use font::{FontType, Name};
+
+    fn font(ps_name: &str, family: &str, weight: u16, italic: bool, fixed_pitch: bool) -> IndexedFont {
+        IndexedFont {
+            path: PathBuf::from(format!("{}.ttf", ps_name)),
+            info: FontInfo {
+                name: Name {
+                    family: Some(family.into()),
+                    postscript_name: Some(ps_name.into()),
+                    ..Name::default()
+                },
+                typ: FontType::TrueType,
+                codepoints: vec![],
+                weight: Some(weight),
+                italic_angle: None,
+                italic,
+                fixed_pitch,
+                panose: None,
+            },
+        }
+    }
+
+    fn index(fonts: Vec<IndexedFont>) -> FontIndex {
+        let mut index = FontIndex::new();
+        for font in fonts {
+            let name = normalize(font.info.name.postscript_name.as_deref().unwrap());
+            index.by_name.insert(name, index.fonts.len());
+            index.fonts.push(font);
+        }
+        index
+    }
+
+    fn find(index: &FontIndex, query: &FontQuery) -> (String, MatchKind) {
+        let (font, kind) = index.find_font(query).unwrap();
+        (font.info.name.postscript_name.clone().unwrap(), kind)
+    }
+
+    #[test]
+    fn names() {
+        std::assert_eq!(normalize("Times New Roman,Bold"), normalize("TimesNewRoman-Bold"));
+        std::assert_eq!(family_from_name("Arial-BoldMT"), "Arial");
+        std::assert_eq!(family_from_name("Arial,Bold"), "Arial");
+        std::assert_eq!(weight_from_name("Arial-BoldMT"), 700.);
+        std::assert_eq!(weight_from_name("Roboto-ExtraBold"), 800.);
+        std::assert_eq!(weight_from_name("Roboto-SemiBold"), 600.);
+        std::assert_eq!(weight_from_name("Roboto"), 400.);
+    }
+
+    #[test]
+    fn exact_match_by_postscript_name() {
+        let index = index(vec![font("Arial-BoldMT", "Arial", 700, false, false)]);
+        let query = FontQuery::new("ABCDEF+Arial,Bold");
+        std::assert_eq!(find(&index, &query).1, MatchKind::Similar);
+        let query = FontQuery::new("ABCDEF+ArialBoldMT");
+        std::assert_eq!(find(&index, &query), ("Arial-BoldMT".into(), MatchKind::Exact));
+        assert!(index.resolve("arial-boldmt").is_some());
+    }
+
+    #[test]
+    fn similar_by_family_weight_and_style() {
+        let index = index(vec![
+            font("Lato-Regular", "Lato", 400, false, false),
+            font("Lato-Bold", "Lato", 700, false, false),
+            font("Lato-Italic", "Lato", 400, true, false),
+            font("Mono-Regular", "Mono", 400, false, true),
+        ]);
+        let find_similar = |query: FontQuery| {
+            let (name, kind) = find(&index, &query);
+            std::assert_eq!(kind, MatchKind::Similar);
+            name
+        };
+        std::assert_eq!(find_similar(FontQuery::new("Lato-Heavy")), "Lato-Bold");
+        std::assert_eq!(find_similar(FontQuery { weight: Some(400.), italic_angle: -10., ..FontQuery::new("Lato-Oblique") }), "Lato-Italic");
+        std::assert_eq!(find_similar(FontQuery { family: Some("Lato".into()), ..FontQuery::new("XYZ") }), "Lato-Regular");
+        // the family outweighs fixed pitch, without one the fixed pitch font wins
+        std::assert_eq!(find_similar(FontQuery { flags: 1, ..FontQuery::new("Lato-Medium") }), "Lato-Regular");
+        std::assert_eq!(find_similar(FontQuery { flags: 1, ..FontQuery::new("Courier") }), "Mono-Regular");
+    }
+}
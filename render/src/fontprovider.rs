@@ -0,0 +1,274 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use glyphmatcher::FontDb;
+use pdf::error::PdfError;
+
+/// What is known about a font that is not embedded in the document.
+#[derive(Debug, Clone, Default)]
+pub struct FontQuery {
+    /// PostScript name without the subset prefix
+    pub name: String,
+    /// /FontFamily of the font descriptor
+    pub family: Option<String>,
+    /// /FontWeight of the font descriptor
+    pub weight: Option<f32>,
+    /// /ItalicAngle of the font descriptor
+    pub italic_angle: f32,
+    /// /Flags of the font descriptor
+    pub flags: u32,
+    /// from the /Style dictionary of the font descriptor
+    pub panose: Option<[u8; 10]>,
+}
+impl FontQuery {
+    pub fn new(name: &str) -> Self {
+        FontQuery {
+            name: strip_subset(name).into(),
+            ..FontQuery::default()
+        }
+    }
+    pub fn fixed_pitch(&self) -> bool {
+        self.flags & (1 << 0) != 0
+    }
+    pub fn serif(&self) -> bool {
+        self.flags & (1 << 1) != 0
+    }
+    pub fn symbolic(&self) -> bool {
+        self.flags & (1 << 2) != 0 && self.flags & (1 << 5) == 0
+    }
+    pub fn italic(&self) -> bool {
+        self.flags & (1 << 6) != 0 || self.italic_angle != 0.
+    }
+}
+
+/// Remove the `ABCDEF+` prefix of font subsets.
+pub fn strip_subset(name: &str) -> &str {
+    match name.split_once('+') {
+        Some((tag, rest)) if tag.len() == 6 && tag.bytes().all(|b| b.is_ascii_uppercase()) => rest,
+        _ => name,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchKind {
+    /// found by name
+    Exact,
+    /// the closest font by family and style
+    Similar,
+    /// nothing matched, the default font was used
+    Fallback,
+}
+
+#[derive(Debug, Clone)]
+pub struct FontMatch {
+    pub key: String,
+    pub kind: MatchKind,
+}
+
+/// A font that was used in place of a missing one.
+#[derive(Debug, Clone)]
+pub struct FontSubstitution {
+    pub requested: String,
+    /// the key of the font that was used instead
+    pub key: String,
+    pub kind: MatchKind,
+    /// the font should be bold, but the substitute is not
+    pub synthetic_bold: bool,
+    /// the font should be italic, but the substitute is not
+    pub synthetic_oblique: bool,
+}
+
+/// Supplies fonts for PDF fonts that are not embedded in the document.
+///
+/// Lookups go through two steps so that several names (e.g. `Arial` and `Helvetica`) can share
+/// one parsed font: `resolve` maps a font name to a key, `load` returns the data for a key.
+pub trait FontProvider: Send + Sync {
+    /// The key of the font to use for `name`, if there is one.
+    fn resolve(&self, name: &str) -> Option<String>;
+
+    /// The font data for a key returned by `resolve`.
+    fn load(&self, key: &str) -> Result<Cow<'static, [u8]>, PdfError>;
+
+    /// Find a font for `query`. The default only looks at the name.
+    fn find(&self, query: &FontQuery) -> Option<FontMatch> {
+        self.resolve(&query.name).map(|key| FontMatch { key, kind: MatchKind::Exact })
+    }
+
+    /// Database used to fix up the unicode mapping of known fonts.
+    fn font_db(&self) -> Option<&FontDb> {
+        None
+    }
+}
+
+/// Fonts in a directory, listed in a `fonts.json` mapping font names to file names.
+///
+/// If the directory has a `db` subdirectory, it is used as the `FontDb`.
+pub struct DirectoryFontProvider {
+    dir: PathBuf,
+    fonts: HashMap<String, String>,
+    font_db: Option<FontDb>,
+}
+impl DirectoryFontProvider {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, PdfError> {
+        let dir = dir.into();
+        let index = dir.join("fonts.json");
+        let data = std::fs::read_to_string(&index).map_err(|e| PdfError::Other {
+            msg: format!("can't read {}: {}", index.display(), e),
+        })?;
+        let fonts = serde_json::from_str(&data).map_err(|e| PdfError::Other {
+            msg: format!("{} is invalid: {}", index.display(), e),
+        })?;
+
+        let db_path = dir.join("db");
+        let font_db = db_path.is_dir().then(|| FontDb::new(db_path));
+
+        Ok(DirectoryFontProvider { dir, fonts, font_db })
+    }
+
+    /// The directory in `STANDARD_FONTS`, or `fonts/` if it is not set.
+    pub fn from_env() -> Result<Self, PdfError> {
+        let dir = match std::env::var_os("STANDARD_FONTS") {
+            Some(path) => PathBuf::from(path),
+            None => {
+                info!("STANDARD_FONTS not set. using fonts/ instead.");
+                PathBuf::from("fonts")
+            }
+        };
+        DirectoryFontProvider::new(dir)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+impl FontProvider for DirectoryFontProvider {
+    fn resolve(&self, name: &str) -> Option<String> {
+        self.fonts.get(name).cloned()
+    }
+    fn load(&self, key: &str) -> Result<Cow<'static, [u8]>, PdfError> {
+        let path = self.dir.join(key);
+        std::fs::read(&path).map(Cow::Owned).map_err(|e| PdfError::Other {
+            msg: format!("can't open {}: {}", path.display(), e),
+        })
+    }
+    fn font_db(&self) -> Option<&FontDb> {
+        self.font_db.as_ref()
+    }
+}
+
+/// Fonts added at runtime, e.g. fetched by a wasm build.
+#[derive(Default)]
+pub struct MemoryFontProvider {
+    fonts: HashMap<String, Arc<[u8]>>,
+    aliases: HashMap<String, String>,
+}
+impl MemoryFontProvider {
+    pub fn new() -> Self {
+        MemoryFontProvider::default()
+    }
+    pub fn add(&mut self, name: impl Into<String>, data: impl Into<Arc<[u8]>>) {
+        self.fonts.insert(name.into(), data.into());
+    }
+    /// Use the font added as `target` for `name`.
+    pub fn alias(&mut self, name: impl Into<String>, target: impl Into<String>) {
+        self.aliases.insert(name.into(), target.into());
+    }
+}
+impl FontProvider for MemoryFontProvider {
+    fn resolve(&self, name: &str) -> Option<String> {
+        let name = self.aliases.get(name).map(|s| s.as_str()).unwrap_or(name);
+        self.fonts.contains_key(name).then(|| name.into())
+    }
+    fn load(&self, key: &str) -> Result<Cow<'static, [u8]>, PdfError> {
+        match self.fonts.get(key) {
+            Some(data) => Ok(Cow::Owned(data.to_vec())),
+            None => Err(PdfError::Other { msg: format!("no font {}", key) }),
+        }
+    }
+}
+
+/// Fonts compiled into the binary.
+///
+/// ```ignore
+/// static FONTS: StaticFontProvider = StaticFontProvider::new(&[
+///     ("Helvetica", include_bytes!("fonts/Helvetica.otf") as &[u8]),
+/// ]).aliases(&[("Arial", "Helvetica")]);
+/// ```
+pub struct StaticFontProvider {
+    fonts: &'static [(&'static str, &'static [u8])],
+    aliases: &'static [(&'static str, &'static str)],
+}
+impl StaticFontProvider {
+    pub const fn new(fonts: &'static [(&'static str, &'static [u8])]) -> Self {
+        StaticFontProvider { fonts, aliases: &[] }
+    }
+    pub const fn aliases(self, aliases: &'static [(&'static str, &'static str)]) -> Self {
+        StaticFontProvider { aliases, ..self }
+    }
+}
+impl FontProvider for StaticFontProvider {
+    fn resolve(&self, name: &str) -> Option<String> {
+        let name = self.aliases.iter().find(|&&(alias, _)| alias == name).map(|&(_, target)| target).unwrap_or(name);
+        self.fonts.iter().find(|&&(n, _)| n == name).map(|&(n, _)| n.into())
+    }
+    fn load(&self, key: &str) -> Result<Cow<'static, [u8]>, PdfError> {
+        match self.fonts.iter().find(|&&(n, _)| n == key) {
+            Some(&(_, data)) => Ok(Cow::Borrowed(data)),
+            None => Err(PdfError::Other { msg: format!("no font {}", key) }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subset_prefix() {
+        std::assert_eq!(strip_subset("ABCDEF+Helvetica"), "Helvetica");
+        std::assert_eq!(strip_subset("ABCDE+Helvetica"), "ABCDE+Helvetica");
+        std::assert_eq!(strip_subset("abcdef+Helvetica"), "abcdef+Helvetica");
+        std::assert_eq!(FontQuery::new("XYZABC+Times-Bold").name, "Times-Bold");
+    }
+
+    #[test]
+    fn query_flags() {
+        let query = FontQuery { flags: 1 | 2 | 4, ..FontQuery::new("F") };
+        assert!(query.fixed_pitch() && query.serif() && query.symbolic() && !query.italic());
+        // nonsymbolic wins over symbolic
+        let query = FontQuery { flags: 4 | 32, italic_angle: -12., ..FontQuery::new("F") };
+        assert!(!query.symbolic() && query.italic());
+    }
+
+    #[test]
+    fn memory_and_static_providers() {
+        let mut fonts = MemoryFontProvider::new();
+        fonts.add("Helvetica", &b"data"[..]);
+        fonts.alias("Arial", "Helvetica");
+        std::assert_eq!(fonts.resolve("Arial").as_deref(), Some("Helvetica"));
+        std::assert_eq!(fonts.resolve("Times"), None);
+        std::assert_eq!(&*fonts.load("Helvetica").unwrap(), b"data");
+        assert!(fonts.load("Arial").is_err());
+
+        let fonts = StaticFontProvider::new(&[("Helvetica", b"data" as &[u8])]).aliases(&[("Arial", "Helvetica")]);
+        let found = fonts.find(&FontQuery::new("ABCDEF+Arial")).unwrap();
+        std::assert_eq!((found.key.as_str(), found.kind), ("Helvetica", MatchKind::Exact));
+    }
+
+    #[test]
+    fn directory_provider() {
+        let dir = std::env::temp_dir().join(format!("pdf_render-fonts-{}", std::process::id()));
+        assert!(DirectoryFontProvider::new(&dir).is_err());
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("fonts.json"), r#"{"Helvetica": "helvetica.otf"}"#).unwrap();
+        std::fs::write(dir.join("helvetica.otf"), b"data").unwrap();
+        let fonts = DirectoryFontProvider::new(&dir).unwrap();
+        std::assert_eq!(fonts.resolve("Helvetica").as_deref(), Some("helvetica.otf"));
+        std::assert_eq!(&*fonts.load("helvetica.otf").unwrap(), b"data");
+        assert!(fonts.font_db().is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -28,6 +28,9 @@ pub struct GraphicsState<'a, B: Backend> {
     pub overprint_fill: bool,
     pub overprint_stroke: bool,
     pub overprint_mode: i32,
+
+    // number of clip operators applied to this state
+    pub clip_depth: usize,
 }
 
 impl<'a, B: Backend> Clone for GraphicsState<'a, B> {
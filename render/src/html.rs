@@ -0,0 +1,351 @@
+//! HTML reconstruction of traced pages, for previews without WebGL.
+//!
+//! Vector graphics and images of a page go into an inline SVG background, the text is laid
+//! over it as absolutely positioned `<span>`s, so it can be selected and indexed.
+//! Embedded TrueType and OpenType fonts are used directly when their own cmap agrees with the PDF,
+//! otherwise the spans fall back to a font with the same family name, weight and style.
+//!
+//! One unit of the output space becomes one CSS pixel. `render_page` outputs mm, so scale
+//! by 96 / 25.4 to get pages at their printed size:
+//!
+//! ```ignore
+//! let mut clip_paths = vec![];
+//! let mut tracer = Tracer::new(&trace_cache, &mut clip_paths);
+//! render_page(&mut tracer, &resolver, &page, Transform2F::from_scale(96. / 25.4))?;
+//! let view_box = tracer.view_box();
+//! let items = tracer.finish();
+//! let resources = page.resources()?;
+//! let page = HtmlPage { items: &items, clip_paths: &clip_paths, view_box, resources: &resources, number: 1 };
+//! std::fs::write("page.html", html(&[page], &cache, &resolver, "title"))?;
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use font::{OpenTypeFont, TrueTypeFont};
+use pathfinder_geometry::{rect::RectF, transform2d::Transform2F, vector::Vector2F};
+use pdf::content::TextMode;
+use pdf::object::{Resolve, Resources, XObject};
+
+use crate::backend::DrawMode;
+use crate::fontprovider::strip_subset;
+use crate::svg::num;
+use crate::tracer::{ClipPath, DrawItem, VectorPath};
+use crate::{Backend, Cache, Fill, FontEntry, SvgBackend, TextSpan};
+
+/// One page to convert.
+pub struct HtmlPage<'a> {
+    /// the items of the trace
+    pub items: &'a [DrawItem],
+    /// the clip paths the items refer to
+    pub clip_paths: &'a [ClipPath],
+    /// the page in output space, as set by `render_page`
+    pub view_box: RectF,
+    /// resources of the page, used to decode images
+    pub resources: &'a Resources,
+    /// page number, starting at 1
+    pub number: u32,
+}
+
+/// HTML document with one `div.page` per page. Units of the output space of the trace are used as CSS pixels.
+pub fn html(pages: &[HtmlPage], cache: &Cache, resolve: &impl Resolve, title: &str) -> String {
+    let mut fonts = Fonts::default();
+    let mut body = String::new();
+    for page in pages {
+        let size = page.view_box.size();
+        let _ = writeln!(body, r#"<div class="page" id="page{}" style="width:{}px;height:{}px">"#, page.number, num(size.x()), num(size.y()));
+        body.push_str(&background(page, cache, resolve));
+        let origin = Transform2F::from_translation(-page.view_box.origin());
+        for item in page.items {
+            if let DrawItem::Text(ref span, _) = *item {
+                text(&mut body, span, origin, &mut fonts, resolve);
+            }
+        }
+        body.push_str("</div>\n");
+    }
+
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    let _ = writeln!(out, "<title>{}</title>", escape(title));
+    out.push_str(concat!(
+        "<style>\n",
+        ".page{position:relative;overflow:hidden;margin:0 auto 16px auto;}\n",
+        ".page>svg{position:absolute;left:0;top:0;}\n",
+        ".page>span{position:absolute;left:0;top:0;transform-origin:0 0;white-space:pre;line-height:1;}\n",
+    ));
+    out.push_str(&fonts.css);
+    out.push_str("</style>\n</head>\n<body>\n");
+    out.push_str(&body);
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Everything but the text, as an `<svg>` element.
+fn background(page: &HtmlPage, cache: &Cache, resolve: &impl Resolve) -> String {
+    let mut svg = SvgBackend::with_id_prefix(cache, &format!("p{}", page.number));
+    svg.set_view_box(page.view_box);
+
+    // parents always come before their children
+    let mut clips = Vec::with_capacity(page.clip_paths.len());
+    for clip in page.clip_paths {
+        let parent = clip.parent.map(|id| clips[id.0]);
+        let id = svg.create_clip_path(clip.path.clone(), clip.fill_rule, parent);
+        clips.push(id);
+    }
+
+    for item in page.items {
+        match *item {
+            DrawItem::Vector(ref v) => {
+                if let Some(mode) = draw_mode(v) {
+                    svg.draw(&v.outline, &mode, v.fill_rule, v.transform, v.clip.map(|id| clips[id.0]));
+                }
+            }
+            DrawItem::Image(ref im) => match resolve.get(im.id) {
+                Ok(xobject) => {
+                    if let XObject::Image(ref image) = *xobject {
+                        svg.draw_image(im.id, image, page.resources, im.transform, im.mode, im.clip.map(|id| clips[id.0]), resolve);
+                    }
+                }
+                Err(e) => warn!("can't resolve image {:?}: {:?}", im.id, e),
+            },
+            DrawItem::InlineImage(ref im) => {
+                svg.draw_inline_image(&im.im, page.resources, im.transform, im.mode, im.clip.map(|id| clips[id.0]), resolve);
+            }
+            DrawItem::Text(..) => {}
+        }
+    }
+    svg.finish_element()
+}
+
+fn draw_mode(v: &VectorPath) -> Option<DrawMode> {
+    match (&v.fill, &v.stroke) {
+        (Some(fill), None) => Some(DrawMode::Fill { fill: fill.clone() }),
+        (None, Some((stroke, stroke_mode))) => Some(DrawMode::Stroke {
+            stroke: stroke.clone(),
+            stroke_mode: stroke_mode.clone(),
+        }),
+        (Some(fill), Some((stroke, stroke_mode))) => Some(DrawMode::FillStroke {
+            fill: fill.clone(),
+            stroke: stroke.clone(),
+            stroke_mode: stroke_mode.clone(),
+        }),
+        (None, None) => None,
+    }
+}
+
+/// One `<span>` per word of `span`, so differences in the font metrics don't add up over a line.
+fn text(out: &mut String, span: &TextSpan, origin: Transform2F, fonts: &mut Fonts, resolve: &impl Resolve) {
+    let (class, baseline) = match span.font {
+        Some(ref e) => fonts.get(e, resolve),
+        None => (None, DEFAULT_BASELINE),
+    };
+    let color = match (span.mode, span.color) {
+        (TextMode::Invisible, _) => "transparent".into(),
+        (_, Fill::Solid(r, g, b)) => {
+            let c = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+            format!("#{:02x}{:02x}{:02x}", c(r), c(g), c(b))
+        }
+        (_, Fill::Pattern(_)) => "#000000".into(),
+    };
+
+    // (position, text) of each word, including the space that ends it
+    let mut words: Vec<(f32, String)> = vec![];
+    let mut word_ended = true;
+    for part in span.parts() {
+        if word_ended {
+            words.push((part.pos, String::new()));
+        }
+        let word = &mut words.last_mut().unwrap().1;
+        word.push_str(part.text);
+        word_ended = part.text.chars().all(char::is_whitespace);
+    }
+
+    for (pos, word) in words {
+        let content = escape(&word);
+        if content.trim().is_empty() {
+            continue;
+        }
+        let t = origin
+            * span.transform
+            * Transform2F::from_translation(Vector2F::new(pos, -baseline * span.font_size));
+        out.push_str("<span");
+        if let Some(class) = class {
+            let _ = write!(out, r#" class="f{class}""#);
+        }
+        let _ = write!(
+            out,
+            r#" style="font-size:{}px;transform:matrix({},{},{},{},{},{});color:{color}"#,
+            num(span.font_size), t.m11(), t.m21(), t.m12(), t.m22(), t.m13(), t.m23()
+        );
+        if span.alpha < 1.0 {
+            let _ = write!(out, ";opacity:{}", num(span.alpha));
+        }
+        let _ = writeln!(out, r#"">{content}</span>"#);
+    }
+}
+
+/// Distance of the baseline from the top of a span with `line-height:1`, in em,
+/// for fonts without vertical metrics.
+const DEFAULT_BASELINE: f32 = 0.8;
+
+#[derive(Default)]
+struct Fonts {
+    // font -> (class number, baseline in em)
+    classes: HashMap<*const FontEntry, (usize, f32)>,
+    css: String,
+}
+impl Fonts {
+    fn get(&mut self, e: &Arc<FontEntry>, resolve: &impl Resolve) -> (Option<usize>, f32) {
+        if let Some(&(class, baseline)) = self.classes.get(&Arc::as_ptr(e)) {
+            return (Some(class), baseline);
+        }
+        let class = self.classes.len();
+        let baseline = baseline(e);
+        self.add(class, e, resolve);
+        self.classes.insert(Arc::as_ptr(e), (class, baseline));
+        (Some(class), baseline)
+    }
+    fn add(&mut self, class: usize, e: &FontEntry, resolve: &impl Resolve) {
+        let name = e.font.name();
+        let family = name.family.clone().unwrap_or_else(|| strip_subset(&e.name).into());
+        let style_name = format!("{} {}", e.name, name.subfamily.as_deref().unwrap_or("")).to_lowercase();
+        let bold = ["bold", "black", "heavy"].iter().any(|s| style_name.contains(s));
+        let weight = e.font.info().weight.unwrap_or(if bold { 700 } else { 400 });
+        let style = if style_name.contains("italic") || style_name.contains("oblique") {
+            "italic"
+        } else {
+            "normal"
+        };
+        let lower = family.to_lowercase();
+        let generic = if lower.contains("mono") || lower.contains("courier") {
+            "monospace"
+        } else if lower.contains("sans") || lower.contains("arial") || lower.contains("helvetica") {
+            "sans-serif"
+        } else if lower.contains("serif") || lower.contains("times") || lower.contains("roman") {
+            "serif"
+        } else {
+            "sans-serif"
+        };
+
+        let mut families = vec![];
+        if let Some((src, format)) = embedded_font(e, resolve) {
+            let _ = writeln!(
+                self.css,
+                r#"@font-face{{font-family:"pdf-f{class}";src:url({src}) format("{format}");font-weight:{weight};font-style:{style};}}"#
+            );
+            families.push(format!(r#""pdf-f{class}""#));
+        }
+        families.push(format!(r#""{}""#, family.replace(['"', '\\', '<', '>'], "")));
+        families.push(generic.into());
+        let _ = writeln!(
+            self.css,
+            ".f{class}{{font-family:{};font-weight:{weight};font-style:{style};}}",
+            families.join(",")
+        );
+    }
+}
+
+/// Baseline from the top of a span with `line-height:1`, in em.
+fn baseline(e: &FontEntry) -> f32 {
+    let vm = match e.font.vmetrics() {
+        Some(vm) => vm,
+        None => return DEFAULT_BASELINE,
+    };
+    let scale = e.font.font_matrix().m22().abs();
+    let ascent = vm.ascent.abs() * scale;
+    let descent = vm.descent.abs() * scale;
+    // the content area is centered in the line box
+    let baseline = (1.0 + ascent - descent) / 2.0;
+    if (0.5..=1.2).contains(&baseline) {
+        baseline
+    } else {
+        DEFAULT_BASELINE
+    }
+}
+
+/// The embedded font as a data URI and its CSS format, if a browser can use it as is.
+fn embedded_font(e: &FontEntry, resolve: &impl Resolve) -> Option<(String, &'static str)> {
+    let (mime, format) = if e.font.downcast_ref::<TrueTypeFont>().is_some() {
+        ("font/ttf", "truetype")
+    } else if e.font.downcast_ref::<OpenTypeFont>().is_some() {
+        ("font/otf", "opentype")
+    } else {
+        return None;
+    };
+    // the browser picks glyphs through the cmap of the font, which has to agree with the PDF
+    let consistent = e.cmap.values().all(|(gid, unicode)| {
+        let mut chars = unicode.as_deref().into_iter().flat_map(|s| s.chars());
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => e.font.gid_for_unicode_codepoint(c as u32) == Some(*gid),
+            _ => true,
+        }
+    });
+    if !consistent {
+        debug!("not embedding {}: the cmap of the font does not match", e.name);
+        return None;
+    }
+    let data = e.pdf_font.embedded_data(resolve)?.ok()?;
+    Some((format!("data:{};base64,{}", mime, STANDARD.encode(&*data)), format))
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pdf::object::NoResolve;
+
+    use crate::testutil::span;
+
+    fn spans(item: DrawItem) -> String {
+        let span = match item {
+            DrawItem::Text(span, _) => span,
+            _ => unreachable!(),
+        };
+        let mut out = String::new();
+        let origin = Transform2F::from_translation(Vector2F::new(-5., 0.));
+        text(&mut out, &span, origin, &mut Fonts::default(), &NoResolve);
+        out
+    }
+
+    #[test]
+    fn one_span_per_word() {
+        // the baseline is 0.8 em below the top of the span
+        std::assert_eq!(spans(span("ab c&d", 10., 30., 10.)), concat!(
+            "<span style=\"font-size:10px;transform:matrix(1,0,0,1,5,22);color:#000000\">ab </span>\n",
+            "<span style=\"font-size:10px;transform:matrix(1,0,0,1,20,22);color:#000000\">c&amp;d</span>\n",
+        ));
+    }
+
+    #[test]
+    fn invisible_text() {
+        let mut item = span("x", 0., 10., 10.);
+        if let DrawItem::Text(ref mut span, _) = item {
+            span.mode = TextMode::Invisible;
+            span.alpha = 0.5;
+        }
+        let out = spans(item);
+        assert!(out.contains("color:transparent;opacity:0.5\">x</span>"));
+        assert!(spans(span("  ", 0., 10., 10.)).is_empty());
+    }
+
+    #[test]
+    fn escaping() {
+        std::assert_eq!(escape("a<b>&\u{7}"), "a&lt;b&gt;&amp;");
+    }
+}
@@ -7,6 +7,7 @@ use std::path::Path;
 use std::sync::Arc;
 
 use crate::BlendMode;
+use crate::limits::Limits;
 
 #[derive(Hash, PartialEq, Eq, Clone)]
 pub struct ImageData<'a> {
@@ -107,9 +108,19 @@ fn resize_alpha(data: &[u8], src_width: u32, src_height: u32, dest_width: u32, d
 }
 
 pub fn load_image(image: &ImageXObject, resources: &Resources, resolve: &impl Resolve, mode: BlendMode) -> Result<ImageData<'static>, PdfError> {
-    let raw_data = image.image_data(resolve)?;
+    load_image_with_limits(image, resources, resolve, mode, &Limits::default())
+}
+
+/// Like `load_image`, but refuses images (and soft masks) that exceed `limits` before decoding them.
+pub fn load_image_with_limits(image: &ImageXObject, resources: &Resources, resolve: &impl Resolve, mode: BlendMode, limits: &Limits) -> Result<ImageData<'static>, PdfError> {
+    limits.check_image(image.width as u32, image.height as u32)?;
 
     let pixel_count = image.width as usize * image.height as usize;
+    if pixel_count == 0 {
+        return Err(PdfError::Other { msg: format!("image has no pixels ({}x{})", image.width, image.height) });
+    }
+
+    let raw_data = image.image_data(resolve)?;
 
     if raw_data.len() % pixel_count != 0 {
         warn!("invalid data length {} bytes for {} pixels", raw_data.len(), pixel_count);
@@ -141,6 +152,7 @@ pub fn load_image(image: &ImageXObject, resources: &Resources, resolve: &impl Re
     let mask = t!(image.smask.map(|r| resolve.get(r)).transpose());
     let alpha = match mask {
         Some(ref mask) => {
+            limits.check_image(mask.width as u32, mask.height as u32)?;
             let data = Data::Arc(t!((**mask).data(resolve)));
             let mask_width = mask.width as usize;
             let mask_height = mask.height as usize;
@@ -158,7 +170,9 @@ pub fn load_image(image: &ImageXObject, resources: &Resources, resolve: &impl Re
                 n => return Err(PdfError::Other { msg: format!("invalid bits per component {}", n)})
             };
             if mask.width != image.width || mask.height != image.height {
-                alpha = resize_alpha(&*alpha, mask.width, mask.height, image.width, image.height).unwrap().into();
+                alpha = resize_alpha(&*alpha, mask.width, mask.height, image.width, image.height)
+                    .ok_or_else(|| PdfError::Other { msg: format!("invalid soft mask data") })?
+                    .into();
             }
             alpha
         }
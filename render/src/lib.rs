@@ -18,20 +18,48 @@ macro_rules! unimplemented {
 
 mod cache;
 mod fontentry;
+mod fontprovider;
+mod fontindex;
 mod graphicsstate;
 mod renderstate;
 mod textstate;
 mod backend;
 pub mod tracer;
+pub mod textlayout;
+pub mod search;
+pub mod postprocess;
+pub mod ocr;
+pub mod dump;
+pub mod html;
+pub mod structure;
+pub mod links;
+pub mod outline;
+pub mod thumbnail;
 mod image;
 mod scene;
+mod svg;
+mod displaylist;
 mod font;
+mod budget;
+mod limits;
+mod lru;
+mod parallel;
+#[cfg(test)]
+mod testutil;
 
-pub use cache::{Cache};
+pub use cache::{Cache, DEFAULT_MEMORY_LIMIT};
+pub use lru::CacheStats;
 pub use fontentry::{FontEntry};
+pub use fontprovider::{FontProvider, DirectoryFontProvider, MemoryFontProvider, StaticFontProvider, FontQuery, FontMatch, FontSubstitution, MatchKind, strip_subset};
+pub use fontindex::{FontIndex, IndexedFont};
 pub use backend::{DrawMode, Backend, BlendMode, FillMode};
 pub use scene::SceneBackend;
-pub use crate::image::{load_image, ImageData};
+pub use svg::SvgBackend;
+pub use displaylist::DisplayList;
+pub use crate::image::{load_image, load_image_with_limits, ImageData};
+pub use budget::{CancelToken, RenderOptions, RenderStatus, Truncation};
+pub use limits::{Limits, LimitError, RenderError};
+pub use parallel::{ParallelRenderer, PageTrace};
 use custom_debug_derive::Debug;
 
 use pdf::{object::*, content::TextMode};
@@ -41,6 +69,7 @@ use pathfinder_geometry::{
     rect::RectF, transform2d::Transform2F,
 };
 use renderstate::RenderState;
+use budget::BudgetTracker;
 use std::sync::Arc;
 use itertools::Itertools;
 const SCALE: f32 = 25.4 / 72.;
@@ -78,43 +107,151 @@ pub fn page_bounds(page: &Page) -> RectF {
     let Rect { left, right, top, bottom } = page.media_box().expect("no media box");
     RectF::from_points(Vector2F::new(left, bottom), Vector2F::new(right, top)) * SCALE
 }
-pub fn render_page(backend: &mut impl Backend, resolve: &impl Resolve, page: &Page, transform: Transform2F) -> Result<Transform2F, PdfError> {
-    let bounds = page_bounds(page);
+
+/// The page boundary that is rendered. Everything outside of it is outside of the view box.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum PageBox {
+    /// the whole medium (`/MediaBox`)
+    #[default]
+    MediaBox,
+    /// the area shown by viewers and printed (`/CropBox`, defaults to the media box)
+    CropBox,
+    /// the area including bleed for production (`/BleedBox`, defaults to the crop box)
+    BleedBox,
+    /// the finished page after trimming (`/TrimBox`, defaults to the crop box)
+    TrimBox,
+    /// the meaningful content (`/ArtBox`, defaults to the crop box)
+    ArtBox,
+}
+impl PageBox {
+    /// The rectangle of this box, like `page_bounds` (in the units of `render_page`, not rotated).
+    pub fn bounds(self, page: &Page) -> RectF {
+        let crop_box = || match page.crop_box() {
+            Ok(r) => rect(r),
+            Err(_) => page_bounds(page),
+        };
+        let other = |key: &str| {
+            let numbers = page.other.get(key)?.as_array().ok()?
+                .iter()
+                .map(|p| p.as_number().ok())
+                .collect::<Option<Vec<f32>>>()?;
+            match numbers[..] {
+                [left, bottom, right, top] => Some(rect(Rect { left, bottom, right, top })),
+                _ => None,
+            }
+        };
+        match self {
+            PageBox::MediaBox => page_bounds(page),
+            PageBox::CropBox => crop_box(),
+            PageBox::BleedBox => other("BleedBox").unwrap_or_else(crop_box),
+            PageBox::TrimBox => page.trim_box.map(rect).unwrap_or_else(crop_box),
+            PageBox::ArtBox => other("ArtBox").unwrap_or_else(crop_box),
+        }
+    }
+}
+fn rect(Rect { left, right, top, bottom }: Rect) -> RectF {
+    RectF::from_points(
+        Vector2F::new(left.min(right), bottom.min(top)),
+        Vector2F::new(left.max(right), bottom.max(top)),
+    ) * SCALE
+}
+
+/// The transformation from PDF user space to the output of `render_page` with an identity transform.
+///
+/// Use it to map destinations and other page coordinates into the same space as the rendered page.
+pub fn page_transform(page: &Page) -> Transform2F {
+    page_layout(page, PageBox::MediaBox).1
+}
+
+/// The size of the output of `render_page_with` for `page_box` with an identity transform, which includes the rotation of the page.
+pub fn page_size(page: &Page, page_box: PageBox) -> Vector2F {
+    page_layout(page, page_box).0.size()
+}
+
+// (view box, transform from user space), both without the transform passed to `render_page`
+fn page_layout(page: &Page, page_box: PageBox) -> (RectF, Transform2F) {
+    let bounds = page_box.bounds(page);
     let rotate = Transform2F::from_rotation(page.rotate as f32 * std::f32::consts::PI / 180.);
     let br = rotate * RectF::new(Vector2F::zero(), bounds.size());
     let translate = Transform2F::from_translation(Vector2F::new(
         -br.min_x().min(br.max_x()),
         -br.min_y().min(br.max_y()),
     ));
-    let view_box = transform * translate * br;
-    backend.set_view_box(view_box);
-    
-    let root_transformation = transform
-        * translate
+    let transform = translate
         * rotate
         * Transform2F::row_major(SCALE, 0.0, -bounds.min_x(), 0.0, -SCALE, bounds.max_y());
-    
-    let resources = t!(page.resources());
+    (translate * br, transform)
+}
 
-    let contents = try_opt!(page.contents.as_ref());
+pub fn render_page(backend: &mut impl Backend, resolve: &impl Resolve, page: &Page, transform: Transform2F) -> Result<Transform2F, PdfError> {
+    render_page_with(backend, resolve, page, transform, &RenderOptions::default())
+        .map(|r| r.transform)
+        .map_err(PdfError::from)
+}
+
+/// Render the part of `page` that is visible in `region` of the output, e.g. one tile of a zoomed-in page.
+///
+/// Paths, glyphs and images outside of `region` are skipped and the view box is set to `region`.
+pub fn render_region(backend: &mut impl Backend, resolve: &impl Resolve, page: &Page, transform: Transform2F, region: RectF) -> Result<Transform2F, PdfError> {
+    render_page_with(backend, resolve, page, transform, &RenderOptions::default().region(region))
+        .map(|r| r.transform)
+        .map_err(PdfError::from)
+}
+
+/// The outcome of `render_page_with`.
+#[derive(Debug, Copy, Clone)]
+pub struct RenderResult {
+    /// the transformation from PDF user space to the output, as returned by `render_page`
+    pub transform: Transform2F,
+    /// whether all operators were drawn. If not, the backend contains a partial page.
+    pub status: RenderStatus,
+    /// number of operators that were processed
+    pub ops: usize,
+}
+
+/// Like `render_page`, but stops early when a budget in `options` runs out or the render is cancelled.
+///
+/// Violations of `options.limits` are reported as `RenderError::Limit`.
+pub fn render_page_with(backend: &mut impl Backend, resolve: &impl Resolve, page: &Page, transform: Transform2F, options: &RenderOptions) -> Result<RenderResult, RenderError> {
+    let (view_box, page_transform) = page_layout(page, options.page_box);
+    backend.set_view_box(options.region.unwrap_or(transform * view_box));
+
+    let root_transformation = transform * page_transform;
+
+    let budget = BudgetTracker::new(options);
+    let result = |budget: &BudgetTracker| RenderResult {
+        transform: root_transformation,
+        status: budget.status(),
+        ops: budget.ops(),
+    };
+
+    let resources = page.resources()?;
+
+    let contents = match page.contents.as_ref() {
+        Some(contents) => contents,
+        None => return Ok(result(&budget)),
+    };
     let ops = contents.operations(resolve)?;
-    let mut renderstate = RenderState::new(backend, resolve, &resources, root_transformation);
-    for (i, op) in ops.iter().enumerate() {
-        debug!("op {}: {:?}", i, op);
-        renderstate.draw_op(op, i)?;
+    let mut renderstate = RenderState::new(backend, resolve, &resources, root_transformation, &budget);
+    if let Err(e) = renderstate.draw_ops(&ops) {
+        if let Some(limit) = budget.take_limit_error() {
+            return Err(RenderError::Limit(limit));
+        }
+        // a budget that ran out in the middle of an operator aborts it with an error
+        if budget.status().is_complete() {
+            return Err(RenderError::Pdf(e));
+        }
     }
 
-    Ok(root_transformation)
+    Ok(result(&budget))
 }
 pub fn render_pattern(backend: &mut impl Backend, pattern: &Pattern, resolve: &impl Resolve) -> Result<(), PdfError> {
     match pattern {
         Pattern::Stream(ref dict, ref ops) => {
             let resources = resolve.get(dict.resources)?;
-            let mut renderstate = RenderState::new(backend, resolve, &*resources, Transform2F::default());
-            for (i, op) in ops.iter().enumerate() {
-                debug!("op {}: {:?}", i, op);
-                renderstate.draw_op(op, i)?;
-            }
+            let budget = BudgetTracker::unlimited();
+            let mut renderstate = RenderState::new(backend, resolve, &*resources, Transform2F::default(), &budget);
+            renderstate.draw_ops(ops)?;
         }
         Pattern::Dict(_) => {}
     }
@@ -154,6 +291,10 @@ pub struct TextSpan {
     pub transform: Transform2F,
     pub mode: TextMode,
     pub op_nr: usize,
+    // marked-content identifier, links the span to an element of the structure tree
+    pub mcid: Option<u32>,
+    // the form xobject whose content stream `mcid` belongs to, `None` for the page
+    pub mcid_stream: Option<PlainRef>,
 }
 impl TextSpan {
     pub fn parts(&self) -> impl Iterator<Item=Part> + '_ {
@@ -0,0 +1,193 @@
+use std::fmt;
+use pdf::error::PdfError;
+use crate::FontEntry;
+
+/// Upper bounds for resources a single (possibly hostile) document may claim.
+///
+/// The defaults are far above what legitimate files need, but low enough that a server
+/// will not try to allocate gigabytes because a number in the file says so.
+#[derive(Clone, Debug)]
+pub struct Limits {
+    /// maximum width or height of an image in pixels
+    pub max_image_dimension: u32,
+    /// maximum number of pixels (width × height) of an image
+    pub max_image_pixels: usize,
+    /// maximum nesting of `q` operators within one content stream
+    pub max_stack_depth: usize,
+    /// maximum number of segments in a single path
+    pub max_path_segments: usize,
+    /// maximum number of nested clip paths within one graphics state
+    pub max_clip_depth: usize,
+    /// maximum nesting of form xobjects
+    pub max_form_depth: usize,
+    /// maximum number of glyphs in a font
+    pub max_glyphs: u32,
+    /// maximum size of embedded font data in bytes
+    pub max_font_data: usize,
+}
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_image_dimension: 1 << 16,
+            max_image_pixels: 1 << 26,
+            max_stack_depth: 1024,
+            max_path_segments: 1 << 20,
+            max_clip_depth: 1024,
+            max_form_depth: 32,
+            max_glyphs: 1 << 16,
+            max_font_data: 1 << 26,
+        }
+    }
+}
+impl Limits {
+    /// No limits at all. Only use this for trusted input.
+    pub fn unlimited() -> Self {
+        Limits {
+            max_image_dimension: u32::MAX,
+            max_image_pixels: usize::MAX,
+            max_stack_depth: usize::MAX,
+            max_path_segments: usize::MAX,
+            max_clip_depth: usize::MAX,
+            max_form_depth: usize::MAX,
+            max_glyphs: u32::MAX,
+            max_font_data: usize::MAX,
+        }
+    }
+
+    pub(crate) fn check_image(&self, width: u32, height: u32) -> Result<(), LimitError> {
+        let pixels = width as usize * height as usize;
+        if width > self.max_image_dimension || height > self.max_image_dimension || pixels > self.max_image_pixels {
+            return Err(LimitError::ImageSize { width, height });
+        }
+        Ok(())
+    }
+
+    /// Check a cached font, which may have been loaded with other limits.
+    pub(crate) fn check_font(&self, entry: &FontEntry) -> Result<(), LimitError> {
+        if let Some(size) = entry.embedded_size {
+            if size > self.max_font_data {
+                return Err(LimitError::FontData(size));
+            }
+            if entry.font.num_glyphs() > self.max_glyphs {
+                return Err(LimitError::Glyphs(entry.font.num_glyphs()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A limit from `Limits` was exceeded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LimitError {
+    ImageSize { width: u32, height: u32 },
+    StackDepth(usize),
+    PathSegments(usize),
+    ClipDepth(usize),
+    FormDepth(usize),
+    Glyphs(u32),
+    FontData(usize),
+}
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LimitError::ImageSize { width, height } => write!(f, "image of {}x{} pixels exceeds the size limit", width, height),
+            LimitError::StackDepth(n) => write!(f, "graphics state stack depth {} exceeds the limit", n),
+            LimitError::PathSegments(n) => write!(f, "path with {} segments exceeds the limit", n),
+            LimitError::ClipDepth(n) => write!(f, "clip nesting depth {} exceeds the limit", n),
+            LimitError::FormDepth(n) => write!(f, "form xobject nesting depth {} exceeds the limit", n),
+            LimitError::Glyphs(n) => write!(f, "font with {} glyphs exceeds the limit", n),
+            LimitError::FontData(n) => write!(f, "font data of {} bytes exceeds the limit", n),
+        }
+    }
+}
+impl std::error::Error for LimitError {}
+
+impl From<LimitError> for PdfError {
+    fn from(e: LimitError) -> PdfError {
+        PdfError::Other { msg: e.to_string() }
+    }
+}
+
+/// Error returned by `render_page_with`.
+#[derive(Debug)]
+pub enum RenderError {
+    Pdf(PdfError),
+    Limit(LimitError),
+}
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RenderError::Pdf(e) => e.fmt(f),
+            RenderError::Limit(e) => e.fmt(f),
+        }
+    }
+}
+impl std::error::Error for RenderError {}
+
+impl From<PdfError> for RenderError {
+    fn from(e: PdfError) -> RenderError {
+        RenderError::Pdf(e)
+    }
+}
+impl From<LimitError> for RenderError {
+    fn from(e: LimitError) -> RenderError {
+        RenderError::Limit(e)
+    }
+}
+impl From<RenderError> for PdfError {
+    fn from(e: RenderError) -> PdfError {
+        match e {
+            RenderError::Pdf(e) => e,
+            RenderError::Limit(e) => e.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budget::BudgetTracker;
+    use crate::RenderOptions;
+
+    #[test]
+    fn image_size() {
+        let limits = Limits {
+            max_image_dimension: 100,
+            max_image_pixels: 5000,
+            ..Limits::default()
+        };
+        std::assert_eq!(limits.check_image(100, 50), Ok(()));
+        std::assert_eq!(limits.check_image(101, 1), Err(LimitError::ImageSize { width: 101, height: 1 }));
+        std::assert_eq!(limits.check_image(100, 51), Err(LimitError::ImageSize { width: 100, height: 51 }));
+        std::assert_eq!(Limits::unlimited().check_image(u32::MAX, u32::MAX), Ok(()));
+    }
+
+    #[test]
+    fn form_depth_is_reported_as_limit_error() {
+        let limits = Limits {
+            max_form_depth: 2,
+            ..Limits::default()
+        };
+        let budget = BudgetTracker::new(&RenderOptions::new().limits(limits));
+        assert!(budget.enter_form().is_ok());
+        assert!(budget.enter_form().is_ok());
+        assert!(budget.enter_form().is_err());
+        std::assert_eq!(budget.take_limit_error(), Some(LimitError::FormDepth(3)));
+        std::assert_eq!(budget.take_limit_error(), None);
+
+        budget.leave_form();
+        assert!(budget.enter_form().is_ok());
+    }
+
+    #[test]
+    fn render_error_conversion() {
+        let budget = BudgetTracker::unlimited();
+        let _ = budget.error(RenderError::Limit(LimitError::Glyphs(1 << 20)));
+        std::assert_eq!(budget.take_limit_error(), Some(LimitError::Glyphs(1 << 20)));
+
+        match RenderError::from(LimitError::StackDepth(5)) {
+            RenderError::Limit(LimitError::StackDepth(5)) => {}
+            e => panic!("unexpected {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,640 @@
+//! Link annotations and destinations.
+//!
+//! Link areas are returned in the output space of `render_page`, so they can be hit-tested
+//! against the same coordinates the page was drawn with. Destinations keep the coordinates of
+//! the target page in PDF user space; map them with the transform `render_page` returns for that page.
+//!
+//! ```ignore
+//! let catalog = resolver.resolve(file.trailer.root.get_ref().get_inner())?.into_dictionary()?;
+//! let links = LinkResolver::new(&catalog, &resolver)?;
+//! let transform = render_page(&mut backend, &resolver, &page, view_transform)?;
+//! for link in links.page_links(page.get_ref().get_inner(), transform, &resolver)? {
+//!     if link.contains(cursor) {
+//!         println!("{:?}", link.target);
+//!     }
+//! }
+//! ```
+
+use std::collections::{HashMap, HashSet};
+
+use pathfinder_geometry::{rect::RectF, transform2d::Transform2F, vector::Vector2F};
+use pdf::error::PdfError;
+use pdf::object::{PlainRef, Resolve};
+use pdf::primitive::{Dictionary, Primitive};
+
+// name trees and page trees nested deeper than this are treated as broken
+const MAX_DEPTH: usize = 64;
+
+/// How the target page is positioned. Coordinates are in PDF user space of the target page,
+/// `None` keeps the current value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum View {
+    XYZ { left: Option<f32>, top: Option<f32>, zoom: Option<f32> },
+    Fit,
+    FitH { top: Option<f32> },
+    FitV { left: Option<f32> },
+    FitR(RectF),
+    FitB,
+    FitBH { top: Option<f32> },
+    FitBV { left: Option<f32> },
+}
+impl View {
+    /// Top edge of the visible area, if the view sets one.
+    pub fn top(&self) -> Option<f32> {
+        match *self {
+            View::XYZ { top, .. } | View::FitH { top } | View::FitBH { top } => top,
+            View::FitR(rect) => Some(rect.max_y()),
+            _ => None,
+        }
+    }
+    /// Left edge of the visible area, if the view sets one.
+    pub fn left(&self) -> Option<f32> {
+        match *self {
+            View::XYZ { left, .. } | View::FitV { left } | View::FitBV { left } => left,
+            View::FitR(rect) => Some(rect.min_x()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Destination {
+    /// page index, starting at 0
+    pub page: u32,
+    /// the page object, unless the destination points into another file
+    pub page_ref: Option<PlainRef>,
+    pub view: View,
+}
+
+/// Destination in another document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RemoteDest {
+    Named(String),
+    Explicit(Destination),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkTarget {
+    Uri(String),
+    /// explicit destination in this document
+    Dest(Destination),
+    /// named destination in this document, `dest` is `None` if the name is not defined
+    Named { name: String, dest: Option<Destination> },
+    GoToR { file: String, dest: Option<RemoteDest>, new_window: Option<bool> },
+    Launch { file: String, new_window: Option<bool> },
+    /// named action like `NextPage`, `PrevPage`, `FirstPage` or `LastPage`
+    Action(String),
+    /// other action types, by `/S`
+    Unsupported(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Link {
+    /// the annotation object, if it is not a direct object
+    pub annot: Option<PlainRef>,
+    /// `/Rect`, transformed into output space
+    pub rect: RectF,
+    /// `/QuadPoints`, transformed into output space. The link is only active inside of them if there are any.
+    pub quads: Vec<[Vector2F; 4]>,
+    /// `None` if the link has neither `/Dest` nor `/A`, or they could not be read
+    pub target: Option<LinkTarget>,
+}
+impl Link {
+    /// Whether `p` (in output space) is within the active area of the link.
+    pub fn contains(&self, p: Vector2F) -> bool {
+        if self.quads.is_empty() {
+            return self.rect.contains_point(p);
+        }
+        self.quads.iter().any(|q| quad_contains(q, p))
+    }
+}
+
+// The order of the points is not consistent between producers, so this tests against
+// the convex hull, which is the union of all four triangles.
+fn quad_contains(q: &[Vector2F; 4], p: Vector2F) -> bool {
+    [(0, 1, 2), (0, 2, 3), (0, 1, 3), (1, 2, 3)]
+        .iter()
+        .any(|&(a, b, c)| triangle_contains(q[a], q[b], q[c], p))
+}
+fn triangle_contains(a: Vector2F, b: Vector2F, c: Vector2F, p: Vector2F) -> bool {
+    let side = |u: Vector2F, v: Vector2F| (v - u).det(p - u);
+    let (d1, d2, d3) = (side(a, b), side(b, c), side(c, a));
+    let neg = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+    let pos = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+    !(neg && pos)
+}
+
+/// Resolves destinations of one document. Reads the page tree once.
+pub struct LinkResolver {
+    pages: HashMap<PlainRef, u32>,
+    // old style `/Dests` dictionary of the catalog
+    dests: Option<Dictionary>,
+    // root of the `/Dests` name tree
+    dest_names: Option<Dictionary>,
+    // `/URI /Base` of the catalog
+    uri_base: Option<String>,
+}
+
+impl LinkResolver {
+    pub fn new(catalog: &Dictionary, resolve: &impl Resolve) -> Result<LinkResolver, PdfError> {
+        let mut pages = HashMap::new();
+        if let Some(p) = catalog.get("Pages") {
+            let mut visited = HashSet::new();
+            collect_pages(p, resolve, &mut pages, &mut visited, 0)?;
+        }
+        let dests = match catalog.get("Dests") {
+            Some(p) => Some(p.clone().resolve(resolve)?.into_dictionary()?),
+            None => None,
+        };
+        let dest_names = match catalog.get("Names") {
+            Some(p) => {
+                let names = p.clone().resolve(resolve)?.into_dictionary()?;
+                match names.get("Dests") {
+                    Some(p) => Some(p.clone().resolve(resolve)?.into_dictionary()?),
+                    None => None,
+                }
+            }
+            None => None,
+        };
+        let uri_base = match catalog.get("URI") {
+            Some(p) => string(&p.clone().resolve(resolve)?.into_dictionary()?, "Base", resolve),
+            None => None,
+        };
+        Ok(LinkResolver { pages, dests, dest_names, uri_base })
+    }
+
+    /// Index of the page object `page`.
+    pub fn page_index(&self, page: PlainRef) -> Option<u32> {
+        self.pages.get(&page).cloned()
+    }
+
+    /// All link annotations of the page object `page`, in the order of `/Annots`.
+    /// `transform` is the value returned by `render_page` for this page.
+    pub fn page_links(&self, page: PlainRef, transform: Transform2F, resolve: &impl Resolve) -> Result<Vec<Link>, PdfError> {
+        let page = resolve.resolve(page)?.into_dictionary()?;
+        let annots = match page.get("Annots") {
+            Some(p) => p.clone().resolve(resolve)?,
+            None => return Ok(vec![]),
+        };
+        let annots = match annots {
+            Primitive::Array(annots) => annots,
+            _ => return Ok(vec![]),
+        };
+
+        let mut links = vec![];
+        for p in annots {
+            let annot = match p {
+                Primitive::Reference(r) => Some(r),
+                _ => None,
+            };
+            let dict = match p.resolve(resolve).and_then(|p| p.into_dictionary()) {
+                Ok(dict) => dict,
+                Err(e) => {
+                    warn!("can't read annotation {:?}: {:?}", annot, e);
+                    continue;
+                }
+            };
+            if name(&dict, "Subtype") != Some("Link") {
+                continue;
+            }
+            // hidden
+            if integer(&dict, "F").unwrap_or(0) & 2 != 0 {
+                continue;
+            }
+            let rect = match numbers(&dict, "Rect", resolve).as_deref() {
+                Some(&[a, b, c, d]) => transform * RectF::from_points(
+                    Vector2F::new(a.min(c), b.min(d)),
+                    Vector2F::new(a.max(c), b.max(d)),
+                ),
+                _ => {
+                    warn!("link {:?} without /Rect", annot);
+                    continue;
+                }
+            };
+            let quads = numbers(&dict, "QuadPoints", resolve)
+                .unwrap_or_default()
+                .chunks_exact(8)
+                .map(|q| [
+                    transform * Vector2F::new(q[0], q[1]),
+                    transform * Vector2F::new(q[2], q[3]),
+                    transform * Vector2F::new(q[4], q[5]),
+                    transform * Vector2F::new(q[6], q[7]),
+                ])
+                .collect();
+            let target = match self.target(&dict, resolve) {
+                Ok(target) => target,
+                Err(e) => {
+                    warn!("can't read target of link {:?}: {:?}", annot, e);
+                    None
+                }
+            };
+            links.push(Link { annot, rect, quads, target });
+        }
+        Ok(links)
+    }
+
+    /// Target of a link annotation, from `/Dest` or `/A`.
+    fn target(&self, annot: &Dictionary, resolve: &impl Resolve) -> Result<Option<LinkTarget>, PdfError> {
+        if let Some(dest) = annot.get("Dest") {
+            return self.goto(dest, resolve);
+        }
+        match annot.get("A") {
+            Some(p) => {
+                let action = p.clone().resolve(resolve)?.into_dictionary()?;
+                self.action(&action, resolve)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Target of the action dictionary `action`. Also used for outline items.
+    pub fn action(&self, action: &Dictionary, resolve: &impl Resolve) -> Result<Option<LinkTarget>, PdfError> {
+        let new_window = match action.get("NewWindow") {
+            Some(&Primitive::Boolean(b)) => Some(b),
+            _ => None,
+        };
+        let target = match name(action, "S") {
+            Some("URI") => match string(action, "URI", resolve) {
+                Some(uri) => LinkTarget::Uri(self.uri(uri)),
+                None => return Ok(None),
+            },
+            Some("GoTo") => match action.get("D") {
+                Some(d) => return self.goto(d, resolve),
+                None => return Ok(None),
+            },
+            Some("GoToR") => {
+                let file = match action.get("F") {
+                    Some(f) => file_spec(f, resolve)?,
+                    None => None,
+                };
+                let dest = match action.get("D").map(|d| d.clone().resolve(resolve)).transpose()? {
+                    Some(Primitive::Array(ref parts)) => {
+                        explicit(parts, remote_page, resolve)?.map(RemoteDest::Explicit)
+                    }
+                    Some(ref d) => dest_name(d).map(RemoteDest::Named),
+                    None => None,
+                };
+                match file {
+                    Some(file) => LinkTarget::GoToR { file, dest, new_window },
+                    None => return Ok(None),
+                }
+            }
+            Some("Launch") => {
+                let file = match action.get("F") {
+                    Some(f) => file_spec(f, resolve)?,
+                    None => None,
+                };
+                match file {
+                    Some(file) => LinkTarget::Launch { file, new_window },
+                    None => return Ok(None),
+                }
+            }
+            Some("Named") => match name(action, "N") {
+                Some(n) => LinkTarget::Action(n.into()),
+                None => return Ok(None),
+            },
+            Some(s) => LinkTarget::Unsupported(s.into()),
+            None => return Ok(None),
+        };
+        Ok(Some(target))
+    }
+
+    /// Target of a `/Dest` entry or the `/D` of a GoTo action: an explicit destination or a name.
+    pub fn goto(&self, dest: &Primitive, resolve: &impl Resolve) -> Result<Option<LinkTarget>, PdfError> {
+        match dest.clone().resolve(resolve)? {
+            Primitive::Array(ref parts) => Ok(self.explicit(parts, resolve)?.map(LinkTarget::Dest)),
+            ref d => match dest_name(d) {
+                Some(name) => {
+                    let dest = self.named(&name, resolve)?;
+                    Ok(Some(LinkTarget::Named { name, dest }))
+                }
+                None => Ok(None),
+            },
+        }
+    }
+
+    /// Look up a named destination, in the `/Dests` dictionary of the catalog and the `/Dests` name tree.
+    pub fn named(&self, name: &str, resolve: &impl Resolve) -> Result<Option<Destination>, PdfError> {
+        let mut value = None;
+        if let Some(ref dests) = self.dests {
+            value = dests.get(name).cloned();
+        }
+        if value.is_none() {
+            if let Some(ref root) = self.dest_names {
+                value = name_tree_lookup(root, name.as_bytes(), resolve, 0)?;
+            }
+        }
+        let value = match value {
+            Some(p) => p.resolve(resolve)?,
+            None => return Ok(None),
+        };
+        // either the array itself or a dictionary with `/D`
+        let parts = match value {
+            Primitive::Array(parts) => parts,
+            Primitive::Dictionary(dict) => match dict.get("D") {
+                Some(d) => match d.clone().resolve(resolve)? {
+                    Primitive::Array(parts) => parts,
+                    _ => return Ok(None),
+                },
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        self.explicit(&parts, resolve)
+    }
+
+    /// An explicit destination `[page /Type args...]` in this document.
+    pub fn explicit(&self, parts: &[Primitive], resolve: &impl Resolve) -> Result<Option<Destination>, PdfError> {
+        explicit(parts, |p| match *p {
+            Primitive::Reference(r) => self.page_index(r).map(|idx| (idx, Some(r))),
+            // some producers write page numbers, like in remote destinations
+            _ => remote_page(p),
+        }, resolve)
+    }
+
+    fn uri(&self, uri: String) -> String {
+        match self.uri_base {
+            Some(ref base) if !uri.contains(':') => format!("{}{}", base, uri),
+            _ => uri,
+        }
+    }
+}
+
+fn remote_page(p: &Primitive) -> Option<(u32, Option<PlainRef>)> {
+    p.as_integer().ok().and_then(|n| u32::try_from(n).ok()).map(|n| (n, None))
+}
+
+fn explicit(parts: &[Primitive], page: impl Fn(&Primitive) -> Option<(u32, Option<PlainRef>)>, resolve: &impl Resolve) -> Result<Option<Destination>, PdfError> {
+    let (page, page_ref) = match parts.first().and_then(page) {
+        Some(p) => p,
+        None => return Ok(None),
+    };
+    let mut args = Vec::with_capacity(parts.len().saturating_sub(2));
+    for p in parts.iter().skip(2) {
+        args.push(match p.clone().resolve(resolve)? {
+            Primitive::Null => None,
+            p => p.as_number().ok(),
+        });
+    }
+    let arg = |i: usize| args.get(i).cloned().flatten();
+    let view = match parts.get(1).and_then(|p| p.as_name().ok()) {
+        Some("XYZ") => View::XYZ {
+            left: arg(0),
+            top: arg(1),
+            // 0 means unchanged
+            zoom: arg(2).filter(|&z| z != 0.0),
+        },
+        Some("FitH") => View::FitH { top: arg(0) },
+        Some("FitV") => View::FitV { left: arg(0) },
+        Some("FitR") => match (arg(0), arg(1), arg(2), arg(3)) {
+            (Some(a), Some(b), Some(c), Some(d)) => View::FitR(RectF::from_points(
+                Vector2F::new(a.min(c), b.min(d)),
+                Vector2F::new(a.max(c), b.max(d)),
+            )),
+            _ => View::Fit,
+        },
+        Some("FitB") => View::FitB,
+        Some("FitBH") => View::FitBH { top: arg(0) },
+        Some("FitBV") => View::FitBV { left: arg(0) },
+        _ => View::Fit,
+    };
+    Ok(Some(Destination { page, page_ref, view }))
+}
+
+fn collect_pages(node: &Primitive, resolve: &impl Resolve, pages: &mut HashMap<PlainRef, u32>, visited: &mut HashSet<PlainRef>, depth: usize) -> Result<(), PdfError> {
+    if depth > MAX_DEPTH {
+        warn!("page tree too deep");
+        return Ok(());
+    }
+    let r = match *node {
+        Primitive::Reference(r) => Some(r),
+        _ => None,
+    };
+    if let Some(r) = r {
+        if !visited.insert(r) {
+            warn!("cycle in page tree at {:?}", r);
+            return Ok(());
+        }
+    }
+    let dict = node.clone().resolve(resolve)?.into_dictionary()?;
+    match dict.get("Kids") {
+        Some(kids) => {
+            if let Primitive::Array(kids) = kids.clone().resolve(resolve)? {
+                for kid in &kids {
+                    collect_pages(kid, resolve, pages, visited, depth + 1)?;
+                }
+            }
+        }
+        None => {
+            if let Some(r) = r {
+                let idx = pages.len() as u32;
+                pages.insert(r, idx);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn name_tree_lookup(node: &Dictionary, key: &[u8], resolve: &impl Resolve, depth: usize) -> Result<Option<Primitive>, PdfError> {
+    if depth > MAX_DEPTH {
+        warn!("name tree too deep");
+        return Ok(None);
+    }
+    if let Some(limits) = node.get("Limits") {
+        if let Primitive::Array(ref limits) = limits.clone().resolve(resolve)? {
+            if let (Some(Ok(lo)), Some(Ok(hi))) = (limits.first().map(|p| p.as_string()), limits.get(1).map(|p| p.as_string())) {
+                if key < lo.as_bytes() || key > hi.as_bytes() {
+                    return Ok(None);
+                }
+            }
+        }
+    }
+    if let Some(names) = node.get("Names") {
+        if let Primitive::Array(ref names) = names.clone().resolve(resolve)? {
+            for pair in names.chunks_exact(2) {
+                if pair[0].as_string().map(|s| s.as_bytes() == key).unwrap_or(false) {
+                    return Ok(Some(pair[1].clone()));
+                }
+            }
+        }
+    }
+    if let Some(kids) = node.get("Kids") {
+        if let Primitive::Array(ref kids) = kids.clone().resolve(resolve)? {
+            for kid in kids {
+                let kid = kid.clone().resolve(resolve)?.into_dictionary()?;
+                if let Some(value) = name_tree_lookup(&kid, key, resolve, depth + 1)? {
+                    return Ok(Some(value));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Path of a file specification, which is either a string or a dictionary.
+fn file_spec(p: &Primitive, resolve: &impl Resolve) -> Result<Option<String>, PdfError> {
+    Ok(match p.clone().resolve(resolve)? {
+        Primitive::String(s) => s.to_string().ok(),
+        Primitive::Dictionary(dict) => string(&dict, "UF", resolve)
+            .or_else(|| string(&dict, "F", resolve))
+            .or_else(|| string(&dict, "Unix", resolve))
+            .or_else(|| string(&dict, "DOS", resolve)),
+        _ => None,
+    })
+}
+
+fn dest_name(p: &Primitive) -> Option<String> {
+    match *p {
+        Primitive::Name(ref n) => Some(n.as_str().into()),
+        Primitive::String(ref s) => s.to_string().ok(),
+        _ => None,
+    }
+}
+
+fn name<'a>(dict: &'a Dictionary, key: &str) -> Option<&'a str> {
+    dict.get(key).and_then(|p| p.as_name().ok())
+}
+
+fn integer(dict: &Dictionary, key: &str) -> Option<i32> {
+    dict.get(key).and_then(|p| p.as_integer().ok())
+}
+
+fn string(dict: &Dictionary, key: &str, resolve: &impl Resolve) -> Option<String> {
+    let p = dict.get(key)?.clone().resolve(resolve).ok()?;
+    p.as_string().ok().and_then(|s| s.to_string().ok())
+}
+
+fn numbers(dict: &Dictionary, key: &str, resolve: &impl Resolve) -> Option<Vec<f32>> {
+    match dict.get(key)?.clone().resolve(resolve).ok()? {
+        Primitive::Array(items) => items.iter().map(|p| p.as_number().ok()).collect(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pdf::file::FileOptions;
+
+    use crate::testutil::PdfBuilder;
+
+    fn obj(id: u32) -> PlainRef {
+        PlainRef { id: id.into(), gen: 0 }
+    }
+
+    /// A document with two pages, a catalog with destinations at `catalog` and a page-like
+    /// dictionary with links at `annots`.
+    struct Document {
+        data: Vec<u8>,
+        pages: [u32; 2],
+        catalog: u32,
+        annots: u32,
+    }
+    fn document() -> Document {
+        let mut pdf = PdfBuilder::new();
+        let pages = [pdf.page((100., 100.), "", ""), pdf.page((100., 100.), "", "")];
+        let leaf1 = pdf.add(format!("<< /Limits [(a) (c)] /Names [(a) [{} 0 R /XYZ 10 20 0] (c) [{} 0 R /Fit]] >>", pages[0], pages[1]));
+        let leaf2 = pdf.add(format!("<< /Limits [(m) (z)] /Names [(x) << /D [{} 0 R /FitH 50] >>] >>", pages[1]));
+        let root = pdf.add(format!("<< /Kids [{} 0 R {} 0 R] >>", leaf1, leaf2));
+        let catalog = pdf.add(format!(
+            "<< /Pages 2 0 R /Dests << /old [1 /FitR 30 40 10 20] >> /Names << /Dests {} 0 R >> /URI << /Base (http://example.com/) >> >>",
+            root
+        ));
+        let annots = pdf.add(concat!(
+            "<< /Annots [",
+            "<< /Subtype /Link /Rect [10 10 50 30] /A << /S /URI /URI (docs/a.html) >> >> ",
+            "<< /Subtype /Link /Rect [0 0 100 100] /QuadPoints [0 0 10 0 10 10 0 10] /Dest (x) >> ",
+            "<< /Subtype /Link /F 2 /Rect [0 0 1 1] /Dest (a) >> ",
+            "<< /Subtype /Text /Rect [0 0 1 1] >> ",
+            "<< /Subtype /Link /Rect [0 0 1 1] /A << /S /Named /N /NextPage >> >>",
+            "] >>",
+        ));
+        Document { data: pdf.finish(), pages, catalog, annots }
+    }
+
+    #[test]
+    fn named_destinations() {
+        let doc = document();
+        let file = FileOptions::cached().load(doc.data).unwrap();
+        let resolver = file.resolver();
+        let catalog = resolver.resolve(obj(doc.catalog)).unwrap().into_dictionary().unwrap();
+        let links = LinkResolver::new(&catalog, &resolver).unwrap();
+
+        std::assert_eq!(links.page_index(obj(doc.pages[1])), Some(1));
+        std::assert_eq!(links.named("a", &resolver).unwrap(), Some(Destination {
+            page: 0,
+            page_ref: Some(obj(doc.pages[0])),
+            view: View::XYZ { left: Some(10.), top: Some(20.), zoom: None },
+        }));
+        // in the second leaf, as a dictionary with `/D`
+        std::assert_eq!(links.named("x", &resolver).unwrap(), Some(Destination {
+            page: 1,
+            page_ref: Some(obj(doc.pages[1])),
+            view: View::FitH { top: Some(50.) },
+        }));
+        // from the `/Dests` dictionary of the catalog, with a page number
+        let old = links.named("old", &resolver).unwrap().unwrap();
+        std::assert_eq!((old.page, old.page_ref), (1, None));
+        std::assert_eq!(old.view, View::FitR(RectF::from_points(Vector2F::new(10., 20.), Vector2F::new(30., 40.))));
+        std::assert_eq!((old.view.left(), old.view.top()), (Some(10.), Some(40.)));
+        // within the limits of a leaf, but not in it
+        std::assert_eq!(links.named("b", &resolver).unwrap(), None);
+        std::assert_eq!(links.named("q", &resolver).unwrap(), None);
+    }
+
+    #[test]
+    fn page_links() {
+        let doc = document();
+        let file = FileOptions::cached().load(doc.data).unwrap();
+        let resolver = file.resolver();
+        let catalog = resolver.resolve(obj(doc.catalog)).unwrap().into_dictionary().unwrap();
+        let links = LinkResolver::new(&catalog, &resolver).unwrap();
+
+        let transform = Transform2F::from_scale(Vector2F::splat(2.));
+        let page_links = links.page_links(obj(doc.annots), transform, &resolver).unwrap();
+        // hidden links and other annotations are left out
+        let targets: Vec<_> = page_links.iter().map(|l| l.target.clone()).collect();
+        std::assert_eq!(targets, [
+            Some(LinkTarget::Uri("http://example.com/docs/a.html".into())),
+            Some(LinkTarget::Named { name: "x".into(), dest: links.named("x", &resolver).unwrap() }),
+            Some(LinkTarget::Action("NextPage".into())),
+        ]);
+
+        std::assert_eq!(page_links[0].rect, RectF::from_points(Vector2F::new(20., 20.), Vector2F::new(100., 60.)));
+        assert!(page_links[0].contains(Vector2F::new(50., 50.)));
+        assert!(!page_links[0].contains(Vector2F::new(10., 10.)));
+        // only active inside the quad, not the whole rect
+        assert!(page_links[1].contains(Vector2F::new(10., 10.)));
+        assert!(!page_links[1].contains(Vector2F::new(50., 50.)));
+    }
+
+    #[test]
+    fn quads() {
+        let v = Vector2F::new;
+        let square = [v(0., 0.), v(10., 0.), v(10., 10.), v(0., 10.)];
+        // the order some producers use
+        let crossed = [v(0., 10.), v(10., 10.), v(0., 0.), v(10., 0.)];
+        for q in [square, crossed] {
+            assert!(quad_contains(&q, v(5., 5.)));
+            assert!(quad_contains(&q, v(9., 1.)));
+            assert!(quad_contains(&q, v(10., 10.)));
+            assert!(!quad_contains(&q, v(11., 5.)));
+        }
+    }
+
+    #[test]
+    fn explicit_views() {
+        let dest = |parts: Vec<Primitive>| explicit(&parts, remote_page, &pdf::object::NoResolve).unwrap();
+        let name = |s: &str| Primitive::Name(s.into());
+        std::assert_eq!(
+            dest(vec![Primitive::Integer(2), name("XYZ"), Primitive::Null, Primitive::Integer(700), Primitive::Number(1.5)]),
+            Some(Destination { page: 2, page_ref: None, view: View::XYZ { left: None, top: Some(700.), zoom: Some(1.5) } })
+        );
+        std::assert_eq!(dest(vec![Primitive::Integer(0), name("FitBV"), Primitive::Integer(3)]).unwrap().view, View::FitBV { left: Some(3.) });
+        // incomplete FitR and unknown types fall back to Fit
+        std::assert_eq!(dest(vec![Primitive::Integer(0), name("FitR"), Primitive::Integer(3)]).unwrap().view, View::Fit);
+        std::assert_eq!(dest(vec![Primitive::Integer(0), name("Zoom")]).unwrap().view, View::Fit);
+        std::assert_eq!(dest(vec![Primitive::Integer(-1), name("Fit")]), None);
+        std::assert_eq!(dest(vec![]), None);
+    }
+}
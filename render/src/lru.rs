@@ -0,0 +1,224 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::sync::Mutex;
+
+use globalcache::ValueSize;
+
+/// Counters of a cache, as returned by `Cache::stats` and `TraceCache::stats`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    /// number of cached entries
+    pub entries: usize,
+    /// approximate memory used by the cached entries in bytes
+    pub bytes: usize,
+    /// the memory limit in bytes
+    pub capacity: usize,
+}
+
+struct Slot<V> {
+    value: V,
+    size: usize,
+    last_used: u64,
+}
+
+struct Inner<K, V> {
+    map: HashMap<K, Slot<V>>,
+    // last_used -> key, oldest first
+    order: BTreeMap<u64, K>,
+    tick: u64,
+    stats: CacheStats,
+}
+impl<K: Hash + Eq + Clone, V> Inner<K, V> {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+    fn remove(&mut self, key: &K) {
+        if let Some(slot) = self.map.remove(key) {
+            self.order.remove(&slot.last_used);
+            self.stats.bytes -= slot.size;
+        }
+    }
+    /// Evict the least recently used entries until the cache fits, sparing `keep`.
+    fn evict(&mut self, keep: Option<&K>) {
+        while self.stats.bytes > self.stats.capacity {
+            let victim = self.order.values().find(|&k| Some(k) != keep).cloned();
+            match victim {
+                Some(key) => {
+                    self.remove(&key);
+                    self.stats.evictions += 1;
+                }
+                None => break,
+            }
+        }
+        self.stats.entries = self.map.len();
+    }
+}
+
+/// A cache bounded by the (approximate) memory of its values.
+///
+/// Values are computed outside the lock, so slow loads on one thread don't block lookups
+/// on other threads. Two threads missing the same key at the same time will both compute it.
+pub(crate) struct LruCache<K, V> {
+    inner: Mutex<Inner<K, V>>,
+}
+impl<K: Hash + Eq + Clone, V: ValueSize + Clone> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        LruCache {
+            inner: Mutex::new(Inner {
+                map: HashMap::new(),
+                order: BTreeMap::new(),
+                tick: 0,
+                stats: CacheStats {
+                    capacity,
+                    ..CacheStats::default()
+                },
+            }),
+        }
+    }
+
+    pub fn get(&self, key: K, compute: impl FnOnce() -> V) -> V {
+        match self.try_get(key, || Ok::<V, std::convert::Infallible>(compute())) {
+            Ok(value) => value,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Like `get`, but nothing is cached if `compute` fails.
+    pub fn try_get<E>(&self, key: K, compute: impl FnOnce() -> Result<V, E>) -> Result<V, E> {
+        {
+            let mut guard = self.inner.lock().unwrap();
+            let inner = &mut *guard;
+            let tick = inner.next_tick();
+            if let Some(slot) = inner.map.get_mut(&key) {
+                inner.order.remove(&slot.last_used);
+                inner.order.insert(tick, key);
+                slot.last_used = tick;
+                inner.stats.hits += 1;
+                return Ok(slot.value.clone());
+            }
+            inner.stats.misses += 1;
+        }
+
+        let value = compute()?;
+        let size = value.size();
+
+        let mut inner = self.inner.lock().unwrap();
+        inner.remove(&key);
+        let tick = inner.next_tick();
+        inner.order.insert(tick, key.clone());
+        inner.map.insert(key.clone(), Slot {
+            value: value.clone(),
+            size,
+            last_used: tick,
+        });
+        inner.stats.bytes += size;
+        inner.evict(Some(&key));
+        Ok(value)
+    }
+
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.stats.capacity = capacity;
+        inner.evict(None);
+    }
+
+    /// Drop the entries for which `keep` returns false.
+    pub fn retain(&self, keep: impl Fn(&K) -> bool) {
+        let mut inner = self.inner.lock().unwrap();
+        let keys: Vec<K> = inner.map.keys().filter(|&k| !keep(k)).cloned().collect();
+        for key in &keys {
+            inner.remove(key);
+        }
+        inner.stats.entries = inner.map.len();
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.map.clear();
+        inner.order.clear();
+        inner.stats.bytes = 0;
+        inner.stats.entries = 0;
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.inner.lock().unwrap().stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Value(usize);
+    impl ValueSize for Value {
+        fn size(&self) -> usize {
+            self.0
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = LruCache::new(10);
+        cache.get(1, || Value(4));
+        cache.get(2, || Value(4));
+        // a hit makes 1 the most recently used entry
+        std::assert_eq!(cache.get(1, || unreachable!()), Value(4));
+        cache.get(3, || Value(4));
+
+        let stats = cache.stats();
+        std::assert_eq!((stats.hits, stats.misses, stats.evictions), (1, 3, 1));
+        std::assert_eq!((stats.entries, stats.bytes), (2, 8));
+        std::assert_eq!(cache.get(2, || Value(1)), Value(1));
+        std::assert_eq!(cache.get(1, || unreachable!()), Value(4));
+    }
+
+    #[test]
+    fn oversized_value_is_kept_alone() {
+        let cache = LruCache::new(10);
+        cache.get(1, || Value(4));
+        std::assert_eq!(cache.get(2, || Value(20)), Value(20));
+        let stats = cache.stats();
+        std::assert_eq!((stats.entries, stats.bytes, stats.evictions), (1, 20, 1));
+    }
+
+    #[test]
+    fn set_capacity_and_clear() {
+        let cache = LruCache::new(100);
+        for key in 0..10 {
+            cache.get(key, || Value(10));
+        }
+        cache.set_capacity(35);
+        let stats = cache.stats();
+        std::assert_eq!((stats.entries, stats.bytes, stats.evictions), (3, 30, 7));
+        std::assert_eq!(cache.get(9, || unreachable!()), Value(10));
+
+        cache.clear();
+        std::assert_eq!((cache.stats().entries, cache.stats().bytes), (0, 0));
+    }
+
+    #[test]
+    fn errors_are_not_cached() {
+        let cache = LruCache::new(100);
+        std::assert_eq!(cache.try_get(1, || Err::<Value, _>("fail")), Err("fail"));
+        std::assert_eq!(cache.try_get(1, || Ok::<_, ()>(Value(1))), Ok(Value(1)));
+        std::assert_eq!(cache.stats().entries, 1);
+    }
+
+    #[test]
+    fn retain() {
+        let cache = LruCache::new(100);
+        for key in 0..6 {
+            cache.get(key, || Value(10));
+        }
+        cache.retain(|&key| key % 2 == 0);
+        let stats = cache.stats();
+        std::assert_eq!((stats.entries, stats.bytes), (3, 30));
+        std::assert_eq!(cache.get(2, || Value(99)).0, 10);
+        std::assert_eq!(cache.get(3, || Value(99)).0, 99);
+    }
+}
@@ -0,0 +1,293 @@
+//! ALTO 4 and hOCR export of the text of traced pages.
+//!
+//! ```ignore
+//! let transform = render_page(&mut tracer, &resolver, &page, Transform2F::default())?;
+//! let view_box = tracer.view_box();
+//! let items = tracer.finish();
+//! let text = PageText::new(&items);
+//! let page = OcrPage { items: &items, text: &text, view_box, transform, number: 1 };
+//! std::fs::write("page.xml", alto(&[page]))?;
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use pathfinder_geometry::{rect::RectF, transform2d::Transform2F, vector::Vector2F};
+
+use crate::fontprovider::strip_subset;
+use crate::textlayout::{Line, PageText, Word};
+use crate::tracer::DrawItem;
+
+/// One page to export.
+pub struct OcrPage<'a> {
+    /// the items of the trace
+    pub items: &'a [DrawItem],
+    /// the layout of `items`
+    pub text: &'a PageText,
+    /// the page in output space, as set by `render_page`
+    pub view_box: RectF,
+    /// the transform returned by `render_page`, used to give font sizes in points
+    pub transform: Transform2F,
+    /// page number, starting at 1
+    pub number: u32,
+}
+impl<'a> OcrPage<'a> {
+    fn units_per_point(&self) -> f32 {
+        (self.transform.matrix * Vector2F::new(1.0, 0.0)).length().max(f32::EPSILON)
+    }
+    /// Font family and size in points of the first glyph of `word`.
+    fn font(&self, word: &Word) -> Option<(String, f32)> {
+        let c = self.text.chars.get(word.chars.start)?;
+        let span = match self.items.get(c.item)? {
+            DrawItem::Text(span, _) => span,
+            _ => return None,
+        };
+        let family = match span.font {
+            Some(ref e) => e.font.name().family.clone().unwrap_or_else(|| strip_subset(&e.name).into()),
+            None => String::new(),
+        };
+        let size = (span.transform.matrix * Vector2F::new(0.0, span.font_size)).length() / self.units_per_point();
+        Some((family, size))
+    }
+    /// `r` relative to the top left of the page
+    fn local(&self, r: RectF) -> RectF {
+        RectF::new(r.origin() - self.view_box.origin(), r.size())
+    }
+    /// `r` relative to the top left of the page in 1/10 mm, the `mm10` unit of ALTO
+    fn mm10(&self, r: RectF) -> RectF {
+        self.local(r) * (MM10_PER_POINT / self.units_per_point())
+    }
+}
+
+/// Angle of the baseline in degrees, counter-clockwise.
+fn rotation(line: &Line) -> f32 {
+    let d = line.direction;
+    let angle = (-d.y()).atan2(d.x()).to_degrees();
+    if angle.abs() < 0.5 {
+        0.0
+    } else {
+        angle
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+const MM10_PER_POINT: f32 = 254. / 72.;
+
+/// ALTO 4 document with one `Page` per page.
+///
+/// Positions are in 1/10 mm (`mm10`), independent of the scale of the output space. Font sizes are in points.
+pub fn alto(pages: &[OcrPage]) -> String {
+    // (family, size in 1/10 pt) -> style number
+    let mut styles: HashMap<(String, i32), usize> = HashMap::new();
+    let mut layout = String::new();
+
+    for page in pages {
+        let n = page.number;
+        let size = page.mm10(page.view_box).size();
+        let _ = writeln!(layout, r#"    <Page ID="page{n}" PHYSICAL_IMG_NR="{n}" WIDTH="{:.2}" HEIGHT="{:.2}">"#, size.x(), size.y());
+        let _ = writeln!(layout, r#"      <PrintSpace HPOS="0" VPOS="0" WIDTH="{:.2}" HEIGHT="{:.2}">"#, size.x(), size.y());
+        for (b, block) in page.text.blocks().enumerate() {
+            let r = page.mm10(block.rect);
+            let rotation = block.lines.first().map(rotation).unwrap_or(0.0);
+            let _ = write!(layout, r#"        <TextBlock ID="p{n}_b{b}" HPOS="{:.2}" VPOS="{:.2}" WIDTH="{:.2}" HEIGHT="{:.2}""#, r.min_x(), r.min_y(), r.width(), r.height());
+            if rotation != 0.0 {
+                let _ = write!(layout, r#" ROTATION="{:.1}""#, rotation);
+            }
+            layout.push_str(">\n");
+            for (l, line) in block.lines.iter().enumerate() {
+                let r = page.mm10(line.rect);
+                let _ = writeln!(layout, r#"          <TextLine ID="p{n}_b{b}_l{l}" HPOS="{:.2}" VPOS="{:.2}" WIDTH="{:.2}" HEIGHT="{:.2}">"#, r.min_x(), r.min_y(), r.width(), r.height());
+                for (w, word) in line.words.iter().enumerate() {
+                    if w > 0 {
+                        layout.push_str("            <SP/>\n");
+                    }
+                    let style = page.font(word).map(|(family, size)| {
+                        let key = (family, (size * 10.0).round() as i32);
+                        let next = styles.len();
+                        *styles.entry(key).or_insert(next)
+                    });
+                    let r = page.mm10(word.rect);
+                    let _ = write!(layout, r#"            <String ID="p{n}_b{b}_l{l}_w{w}" CONTENT="{}" HPOS="{:.2}" VPOS="{:.2}" WIDTH="{:.2}" HEIGHT="{:.2}""#,
+                        escape(&page.text.text[word.range.clone()]), r.min_x(), r.min_y(), r.width(), r.height());
+                    if let Some(style) = style {
+                        let _ = write!(layout, r#" STYLEREFS="font{style}""#);
+                    }
+                    layout.push_str("/>\n");
+                }
+                layout.push_str("          </TextLine>\n");
+            }
+            layout.push_str("        </TextBlock>\n");
+        }
+        layout.push_str("      </PrintSpace>\n    </Page>\n");
+    }
+
+    let mut styles: Vec<_> = styles.into_iter().collect();
+    styles.sort_by_key(|&(_, id)| id);
+
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(concat!(
+        r#"<alto xmlns="http://www.loc.gov/standards/alto/ns-v4#" "#,
+        r#"xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" "#,
+        r#"xsi:schemaLocation="http://www.loc.gov/standards/alto/ns-v4# http://www.loc.gov/standards/alto/v4/alto-4-2.xsd">"#,
+        "\n",
+    ));
+    out.push_str("  <Description>\n    <MeasurementUnit>mm10</MeasurementUnit>\n  </Description>\n");
+    if !styles.is_empty() {
+        out.push_str("  <Styles>\n");
+        for ((family, size), id) in styles {
+            let _ = writeln!(out, r#"    <TextStyle ID="font{id}" FONTFAMILY="{}" FONTSIZE="{:.1}"/>"#, escape(&family), size as f32 / 10.0);
+        }
+        out.push_str("  </Styles>\n");
+    }
+    out.push_str("  <Layout>\n");
+    out.push_str(&layout);
+    out.push_str("  </Layout>\n</alto>\n");
+    out
+}
+
+fn bbox(r: RectF) -> String {
+    format!(
+        "bbox {} {} {} {}",
+        r.min_x().floor() as i64,
+        r.min_y().floor() as i64,
+        r.max_x().ceil() as i64,
+        r.max_y().ceil() as i64
+    )
+}
+
+/// hOCR document with one `ocr_page` per page.
+///
+/// hOCR only has integer coordinates, so use a transform that gives enough resolution,
+/// e.g. pixels at 300 dpi.
+pub fn hocr(pages: &[OcrPage], title: &str) -> String {
+    let mut out = String::new();
+    out.push_str(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<!DOCTYPE html PUBLIC \"-//W3C//DTD XHTML 1.0 Transitional//EN\" \"http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd\">\n",
+        "<html xmlns=\"http://www.w3.org/1999/xhtml\">\n",
+        "<head>\n",
+    ));
+    let _ = writeln!(out, "  <title>{}</title>", escape(title));
+    out.push_str(concat!(
+        "  <meta http-equiv=\"Content-Type\" content=\"text/html; charset=utf-8\"/>\n",
+        "  <meta name=\"ocr-system\" content=\"pdf_render\"/>\n",
+        "  <meta name=\"ocr-capabilities\" content=\"ocr_page ocr_carea ocr_par ocr_line ocrx_word ocrp_font\"/>\n",
+        "</head>\n<body>\n",
+    ));
+
+    for page in pages {
+        let n = page.number;
+        let _ = writeln!(out, r#"  <div class="ocr_page" id="page_{n}" title="{}; ppageno {}">"#,
+            bbox(RectF::new(Vector2F::zero(), page.view_box.size())), n.saturating_sub(1));
+        for (c, column) in page.text.columns.iter().enumerate() {
+            let _ = writeln!(out, r#"    <div class="ocr_carea" id="block_{n}_{c}" title="{}">"#, bbox(page.local(column.rect)));
+            for (b, block) in column.blocks.iter().enumerate() {
+                let _ = writeln!(out, r#"      <p class="ocr_par" id="par_{n}_{c}_{b}" title="{}">"#, bbox(page.local(block.rect)));
+                for (l, line) in block.lines.iter().enumerate() {
+                    let mut title = bbox(page.local(line.rect));
+                    let rotation = rotation(line);
+                    if rotation != 0.0 {
+                        let _ = write!(title, "; textangle {}", rotation.round() as i32);
+                    }
+                    let _ = writeln!(out, r#"        <span class="ocr_line" id="line_{n}_{c}_{b}_{l}" title="{title}">"#);
+                    for (w, word) in line.words.iter().enumerate() {
+                        let mut title = bbox(page.local(word.rect));
+                        if let Some((family, size)) = page.font(word) {
+                            if !family.is_empty() {
+                                let _ = write!(title, "; x_font {}", family.replace(';', ""));
+                            }
+                            let _ = write!(title, "; x_fsize {}", size.round() as i32);
+                        }
+                        let _ = writeln!(out, r#"          <span class="ocrx_word" id="word_{n}_{c}_{b}_{l}_{w}" title="{}">{}</span>"#,
+                            escape(&title), escape(&page.text.text[word.range.clone()]));
+                    }
+                    out.push_str("        </span>\n");
+                }
+                out.push_str("      </p>\n");
+            }
+            out.push_str("    </div>\n");
+        }
+        out.push_str("  </div>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::span;
+
+    /// Export a page whose output space is in points.
+    fn export(f: impl FnOnce(&OcrPage) -> String) -> String {
+        export_with(Transform2F::default(), f)
+    }
+    fn export_with(transform: Transform2F, f: impl FnOnce(&OcrPage) -> String) -> String {
+        let items = vec![span("Hello <world>", 10., 30., 10.), span("second", 10., 42., 10.)];
+        let text = PageText::new(&items);
+        let page = OcrPage {
+            items: &items,
+            text: &text,
+            view_box: RectF::new(Vector2F::new(5., 0.), Vector2F::new(200., 100.)),
+            transform,
+            number: 2,
+        };
+        f(&page)
+    }
+
+    #[test]
+    fn alto_words() {
+        let out = export(|page| alto(std::slice::from_ref(page)));
+        // 72 pt = 254 mm10
+        assert!(out.contains(r#"<Page ID="page2" PHYSICAL_IMG_NR="2" WIDTH="705.56" HEIGHT="352.78">"#));
+        // relative to the view box
+        assert!(out.contains(r#"<String ID="p2_b0_l0_w0" CONTENT="Hello" HPOS="17.64" VPOS="70.56" WIDTH="88.19" HEIGHT="35.28" STYLEREFS="font0"/>"#));
+        assert!(out.contains(r#"CONTENT="&lt;world&gt;""#));
+        assert!(out.contains(r#"<String ID="p2_b0_l1_w0" CONTENT="second""#));
+        std::assert_eq!(out.matches("<SP/>").count(), 1);
+        // both spans share one style
+        assert!(out.contains(r#"<TextStyle ID="font0" FONTFAMILY="" FONTSIZE="10.0"/>"#));
+        assert!(!out.contains(r#"ID="font1""#));
+        assert!(!out.contains("ROTATION"));
+    }
+
+    #[test]
+    fn alto_units() {
+        // the output of `render_page` with an identity transform is in mm
+        let out = export_with(Transform2F::from_scale(25.4 / 72.), |page| alto(std::slice::from_ref(page)));
+        assert!(out.contains("<MeasurementUnit>mm10</MeasurementUnit>"));
+        assert!(out.contains(r#"<Page ID="page2" PHYSICAL_IMG_NR="2" WIDTH="2000.00" HEIGHT="1000.00">"#));
+        assert!(out.contains(r#"CONTENT="Hello" HPOS="50.00" VPOS="200.00" WIDTH="250.00" HEIGHT="100.00""#));
+    }
+
+    #[test]
+    fn hocr_words() {
+        let out = export(|page| hocr(std::slice::from_ref(page), "a & b"));
+        assert!(out.contains("<title>a &amp; b</title>"));
+        assert!(out.contains(r#"<div class="ocr_page" id="page_2" title="bbox 0 0 200 100; ppageno 1">"#));
+        assert!(out.contains(r#"<span class="ocrx_word" id="word_2_0_0_0_0" title="bbox 5 20 30 30; x_fsize 10">Hello</span>"#));
+        assert!(out.contains(r#"<span class="ocrx_word" id="word_2_0_0_1_0" title="bbox 5 32 35 42; x_fsize 10">second</span>"#));
+        assert!(!out.contains("textangle"));
+    }
+
+    #[test]
+    fn escaping() {
+        std::assert_eq!(escape("<a href=\"x\">'&'</a>\u{7}"), "&lt;a href=&quot;x&quot;&gt;&apos;&amp;&apos;&lt;/a&gt;");
+    }
+}
@@ -0,0 +1,229 @@
+//! The document outline (bookmarks).
+//!
+//! ```ignore
+//! let links = LinkResolver::new(&catalog, &resolver)?;
+//! if let Some(outline) = OutlineTree::from_catalog(&catalog, &links, &resolver)? {
+//!     for (path, item) in outline.rows(|_, item| item.open) {
+//!         println!("{:indent$}{}", "", item.title, indent = 2 * (path.len() - 1));
+//!     }
+//! }
+//! ```
+
+use std::collections::HashSet;
+
+use pdf::error::PdfError;
+use pdf::object::{PlainRef, Resolve};
+use pdf::primitive::{Dictionary, Primitive};
+
+use crate::links::{Destination, LinkResolver, LinkTarget};
+
+// nesting beyond this is treated as broken
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct OutlineItem {
+    pub title: String,
+    /// `/C`, RGB in the range 0 to 1
+    pub color: Option<(f32, f32, f32)>,
+    pub italic: bool,
+    pub bold: bool,
+    /// whether the children are shown initially (positive `/Count`)
+    pub open: bool,
+    pub target: Option<LinkTarget>,
+    pub children: Vec<OutlineItem>,
+}
+impl OutlineItem {
+    /// The destination in this document, if the item has one.
+    pub fn destination(&self) -> Option<&Destination> {
+        match self.target {
+            Some(LinkTarget::Dest(ref dest)) | Some(LinkTarget::Named { dest: Some(ref dest), .. }) => Some(dest),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OutlineTree {
+    /// top level items
+    pub items: Vec<OutlineItem>,
+}
+
+impl OutlineTree {
+    /// The outline of the document with the catalog `catalog`, if it has one.
+    pub fn from_catalog(catalog: &Dictionary, links: &LinkResolver, resolve: &impl Resolve) -> Result<Option<OutlineTree>, PdfError> {
+        let root = match catalog.get("Outlines") {
+            Some(p) => p.clone().resolve(resolve)?.into_dictionary()?,
+            None => return Ok(None),
+        };
+        let mut visited = HashSet::new();
+        let items = match root.get("First") {
+            Some(first) => siblings(first, links, resolve, &mut visited, 0)?,
+            None => vec![],
+        };
+        Ok(Some(OutlineTree { items }))
+    }
+
+    /// The item at `path`, which lists the index at each level, starting at the top.
+    pub fn get(&self, path: &[usize]) -> Option<&OutlineItem> {
+        let (&first, rest) = path.split_first()?;
+        let mut item = self.items.get(first)?;
+        for &idx in rest {
+            item = item.children.get(idx)?;
+        }
+        Some(item)
+    }
+
+    /// Items in display order with their path. The children of an item are included if `expanded` returns true for it.
+    pub fn rows(&self, expanded: impl Fn(&[usize], &OutlineItem) -> bool) -> Vec<(Vec<usize>, &OutlineItem)> {
+        let mut rows = vec![];
+        let mut stack: Vec<(Vec<usize>, &OutlineItem)> = self.items.iter().enumerate().rev()
+            .map(|(idx, item)| (vec![idx], item))
+            .collect();
+        while let Some((path, item)) = stack.pop() {
+            if expanded(&path, item) {
+                for (idx, child) in item.children.iter().enumerate().rev() {
+                    let mut child_path = path.clone();
+                    child_path.push(idx);
+                    stack.push((child_path, child));
+                }
+            }
+            rows.push((path, item));
+        }
+        rows
+    }
+}
+
+/// Items starting at `first`, following `/Next`.
+fn siblings(first: &Primitive, links: &LinkResolver, resolve: &impl Resolve, visited: &mut HashSet<PlainRef>, depth: usize) -> Result<Vec<OutlineItem>, PdfError> {
+    if depth > MAX_DEPTH {
+        warn!("outline too deep");
+        return Ok(vec![]);
+    }
+    let mut items = vec![];
+    let mut next = Some(first.clone());
+    while let Some(p) = next.take() {
+        if let Primitive::Reference(r) = p {
+            if !visited.insert(r) {
+                warn!("cycle in outline at {:?}", r);
+                break;
+            }
+        }
+        let dict = p.resolve(resolve)?.into_dictionary()?;
+        items.push(item(&dict, links, resolve, visited, depth)?);
+        next = dict.get("Next").cloned();
+    }
+    Ok(items)
+}
+
+fn item(dict: &Dictionary, links: &LinkResolver, resolve: &impl Resolve, visited: &mut HashSet<PlainRef>, depth: usize) -> Result<OutlineItem, PdfError> {
+    let title = match dict.get("Title") {
+        Some(p) => p.clone().resolve(resolve)?.as_string().ok().and_then(|s| s.to_string().ok()).unwrap_or_default(),
+        None => String::new(),
+    };
+    let color = match dict.get("C").map(|p| p.clone().resolve(resolve)).transpose()? {
+        Some(Primitive::Array(ref c)) => match c.iter().map(|p| p.as_number().ok()).collect::<Option<Vec<f32>>>().as_deref() {
+            Some(&[r, g, b]) => Some((r, g, b)),
+            _ => None,
+        },
+        _ => None,
+    };
+    let flags = dict.get("F").and_then(|p| p.as_integer().ok()).unwrap_or(0);
+    let open = dict.get("Count").and_then(|p| p.as_integer().ok()).unwrap_or(0) > 0;
+
+    let target = if let Some(dest) = dict.get("Dest") {
+        links.goto(dest, resolve)
+    } else if let Some(action) = dict.get("A") {
+        action.clone().resolve(resolve)
+            .and_then(|p| p.into_dictionary())
+            .and_then(|action| links.action(&action, resolve))
+    } else {
+        Ok(None)
+    };
+    let target = target.unwrap_or_else(|e| {
+        warn!("can't read the target of outline item {:?}: {:?}", title, e);
+        None
+    });
+
+    let children = match dict.get("First") {
+        Some(first) => siblings(first, links, resolve, visited, depth + 1)?,
+        None => vec![],
+    };
+    Ok(OutlineItem {
+        title,
+        color,
+        italic: flags & 1 != 0,
+        bold: flags & 2 != 0,
+        open,
+        target,
+        children,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pdf::file::FileOptions;
+
+    use crate::testutil::PdfBuilder;
+
+    fn item(title: &str, open: bool, children: Vec<OutlineItem>) -> OutlineItem {
+        OutlineItem { title: title.into(), color: None, italic: false, bold: false, open, target: None, children }
+    }
+
+    #[test]
+    fn rows() {
+        let tree = OutlineTree {
+            items: vec![
+                item("a", true, vec![item("a0", false, vec![item("a00", false, vec![])]), item("a1", false, vec![])]),
+                item("b", false, vec![item("b0", false, vec![])]),
+            ],
+        };
+        let rows = |expanded: &dyn Fn(&[usize], &OutlineItem) -> bool| -> Vec<(Vec<usize>, String)> {
+            tree.rows(expanded).into_iter().map(|(path, item)| (path, item.title.clone())).collect()
+        };
+        std::assert_eq!(rows(&|_, item| item.open), [
+            (vec![0], "a".into()),
+            (vec![0, 0], "a0".into()),
+            (vec![0, 1], "a1".into()),
+            (vec![1], "b".into()),
+        ]);
+        std::assert_eq!(rows(&|_, _| true).len(), 6);
+        std::assert_eq!(rows(&|path, _| path == [1]).into_iter().map(|(_, t)| t).collect::<Vec<_>>(), ["a", "b", "b0"]);
+
+        std::assert_eq!(tree.get(&[0, 0, 0]).map(|i| i.title.as_str()), Some("a00"));
+        assert!(tree.get(&[0, 2]).is_none());
+        assert!(tree.get(&[]).is_none());
+    }
+
+    #[test]
+    fn from_catalog() {
+        let mut pdf = PdfBuilder::new();
+        let page = pdf.page((100., 100.), "", "");
+        std::assert_eq!(page, 4);
+        pdf.add("<< /First 6 0 R /Count 3 >>");
+        pdf.add("<< /Title (Intro) /Dest [4 0 R /Fit] /Next 7 0 R /C [1 0 0.5] /F 3 >>");
+        // points back to the first item
+        pdf.add("<< /Title (Parts) /Count 1 /First 8 0 R /Next 6 0 R >>");
+        pdf.add("<< /Title (Part 1) /A << /S /URI /URI (http://example.com) >> >>");
+        let catalog = pdf.add("<< /Pages 2 0 R /Outlines 5 0 R >>");
+
+        let file = FileOptions::cached().load(pdf.finish()).unwrap();
+        let resolver = file.resolver();
+        let catalog = resolver.resolve(PlainRef { id: catalog.into(), gen: 0 }).unwrap().into_dictionary().unwrap();
+        let links = LinkResolver::new(&catalog, &resolver).unwrap();
+        let tree = OutlineTree::from_catalog(&catalog, &links, &resolver).unwrap().unwrap();
+
+        let titles: Vec<_> = tree.rows(|_, _| true).into_iter().map(|(_, item)| item.title.as_str()).collect();
+        std::assert_eq!(titles, ["Intro", "Parts", "Part 1"]);
+        let intro = &tree.items[0];
+        std::assert_eq!(intro.color, Some((1., 0., 0.5)));
+        assert!(intro.italic && intro.bold && !intro.open);
+        std::assert_eq!(intro.destination().map(|d| d.page), Some(0));
+        assert!(tree.items[1].open);
+        std::assert_eq!(tree.items[1].children[0].target, Some(LinkTarget::Uri("http://example.com".into())));
+        assert!(tree.items[1].children[0].destination().is_none());
+
+        let empty = Dictionary::new();
+        assert!(OutlineTree::from_catalog(&empty, &links, &resolver).unwrap().is_none());
+    }
+}
@@ -0,0 +1,166 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use pathfinder_geometry::{rect::RectF, transform2d::Transform2F};
+use pathfinder_renderer::scene::Scene;
+use pdf::any::AnySync;
+use pdf::backend::Backend as PdfBackend;
+use pdf::error::PdfError;
+use pdf::file::{Cache as PdfCache, File as PdfFile, Log};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+
+use crate::tracer::{ClipPath, DrawItem, TraceCache, Tracer};
+use crate::{render_page_with, Cache, RenderError, RenderOptions, RenderStatus, SceneBackend};
+
+/// The trace of a single page, as produced by `Tracer`.
+pub struct PageTrace {
+    pub items: Vec<DrawItem>,
+    pub clip_paths: Vec<ClipPath>,
+    pub view_box: RectF,
+    /// whether the whole page was traced, see `render_page_with`
+    pub status: RenderStatus,
+}
+
+/// Renders the pages of a document on a dedicated thread pool.
+///
+/// All pages share the given cache, so fonts and images used on several pages are only loaded once.
+pub struct ParallelRenderer {
+    pool: ThreadPool,
+}
+impl ParallelRenderer {
+    /// Create a thread pool with `num_threads` threads. `0` uses one thread per CPU.
+    pub fn new(num_threads: usize) -> Result<Self, ThreadPoolBuildError> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .thread_name(|i| format!("pdf_render-{}", i))
+            .build()?;
+        Ok(ParallelRenderer { pool })
+    }
+
+    pub fn num_threads(&self) -> usize {
+        self.pool.current_num_threads()
+    }
+
+    /// Render `pages` into scenes. The results are in page order.
+    ///
+    /// `options` applies to each page; a cancel token or deadline in it stops all pages.
+    pub fn render_pages<B, OC, SC, L>(
+        &self,
+        file: &PdfFile<B, OC, SC, L>,
+        pages: Range<u32>,
+        cache: &Cache,
+        transform: Transform2F,
+        options: &RenderOptions,
+    ) -> Vec<Result<(Scene, RenderStatus), RenderError>>
+    where
+        B: PdfBackend + Sync,
+        OC: PdfCache<Result<AnySync, Arc<PdfError>>> + Sync,
+        SC: PdfCache<Result<Arc<[u8]>, Arc<PdfError>>> + Sync,
+        L: Log + Sync,
+    {
+        self.pool.install(|| {
+            pages
+                .into_par_iter()
+                .map(|page_nr| {
+                    let page = file.get_page(page_nr)?;
+                    let resolver = file.resolver();
+                    let mut backend = SceneBackend::new(cache);
+                    let result = render_page_with(&mut backend, &resolver, &page, transform, options)?;
+                    Ok((backend.finish(), result.status))
+                })
+                .collect()
+        })
+    }
+
+    /// Trace `pages`. The results are in page order. `options` applies to each page, like with `render_pages`.
+    pub fn trace_pages<B, OC, SC, L>(
+        &self,
+        file: &PdfFile<B, OC, SC, L>,
+        pages: Range<u32>,
+        cache: &TraceCache,
+        transform: Transform2F,
+        options: &RenderOptions,
+    ) -> Vec<Result<PageTrace, RenderError>>
+    where
+        B: PdfBackend + Sync,
+        OC: PdfCache<Result<AnySync, Arc<PdfError>>> + Sync,
+        SC: PdfCache<Result<Arc<[u8]>, Arc<PdfError>>> + Sync,
+        L: Log + Sync,
+    {
+        self.pool.install(|| {
+            pages
+                .into_par_iter()
+                .map(|page_nr| {
+                    let page = file.get_page(page_nr)?;
+                    let resolver = file.resolver();
+                    let mut clip_paths = vec![];
+                    let mut tracer = Tracer::new(cache, &mut clip_paths);
+                    let result = render_page_with(&mut tracer, &resolver, &page, transform, options)?;
+                    let view_box = tracer.view_box();
+                    let items = tracer.finish();
+                    Ok(PageTrace {
+                        items,
+                        clip_paths,
+                        view_box,
+                        status: result.status,
+                    })
+                })
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pdf::file::FileOptions;
+    use crate::testutil::PdfBuilder;
+    use crate::StaticFontProvider;
+
+    fn is_shared<T: Send + Sync>() {}
+
+    #[test]
+    fn caches_are_shared() {
+        is_shared::<Cache>();
+        is_shared::<TraceCache>();
+    }
+
+    #[test]
+    fn pages_stay_in_order() {
+        let mut pdf = PdfBuilder::new();
+        for n in 1..=6 {
+            let content: String = (0..n).map(|i| format!("{} 0 1 1 re f ", 2 * i)).collect();
+            pdf.page((10. * n as f32, 10.), "", &content);
+        }
+        let file = FileOptions::cached().load(pdf.finish()).unwrap();
+        let cache = TraceCache::with_font_provider(Arc::new(StaticFontProvider::new(&[])));
+
+        let renderer = ParallelRenderer::new(3).unwrap();
+        std::assert_eq!(renderer.num_threads(), 3);
+        let traces = renderer.trace_pages(&file, 1..6, &cache, Transform2F::default(), &RenderOptions::default());
+        std::assert_eq!(traces.len(), 5);
+        for (trace, n) in traces.into_iter().zip(2..) {
+            let trace = trace.unwrap();
+            std::assert_eq!(trace.items.len(), n);
+            std::assert_eq!(trace.view_box.width().round(), (10. * n as f32 * 25.4 / 72.).round());
+            assert!(trace.status.is_complete());
+        }
+
+        // every page stops before its third operator
+        let options = RenderOptions::new().max_ops(2);
+        for trace in renderer.trace_pages(&file, 2..6, &cache, Transform2F::default(), &options) {
+            let trace = trace.unwrap();
+            std::assert_eq!(trace.items.len(), 1);
+            std::assert_eq!(trace.status, RenderStatus::Truncated(crate::Truncation::Ops));
+        }
+
+        // a cancelled render stops all pages
+        let token = crate::CancelToken::new();
+        token.cancel();
+        let options = RenderOptions::new().cancel_token(token);
+        for trace in renderer.trace_pages(&file, 0..6, &cache, Transform2F::default(), &options) {
+            std::assert_eq!(trace.unwrap().status, RenderStatus::Cancelled);
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! Cleanup of extracted text: ligatures, hyphenation, bidi order and invisible characters.
+//!
+//! Every step keeps track of where each character came from, so results can be mapped
+//! back to `TextChar`s of a span or `LayoutChar`s of a page.
+
+use unicode_bidi::BidiInfo;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::textlayout::{LayoutChar, PageText};
+use crate::{TextChar, TextSpan};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    None,
+    /// only split ligatures like `ﬁ` and `ﬄ`
+    Ligatures,
+    /// full NFKC, which also replaces e.g. `²` by `2` and `…` by `...`
+    Nfkc,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PostProcess {
+    pub normalization: Normalization,
+    /// join words hyphenated at the end of a line and drop soft hyphens
+    pub dehyphenate: bool,
+    /// reorder right-to-left runs, which PDFs store in visual order, into logical order
+    pub bidi: bool,
+    /// drop characters from the private use areas
+    pub remove_private_use: bool,
+    /// drop control characters other than newline and tab
+    pub remove_control: bool,
+}
+impl Default for PostProcess {
+    fn default() -> Self {
+        PostProcess {
+            normalization: Normalization::Ligatures,
+            dehyphenate: true,
+            bidi: true,
+            remove_private_use: true,
+            remove_control: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MappedChar {
+    /// byte offset in `ProcessedText::text`
+    pub offset: usize,
+    /// byte offset of the character it came from in the input
+    pub source: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProcessedText {
+    pub text: String,
+    /// one entry per char of `text`
+    pub chars: Vec<MappedChar>,
+}
+impl ProcessedText {
+    /// Byte offset in the input of the character at `offset` in `text`.
+    pub fn source(&self, offset: usize) -> Option<usize> {
+        let idx = self.chars.partition_point(|c| c.offset <= offset).checked_sub(1)?;
+        Some(self.chars[idx].source)
+    }
+
+    /// The glyph of `span` the character at `offset` came from.
+    /// Only valid if this was created by `process_span` for the same span.
+    pub fn text_char<'a>(&self, span: &'a TextSpan, offset: usize) -> Option<&'a TextChar> {
+        let source = self.source(offset)?;
+        let idx = span.chars.partition_point(|c| c.offset <= source).checked_sub(1)?;
+        span.chars.get(idx)
+    }
+
+    /// The glyph of `page` the character at `offset` came from.
+    /// Only valid if this was created by `process_page` for the same page.
+    pub fn layout_char<'a>(&self, page: &'a PageText, offset: usize) -> Option<&'a LayoutChar> {
+        let source = self.source(offset)?;
+        let idx = page.chars.partition_point(|c| c.range.start <= source).checked_sub(1)?;
+        page.chars.get(idx).filter(|c| c.range.contains(&source))
+    }
+}
+
+/// Process the text of a single span. Hyphenation is only fixed within the span.
+pub fn process_span(span: &TextSpan, options: &PostProcess) -> ProcessedText {
+    process(&span.text, options)
+}
+
+/// Process the text of a page, including words hyphenated across lines.
+pub fn process_page(page: &PageText, options: &PostProcess) -> ProcessedText {
+    process(&page.text, options)
+}
+
+pub fn process(text: &str, options: &PostProcess) -> ProcessedText {
+    let mut chars: Vec<(char, usize)> = text
+        .char_indices()
+        .map(|(i, c)| (c, i))
+        .filter(|&(c, _)| keep(c, options))
+        .collect();
+
+    if options.normalization != Normalization::None {
+        chars = normalize(&chars, options.normalization);
+    }
+    if options.bidi {
+        reorder_lines(&mut chars);
+    }
+    if options.dehyphenate {
+        chars = dehyphenate(&chars);
+    }
+
+    let mut out = ProcessedText::default();
+    for (c, source) in chars {
+        out.chars.push(MappedChar {
+            offset: out.text.len(),
+            source,
+        });
+        out.text.push(c);
+    }
+    out
+}
+
+fn keep(c: char, options: &PostProcess) -> bool {
+    let private_use = matches!(c as u32, 0xE000..=0xF8FF | 0xF0000..=0xFFFFD | 0x100000..=0x10FFFD);
+    if options.remove_private_use && private_use {
+        return false;
+    }
+    if options.remove_control && c.is_control() && c != '\n' && c != '\t' {
+        return false;
+    }
+    true
+}
+
+fn is_ligature(c: char) -> bool {
+    matches!(c, '\u{FB00}'..='\u{FB06}' | '\u{FB13}'..='\u{FB17}')
+}
+
+/// Normalize each base character together with the combining marks that follow it.
+/// All output characters of a cluster map to the source of its first character.
+fn normalize(chars: &[(char, usize)], normalization: Normalization) -> Vec<(char, usize)> {
+    let mut out = Vec::with_capacity(chars.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let (c, source) = chars[i];
+        let mut end = i + 1;
+        while end < chars.len() && is_combining_mark(chars[end].0) {
+            end += 1;
+        }
+        let cluster = chars[i..end].iter().map(|&(c, _)| c);
+        match normalization {
+            Normalization::Nfkc => out.extend(cluster.nfkc().map(|c| (c, source))),
+            Normalization::Ligatures if is_ligature(c) => {
+                out.extend(cluster.nfkd().map(|c| (c, source)))
+            }
+            _ => out.extend(chars[i..end].iter().copied()),
+        }
+        i = end;
+    }
+    out
+}
+
+/// PDFs draw right-to-left text in visual order. Reorder each line as bidi text
+/// would be displayed, which turns visual into logical order.
+fn reorder_lines(chars: &mut [(char, usize)]) {
+    for line in chars.split_mut(|&(c, _)| c == '\n') {
+        let text: String = line.iter().map(|&(c, _)| c).collect();
+        let info = BidiInfo::new(&text, None);
+        if !info.has_rtl() {
+            continue;
+        }
+        // a U+2029 starts another paragraph, leave such lines alone
+        let para = match info.paragraphs.as_slice() {
+            [para] => para,
+            _ => continue,
+        };
+        let levels = info.reordered_levels_per_char(para, para.range.clone());
+        let reordered: Vec<_> = BidiInfo::reorder_visual(&levels).into_iter().map(|idx| line[idx]).collect();
+        line.copy_from_slice(&reordered);
+    }
+}
+
+fn is_hyphen(c: char) -> bool {
+    matches!(c, '-' | '\u{00AD}' | '\u{2010}')
+}
+
+/// Remove soft hyphens, and hyphens at the end of a line that are followed by a lowercase letter.
+fn dehyphenate(chars: &[(char, usize)]) -> Vec<(char, usize)> {
+    let mut out = Vec::with_capacity(chars.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i].0;
+        if is_hyphen(c) {
+            let after_letter = out.last().map(|&(p, _): &(char, usize)| p.is_alphabetic()).unwrap_or(false);
+            let line_break = chars.get(i + 1).map(|&(n, _)| n == '\n').unwrap_or(false);
+            let continues = chars.get(i + 2).map(|&(n, _)| n.is_lowercase()).unwrap_or(false);
+            if after_letter && line_break && continues {
+                // drop the hyphen and the newline
+                i += 2;
+                continue;
+            }
+            if c == '\u{00AD}' {
+                i += 1;
+                continue;
+            }
+        }
+        out.push(chars[i]);
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ligatures() {
+        let out = process("\u{FB01}nd", &PostProcess::default());
+        std::assert_eq!(out.text, "find");
+        // both letters of the ligature map to it, `n` follows its three bytes
+        std::assert_eq!(out.source(0), Some(0));
+        std::assert_eq!(out.source(1), Some(0));
+        std::assert_eq!(out.source(2), Some(3));
+
+        let none = PostProcess { normalization: Normalization::None, ..Default::default() };
+        std::assert_eq!(process("\u{FB01}", &none).text, "\u{FB01}");
+        std::assert_eq!(process("x\u{B2}", &PostProcess::default()).text, "x\u{B2}");
+        let nfkc = PostProcess { normalization: Normalization::Nfkc, ..Default::default() };
+        std::assert_eq!(process("x\u{B2}\u{2026}", &nfkc).text, "x2...");
+    }
+
+    #[test]
+    fn hyphens() {
+        let options = PostProcess::default();
+        std::assert_eq!(process("exam-\nple", &options).text, "example");
+        std::assert_eq!(process("exam\u{2010}\nple", &options).text, "example");
+        std::assert_eq!(process("co\u{AD}op", &options).text, "coop");
+        // not followed by a lowercase letter, or not at the end of a line
+        std::assert_eq!(process("North-\nSouth", &options).text, "North-\nSouth");
+        std::assert_eq!(process("well-known", &options).text, "well-known");
+        std::assert_eq!(process("1-\nple", &options).text, "1-\nple");
+
+        let out = process("exam-\nple", &options);
+        std::assert_eq!(out.source(4), Some(6));
+
+        let keep = PostProcess { dehyphenate: false, ..options };
+        std::assert_eq!(process("exam-\nple", &keep).text, "exam-\nple");
+    }
+
+    #[test]
+    fn bidi() {
+        let options = PostProcess::default();
+        // stored in visual order, so the hebrew word is reversed
+        let out = process("abc \u{5DD}\u{5D5}\u{5DC}\u{5E9}\nxyz", &options);
+        std::assert_eq!(out.text, "abc \u{5E9}\u{5DC}\u{5D5}\u{5DD}\nxyz");
+        // the first logical letter is the last one drawn
+        std::assert_eq!(out.source(4), Some(10));
+        std::assert_eq!(out.source(out.text.len() - 1), Some(15));
+
+        let off = PostProcess { bidi: false, ..options };
+        std::assert_eq!(process("\u{5DD}\u{5D5}", &off).text, "\u{5DD}\u{5D5}");
+    }
+
+    #[test]
+    fn invisible_chars() {
+        let options = PostProcess::default();
+        std::assert_eq!(process("a\u{E000}b\u{7}c\td\n", &options).text, "abc\td\n");
+
+        let keep = PostProcess { remove_private_use: false, remove_control: false, ..options };
+        std::assert_eq!(process("a\u{E000}b\u{7}", &keep).text, "a\u{E000}b\u{7}");
+    }
+
+    #[test]
+    fn source_offsets() {
+        let out = process("a\u{E000}b", &PostProcess::default());
+        std::assert_eq!(out.text, "ab");
+        std::assert_eq!(out.source(0), Some(0));
+        std::assert_eq!(out.source(1), Some(4));
+        std::assert_eq!(ProcessedText::default().source(0), None);
+    }
+}
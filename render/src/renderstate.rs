@@ -4,8 +4,14 @@ use pdf::object::*;
 use pdf::primitive::{Primitive, Dictionary};
 use pdf::content::{Op, Matrix, Point, Rect, Color, Rgb, Cmyk, Winding, FormXObject};
 use pdf::error::{PdfError, Result};
+use pdf::font::Font as PdfFont;
 use pdf::content::TextDrawAdjusted;
 use crate::backend::{Backend, BlendMode, FillMode};
+use crate::budget::BudgetTracker;
+use crate::limits::LimitError;
+use std::sync::Arc;
+use std::rc::Rc;
+use std::cell::Cell;
 
 use pathfinder_geometry::{
     vector::Vector2F,
@@ -20,7 +26,9 @@ use super::{
     graphicsstate::GraphicsState,
     textstate::{TextState, Span},
     DrawMode,
+    FontEntry,
     TextSpan,
+    TextChar,
     Fill,
 };
 
@@ -74,19 +82,56 @@ impl Cvt for Cmyk {
     }
 }
 
+/// An open `BDC`/`BMC` … `EMC` sequence.
+#[derive(Clone, Debug)]
+struct MarkedContent {
+    /// marked-content identifier, which links the content to the structure tree
+    mcid: Option<u32>,
+    /// the form xobject whose content stream the MCID belongs to, `None` for the page
+    stream: Option<PlainRef>,
+    /// `/ActualText`, or else `/Alt`, replaces the text of the glyphs drawn inside
+    replacement: Option<String>,
+    /// set once `replacement` was emitted, so it appears only once.
+    /// Shared with the copies in form xobjects drawn inside.
+    replaced: Rc<Cell<bool>>,
+}
+impl MarkedContent {
+    fn new(properties: Option<&Dictionary>, stream: Option<PlainRef>) -> Self {
+        let string = |key: &str| properties
+            .and_then(|dict| dict.get(key))
+            .and_then(|p| p.as_string().ok())
+            .and_then(|s| s.to_string().ok());
+        MarkedContent {
+            mcid: properties
+                .and_then(|dict| dict.get("MCID"))
+                .and_then(|p| p.as_integer().ok())
+                .and_then(|n| u32::try_from(n).ok()),
+            stream,
+            replacement: string("ActualText").or_else(|| string("Alt")),
+            replaced: Rc::new(Cell::new(false)),
+        }
+    }
+}
+
 pub struct RenderState<'a, R: Resolve, B: Backend> {
     graphics_state: GraphicsState<'a, B>,
     text_state: TextState,
     stack: Vec<(GraphicsState<'a, B>, TextState)>,
     current_outline: Outline,
     current_contour: Contour,
+    // number of segments in current_outline and current_contour
+    path_segments: usize,
     resolve: &'a R,
     resources: &'a Resources,
     backend: &'a mut B,
+    budget: &'a BudgetTracker,
+    marked_content: Vec<MarkedContent>,
+    // the form xobject being drawn, `None` for the page
+    stream: Option<PlainRef>,
 }
 
 impl<'a, R: Resolve, B: Backend> RenderState<'a, R, B> {
-    pub fn new(backend: &'a mut B, resolve: &'a R, resources: &'a Resources, root_transformation: Transform2F) -> Self {
+    pub fn new(backend: &'a mut B, resolve: &'a R, resources: &'a Resources, root_transformation: Transform2F, budget: &'a BudgetTracker) -> Self {
         let graphics_state = GraphicsState {
             transform: root_transformation,
             fill_color: Fill::black(),
@@ -111,8 +156,11 @@ impl<'a, R: Resolve, B: Backend> RenderState<'a, R, B> {
             overprint_fill: false,
             overprint_stroke: false,
             overprint_mode: 0,
+            clip_depth: 0,
         };
-        let text_state = TextState::new();
+        let mut text_state = TextState::new();
+        text_state.fit_substituted_widths = budget.fit_substituted_widths;
+        text_state.region = budget.region;
         let stack = vec![];
         let current_outline = Outline::new();
         let current_contour = Contour::new();
@@ -123,44 +171,112 @@ impl<'a, R: Resolve, B: Backend> RenderState<'a, R, B> {
             stack,
             current_outline,
             current_contour,
+            path_segments: 0,
             resources,
             resolve,
             backend,
+            budget,
+            marked_content: vec![],
+            stream: None,
         }
     }
     fn draw(&mut self, mode: &DrawMode, fill_rule: FillRule) {
         self.flush();
-        self.backend.draw(&self.current_outline, mode, fill_rule, self.graphics_state.transform, self.graphics_state.clip_path_id);
+        if self.budget.visible(self.outline_bounds(mode)) {
+            self.backend.draw(&self.current_outline, mode, fill_rule, self.graphics_state.transform, self.graphics_state.clip_path_id);
+        }
         self.current_outline.clear();
+        self.path_segments = 0;
+    }
+    /// Output bounds of the current path, including the stroke.
+    fn outline_bounds(&self, mode: &DrawMode) -> RectF {
+        let tr = self.graphics_state.transform;
+        let bounds = tr * self.current_outline.bounds();
+        match *mode {
+            DrawMode::Stroke { ref stroke_mode, .. } | DrawMode::FillStroke { ref stroke_mode, .. } => {
+                // generous enough for miter joins
+                let scale = tr.m11().abs().max(tr.m12().abs()).max(tr.m21().abs()).max(tr.m22().abs());
+                bounds.dilate(Vector2F::splat(stroke_mode.style.line_width * scale * 2.0))
+            }
+            DrawMode::Fill { .. } => bounds,
+        }
+    }
+    /// Output bounds of an image drawn with the current transform.
+    fn image_bounds(&self) -> RectF {
+        self.graphics_state.transform * RectF::new(Vector2F::zero(), Vector2F::splat(1.0))
+    }
+    fn add_path_segments(&mut self, n: usize) -> Result<()> {
+        self.budget.add_path_segments(n)?;
+        self.path_segments += n;
+        if self.path_segments > self.budget.limits.max_path_segments {
+            return Err(self.budget.limit_exceeded(LimitError::PathSegments(self.path_segments)));
+        }
+        Ok(())
+    }
+    /// Draw all operators until they run out or the budget is exhausted.
+    pub fn draw_ops(&mut self, ops: &'a [Op]) -> Result<()> {
+        for (i, op) in ops.iter().enumerate() {
+            if !self.budget.next_op() {
+                break;
+            }
+            debug!("op {}: {:?}", i, op);
+            self.draw_op(op, i)?;
+        }
+        Ok(())
     }
     #[allow(unused_variables)]
     pub fn draw_op(&mut self, op: &'a Op, op_nr: usize) -> Result<()> {
         self.backend.inspect_op(op);
         self.backend.bug_op(op_nr);
         match *op {
-            Op::BeginMarkedContent { .. } => {}
-            Op::EndMarkedContent { .. } => {}
+            Op::BeginMarkedContent { ref properties, .. } => {
+                let properties = properties.as_ref().and_then(|p| {
+                    self.get_properties(p)
+                        .map_err(|e| warn!("marked content properties: {:?}", e))
+                        .ok()
+                });
+                let marked_content = MarkedContent::new(properties, self.stream);
+                let before = self.mcid();
+                self.marked_content.push(marked_content);
+                if self.mcid() != before {
+                    self.set_mcid();
+                }
+            }
+            Op::EndMarkedContent { .. } => {
+                let before = self.mcid();
+                if self.marked_content.pop().is_none() {
+                    debug!("EMC without BDC");
+                }
+                if self.mcid() != before {
+                    self.set_mcid();
+                }
+            }
             Op::MarkedContentPoint { .. } => {}
             Op::Close => {
                 self.current_contour.close();
             }
             Op::MoveTo { p } => {
                 self.flush();
+                self.add_path_segments(1)?;
                 self.current_contour.push_endpoint(p.cvt());
             },
             Op::LineTo { p } => {
+                self.add_path_segments(1)?;
                 self.current_contour.push_endpoint(p.cvt());
             },
             Op::CurveTo { c1, c2, p } => {
+                self.add_path_segments(1)?;
                 self.current_contour.push_cubic(c1.cvt(), c2.cvt(), p.cvt());
             },
             Op::Rect { rect } => {
                 self.flush();
+                self.add_path_segments(4)?;
                 self.current_outline.push_contour(Contour::from_rect(rect.cvt()));
             },
             Op::EndPath => {
                 self.current_contour.clear();
                 self.current_outline.clear();
+                self.path_segments = 0;
             }
             Op::Stroke => {
                 self.draw(&DrawMode::Stroke { 
@@ -200,6 +316,11 @@ impl<'a, R: Resolve, B: Backend> RenderState<'a, R, B> {
             Op::Shade { ref name } => {},
             Op::Clip { winding } => {
                 self.flush();
+                let clip_depth = self.graphics_state.clip_depth + 1;
+                if clip_depth > self.budget.limits.max_clip_depth {
+                    return Err(self.budget.limit_exceeded(LimitError::ClipDepth(clip_depth)));
+                }
+                self.graphics_state.clip_depth = clip_depth;
                 let mut path = self.current_outline.clone().transformed(&self.graphics_state.transform);
                 let clip_path_rect = to_rect(&path);
 
@@ -234,6 +355,9 @@ impl<'a, R: Resolve, B: Backend> RenderState<'a, R, B> {
             },
 
             Op::Save => {
+                if self.stack.len() >= self.budget.limits.max_stack_depth {
+                    return Err(self.budget.limit_exceeded(LimitError::StackDepth(self.stack.len() + 1)));
+                }
                 self.stack.push((self.graphics_state.clone(), self.text_state.clone()));
             },
             Op::Restore => {
@@ -262,7 +386,7 @@ impl<'a, R: Resolve, B: Backend> RenderState<'a, R, B> {
                 
                 if let Some((font_ref, size)) = gs.font {
                     let font = self.resolve.get(font_ref)?;
-                    if let Some(e) = self.backend.get_font(&MaybeRef::Indirect(font), self.resolve)? {
+                    if let Some(e) = self.get_font(&MaybeRef::Indirect(font))? {
                         debug!("new font: {} at size {}", e.name, size);
                         self.text_state.font_entry = Some(e);
                         self.text_state.font_size = size;
@@ -309,7 +433,7 @@ impl<'a, R: Resolve, B: Backend> RenderState<'a, R, B> {
             Op::TextFont { ref name, size } => {
                 let font = match self.resources.fonts.get(name) {
                     Some(font_ref) => {
-                        self.backend.get_font(font_ref, self.resolve)?
+                        self.get_font(font_ref)?
                     },
                     None => None
                 };
@@ -357,11 +481,13 @@ impl<'a, R: Resolve, B: Backend> RenderState<'a, R, B> {
                 let xobject = self.resolve.get(xobject_ref)?;
                 let mode = self.blend_mode_fill();
                 match *xobject {
+                    XObject::Image(_) if !self.budget.visible(self.image_bounds()) => {}
                     XObject::Image(ref im) => {
+                        self.check_image(im)?;
                         self.backend.draw_image(xobject_ref, im, self.resources, self.graphics_state.transform, mode, self.graphics_state.clip_path_id, self.resolve);
                     }
                     XObject::Form(ref content) => {
-                        self.draw_form(content)?;
+                        self.draw_form(content, xobject_ref.get_inner())?;
                     }
                     XObject::Postscript(ref ps) => {
                         let data = ps.data(self.resolve)?;
@@ -371,6 +497,10 @@ impl<'a, R: Resolve, B: Backend> RenderState<'a, R, B> {
                 }
             },
             Op::InlineImage { ref image } => {
+                if !self.budget.visible(self.image_bounds()) {
+                    return Ok(());
+                }
+                self.check_image(image)?;
                 let mode = self.blend_mode_fill();
                 self.backend.draw_inline_image(image, &self.resources, self.graphics_state.transform, mode, self.graphics_state.clip_path_id, self.resolve);
             }
@@ -379,6 +509,24 @@ impl<'a, R: Resolve, B: Backend> RenderState<'a, R, B> {
         Ok(())
     }
 
+    fn get_font(&mut self, font_ref: &MaybeRef<PdfFont>) -> Result<Option<Arc<FontEntry>>> {
+        self.backend.get_font(font_ref, self.resolve, &self.budget.limits)
+            .map_err(|e| self.budget.error(e))
+    }
+
+    /// Refuse images and soft masks that exceed the limits before the backend decodes them.
+    fn check_image(&self, im: &ImageXObject) -> Result<()> {
+        let limits = &self.budget.limits;
+        let check = |width, height| limits.check_image(width, height)
+            .map_err(|e| self.budget.limit_exceeded(e));
+        check(im.width as u32, im.height as u32)?;
+        if let Some(mask) = im.smask {
+            let mask = self.resolve.get(mask)?;
+            check(mask.width as u32, mask.height as u32)?;
+        }
+        Ok(())
+    }
+
     fn blend_mode_fill(&self) -> BlendMode {
         if self.graphics_state.overprint_fill {
             BlendMode::Darken
@@ -401,6 +549,17 @@ impl<'a, R: Resolve, B: Backend> RenderState<'a, R, B> {
 
         inner(&mut self.backend, &mut self.text_state, &mut self.graphics_state, &mut span);
 
+        // the outermost marked content with a replacement text takes the place of all text inside it
+        if let Some(m) = self.marked_content.iter().find(|m| m.replacement.is_some()) {
+            span.text.clear();
+            span.chars.clear();
+            if let (false, Some(text)) = (m.replaced.get(), m.replacement.as_ref()) {
+                span.text.push_str(text);
+                span.chars.push(TextChar { offset: 0, pos: 0.0, width: span.width });
+                m.replaced.set(true);
+            }
+        }
+
         let transform = self.graphics_state.transform * tm * Transform2F::from_scale(Vector2F::new(1.0, -1.0));
         let p1 = origin;
         let p2 = (tm * Transform2F::from_translation(Vector2F::new(span.width, self.text_state.font_size))).translation();
@@ -419,7 +578,9 @@ impl<'a, R: Resolve, B: Backend> RenderState<'a, R, B> {
             alpha: self.graphics_state.fill_color_alpha,
             mode: self.text_state.mode,
             transform,
-            op_nr
+            op_nr,
+            mcid: self.mcid().map(|(mcid, _)| mcid),
+            mcid_stream: self.mcid().and_then(|(_, stream)| stream),
         }, clip);
     }
 
@@ -442,7 +603,7 @@ impl<'a, R: Resolve, B: Backend> RenderState<'a, R, B> {
             self.current_contour.clear();
         }
     }
-    fn draw_form(&mut self, form: &FormXObject) -> Result<()> {
+    fn draw_form(&mut self, form: &FormXObject, stream: PlainRef) -> Result<()> {
         let graphics_state = GraphicsState {
             stroke_alpha: self.graphics_state.stroke_color_alpha,
             fill_alpha: self.graphics_state.fill_color_alpha,
@@ -462,19 +623,31 @@ impl<'a, R: Resolve, B: Backend> RenderState<'a, R, B> {
             stack: vec![],
             current_outline: Outline::new(),
             current_contour: Contour::new(),
+            path_segments: 0,
             backend: self.backend,
             resolve: self.resolve,
+            budget: self.budget,
+            // the form is part of the marked content it is drawn in
+            marked_content: self.marked_content.clone(),
+            stream: Some(stream),
         };
         
         let ops = t!(form.operations(self.resolve));
-        for (i, op) in ops.iter().enumerate() {
-            debug!(" form op {}: {:?}", i, op);
-            inner.draw_op(op, i)?;
+        self.budget.enter_form()?;
+        let result = inner.draw_ops(&ops);
+        self.budget.leave_form();
+        result
+    }
+    /// The MCID of the innermost marked content that has one, and the stream it belongs to.
+    fn mcid(&self) -> Option<(u32, Option<PlainRef>)> {
+        self.marked_content.iter().rev().find_map(|m| Some((m.mcid?, m.stream)))
+    }
+    fn set_mcid(&mut self) {
+        match self.mcid() {
+            Some((mcid, stream)) => self.backend.set_mcid(Some(mcid), stream),
+            None => self.backend.set_mcid(None, None),
         }
-
-        Ok(())
     }
-    #[allow(dead_code)]
     fn get_properties<'b>(&'b self, p: &'b Primitive) -> Result<&'b Dictionary> {
         match p {
             Primitive::Dictionary(ref dict) => Ok(dict),
@@ -694,4 +867,63 @@ fn to_rect(o: &Outline) -> Option<RectF> {
     }
 
     Some(c.bounds())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{trace, PdfBuilder};
+    use crate::tracer::DrawItem;
+
+    /// Text and MCID of the spans on a page that draws `content`, with a Type3 font `/F1`
+    /// and a form `/Fm0` that shows `A`.
+    fn spans(content: &str) -> Vec<(String, Option<u32>)> {
+        let mut pdf = PdfBuilder::new();
+        let glyph = pdf.stream("", b"1000 0 0 0 1000 1000 d1 0 0 1000 1000 re f");
+        let font = pdf.add(format!(
+            "<< /Type /Font /Subtype /Type3 /FontBBox [0 0 1000 1000] /FontMatrix [0.001 0 0 0.001 0 0] \
+            /CharProcs << /g {} 0 R >> /Encoding << /Type /Encoding /Differences [65 /g /g] >> \
+            /FirstChar 65 /LastChar 66 /Widths [1000 1000] /Resources << >> >>",
+            glyph
+        ));
+        let form = pdf.stream(
+            &format!("/Type /XObject /Subtype /Form /BBox [0 0 100 100] /Resources << /Font << /F1 {} 0 R >> >>", font),
+            b"BT /F1 10 Tf 0 0 Td (A) Tj ET",
+        );
+        pdf.page((100., 100.), &format!("/Font << /F1 {} 0 R >> /XObject << /Fm0 {} 0 R >>", font, form), content);
+        trace(pdf.finish())
+            .into_iter()
+            .filter_map(|item| match item {
+                DrawItem::Text(span, _) => Some((span.text, span.mcid)),
+                _ => None,
+            })
+            .collect()
+    }
+    fn texts(content: &str) -> Vec<String> {
+        spans(content).into_iter().map(|(text, _)| text).collect()
+    }
+
+    #[test]
+    fn actual_text() {
+        std::assert_eq!(texts("/Span << /ActualText (fi) >> BDC BT /F1 10 Tf 0 0 Td (A) Tj (B) Tj ET EMC"), ["fi", ""]);
+        std::assert_eq!(texts("/Figure << /Alt (alt) >> BDC BT /F1 10 Tf (A) Tj ET EMC"), ["alt"]);
+        // the outermost replacement wins
+        std::assert_eq!(
+            texts("/Span << /ActualText (outer) >> BDC /Span << /ActualText (inner) >> BDC BT /F1 10 Tf (A) Tj ET EMC EMC"),
+            ["outer"]
+        );
+    }
+
+    #[test]
+    fn actual_text_in_forms() {
+        // emitted once, even though the text is drawn by two form xobjects
+        std::assert_eq!(texts("/Span << /ActualText (x) >> BDC /Fm0 Do /Fm0 Do EMC"), ["x", ""]);
+    }
+
+    #[test]
+    fn mcid() {
+        let mcids: Vec<_> = spans("/P << /MCID 2 >> BDC BT /F1 10 Tf (A) Tj ET EMC BT /F1 10 Tf (B) Tj ET")
+            .into_iter()
+            .map(|(_, mcid)| mcid)
+            .collect();
+        std::assert_eq!(mcids, [Some(2), None]);
+    }
+}
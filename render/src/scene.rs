@@ -3,7 +3,7 @@ use pathfinder_content::{
     fill::FillRule,
     stroke::OutlineStrokeToFill,
     outline::Outline,
-    pattern::Pattern,
+    pattern::{Image, Pattern},
     dash::OutlineDash,
 };
 use pathfinder_renderer::{
@@ -11,41 +11,56 @@ use pathfinder_renderer::{
     paint::{PaintId, Paint},
 };
 use pathfinder_geometry::{
-    vector::Vector2F,
+    vector::{Vector2F, Vector2I},
     rect::RectF, transform2d::Transform2F,
 };
 use pdf::object::{Ref, XObject, ImageXObject, Resolve, Resources, MaybeRef};
-use crate::backend;
+use crate::backend::{self, Stroke};
 
-use super::{FontEntry, TextSpan, DrawMode, Backend, Fill, Cache};
+use super::{FontEntry, TextSpan, DrawMode, Backend, Fill, Cache, Limits, RenderError};
 use pdf::font::Font as PdfFont;
-use pdf::error::PdfError;
 use std::sync::Arc;
 
 pub struct SceneBackend<'a> {
     scene: Scene,
-    cache: &'a mut Cache,
+    cache: &'a Cache,
+    downsample_images: bool,
+    background: Option<ColorU>,
 }
 
 impl<'a> SceneBackend<'a> {
-    pub fn new(cache: &'a mut Cache) -> Self {
+    pub fn new(cache: &'a Cache) -> Self {
         let scene = Scene::new();
         SceneBackend {
             scene,
-            cache
+            cache,
+            downsample_images: false,
+            background: Some(ColorU::white()),
         }
     }
+    /// The color the view box is filled with before the page is drawn, white by default.
+    /// With `None`, the background stays transparent.
+    pub fn set_background(&mut self, color: Option<ColorU>) {
+        self.background = color;
+    }
+    /// Scale images down to the size they are drawn at, if that is much smaller than their resolution.
+    pub fn set_downsample_images(&mut self, downsample: bool) {
+        self.downsample_images = downsample;
+    }
     pub fn finish(self) -> Scene {
         self.scene
     }
     fn paint(&mut self, fill: Fill, alpha: f32) -> PaintId {
-        let paint = match fill {
-            Fill::Solid(r, g, b) => Paint::from_color(ColorF::new(r, g, b, alpha).to_u8()),
-            Fill::Pattern(_) => {
-                Paint::black()
-            }
-        };
-        self.scene.push_paint(&paint)
+        self.scene.push_paint(&paint(fill, alpha))
+    }
+}
+
+pub(crate) fn paint(fill: Fill, alpha: f32) -> Paint {
+    match fill {
+        Fill::Solid(r, g, b) => Paint::from_color(ColorF::new(r, g, b, alpha).to_u8()),
+        Fill::Pattern(_) => {
+            Paint::black()
+        }
     }
 }
 
@@ -60,9 +75,10 @@ impl<'a> Backend for SceneBackend<'a> {
     fn set_view_box(&mut self, view_box: RectF) {
         self.scene.set_view_box(view_box);
 
-        let white = self.scene.push_paint(&Paint::from_color(ColorU::white()));
-        self.scene.push_draw_path(DrawPath::new(Outline::from_rect(view_box), white));
-
+        if let Some(color) = self.background {
+            let paint = self.scene.push_paint(&Paint::from_color(color));
+            self.scene.push_draw_path(DrawPath::new(Outline::from_rect(view_box), paint));
+        }
     }
     fn draw(&mut self, outline: &Outline, mode: &DrawMode, fill_rule: FillRule, transform: Transform2F, clip: Option<ClipPathId>) {
         match mode {
@@ -79,19 +95,7 @@ impl<'a> Backend for SceneBackend<'a> {
         match mode {
             DrawMode::Stroke { stroke, stroke_mode }| DrawMode::FillStroke { stroke, stroke_mode, .. } => {
                 let paint = self.paint(stroke.color, stroke.alpha);
-                let contour = match stroke_mode.dash_pattern {
-                    Some((ref pat, phase)) => {
-                        let dashed = OutlineDash::new(outline, &*pat, phase).into_outline();
-                        let mut stroke = OutlineStrokeToFill::new(&dashed, stroke_mode.style);
-                        stroke.offset();
-                        stroke.into_outline()
-                    }
-                    None => {
-                        let mut stroke = OutlineStrokeToFill::new(outline, stroke_mode.style);
-                        stroke.offset();
-                        stroke.into_outline()
-                    }
-                };
+                let contour = stroke_outline(outline, stroke_mode);
                 let mut draw_path = DrawPath::new(contour.transformed(&transform), paint);
                 draw_path.set_clip_path(clip);
                 draw_path.set_fill_rule(fill_rule);
@@ -104,6 +108,12 @@ impl<'a> Backend for SceneBackend<'a> {
     }
     fn draw_image(&mut self, xobject_ref: Ref<XObject>, im: &ImageXObject, resources: &Resources, transform: Transform2F, mode: backend::BlendMode, clip: Option<ClipPathId>,  resolve: &impl Resolve) {
         if let Ok(ref image) = *self.cache.get_image(xobject_ref, im, resources, resolve, mode).0 {
+            let device = transform * RectF::new(Vector2F::default(), Vector2F::new(1.0, 1.0));
+            let target = Vector2I::new(device.width().ceil().max(1.0) as i32, device.height().ceil().max(1.0) as i32);
+            let image = match self.downsample_images {
+                true if image.size().x() > 2 * target.x() && image.size().y() > 2 * target.y() => downsample(image, target),
+                _ => image.clone(),
+            };
             let size = image.size();
             let size_f = size.to_f32();
             let outline = Outline::from_rect(transform * RectF::new(Vector2F::default(), Vector2F::new(1.0, 1.0)));
@@ -111,7 +121,7 @@ impl<'a> Backend for SceneBackend<'a> {
                 * Transform2F::from_scale(Vector2F::new(1.0 / size_f.x(), -1.0 / size_f.y()))
                 * Transform2F::from_translation(Vector2F::new(0.0, -size_f.y()));
 
-            let mut pattern = Pattern::from_image(image.clone());
+            let mut pattern = Pattern::from_image(image);
             pattern.apply_transform(im_tr);
             let paint = Paint::from_pattern(pattern);
             let paint_id = self.scene.push_paint(&paint);
@@ -126,13 +136,62 @@ impl<'a> Backend for SceneBackend<'a> {
 
     }
 
-    fn get_font(&mut self, font_ref: &MaybeRef<PdfFont>, resolve: &impl Resolve) -> Result<Option<Arc<FontEntry>>, PdfError> {
-        self.cache.get_font(font_ref, resolve)
+    fn get_font(&mut self, font_ref: &MaybeRef<PdfFont>, resolve: &impl Resolve, limits: &Limits) -> Result<Option<Arc<FontEntry>>, RenderError> {
+        self.cache.get_font(font_ref, resolve, limits)
     }
     fn add_text(&mut self, span: TextSpan, clip: Option<Self::ClipPathId>) {}
 }
 
-fn blend_mode(mode: backend::BlendMode) -> pathfinder_content::effects::BlendMode {
+/// The area covered by stroking `outline`, as a path to fill.
+pub(crate) fn stroke_outline(outline: &Outline, stroke_mode: &Stroke) -> Outline {
+    match stroke_mode.dash_pattern {
+        Some((ref pat, phase)) => {
+            let dashed = OutlineDash::new(outline, &*pat, phase).into_outline();
+            let mut stroke = OutlineStrokeToFill::new(&dashed, stroke_mode.style);
+            stroke.offset();
+            stroke.into_outline()
+        }
+        None => {
+            let mut stroke = OutlineStrokeToFill::new(outline, stroke_mode.style);
+            stroke.offset();
+            stroke.into_outline()
+        }
+    }
+}
+
+/// Box filter `image` down to `size`.
+pub(crate) fn downsample(image: &Image, size: Vector2I) -> Image {
+    let src = image.size();
+    let pixels = image.pixels();
+    let mut out = Vec::with_capacity((size.x() * size.y()) as usize);
+    let range = |i: i32, n: i32, src_n: i32| {
+        let start = (i as i64 * src_n as i64 / n as i64) as i32;
+        let end = (((i + 1) as i64 * src_n as i64 / n as i64) as i32).max(start + 1).min(src_n);
+        start..end
+    };
+    for y in 0..size.y() {
+        let ys = range(y, size.y(), src.y());
+        for x in 0..size.x() {
+            let xs = range(x, size.x(), src.x());
+            let (mut r, mut g, mut b, mut a, mut n) = (0u32, 0u32, 0u32, 0u32, 0u32);
+            for sy in ys.clone() {
+                let row = &pixels[(sy * src.x()) as usize..][..src.x() as usize];
+                for c in &row[xs.start as usize..xs.end as usize] {
+                    r += c.r as u32;
+                    g += c.g as u32;
+                    b += c.b as u32;
+                    a += c.a as u32;
+                    n += 1;
+                }
+            }
+            let n = n.max(1);
+            out.push(ColorU::new((r / n) as u8, (g / n) as u8, (b / n) as u8, (a / n) as u8));
+        }
+    }
+    Image::new(size, Arc::new(out))
+}
+
+pub(crate) fn blend_mode(mode: backend::BlendMode) -> pathfinder_content::effects::BlendMode {
     match mode {
         crate::BlendMode::Darken => pathfinder_content::effects::BlendMode::Multiply,
         crate::BlendMode::Overlay => pathfinder_content::effects::BlendMode::Overlay,
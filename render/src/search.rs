@@ -0,0 +1,222 @@
+//! Full-text search over the text of traced pages.
+//!
+//! ```ignore
+//! let search = Search::new("lorem ipsum", SearchOptions::default())?;
+//! for m in search.find(pages.iter().enumerate().map(|(nr, text)| (nr as u32, text))) {
+//!     println!("page {}: {:?}", m.page, m.quads);
+//! }
+//! ```
+
+use std::ops::Range;
+
+use pathfinder_geometry::vector::Vector2F;
+use regex::{Regex, RegexBuilder};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::textlayout::PageText;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub case_sensitive: bool,
+    /// if false, `é` matches `e` and the other way around
+    pub diacritics_sensitive: bool,
+    /// only match whole words
+    pub whole_word: bool,
+    /// the query is a regular expression
+    pub regex: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchMatch {
+    pub page: u32,
+    /// byte range in the `PageText::text` of the page
+    pub range: Range<usize>,
+    /// one quad per line of the match: baseline start, baseline end, top end, top start
+    pub quads: Vec<[Vector2F; 4]>,
+}
+
+pub struct Search {
+    regex: Regex,
+    options: SearchOptions,
+}
+impl Search {
+    /// Fails if `options.regex` is set and the query is not a valid regular expression.
+    pub fn new(query: &str, options: SearchOptions) -> Result<Search, regex::Error> {
+        let pattern = if options.regex {
+            fold(query, &options)
+        } else {
+            // any whitespace in the query matches any whitespace, including line breaks
+            query
+                .split_whitespace()
+                .map(|word| regex::escape(&fold(word, &options)))
+                .collect::<Vec<_>>()
+                .join(r"\s+")
+        };
+        let pattern = if options.whole_word {
+            format!(r"\b(?:{})\b", pattern)
+        } else {
+            pattern
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(!options.case_sensitive)
+            .build()?;
+        Ok(Search { regex, options })
+    }
+
+    /// All matches on one page, in reading order.
+    pub fn find_in_page(&self, page: u32, text: &PageText) -> Vec<SearchMatch> {
+        let folded = Folded::new(&text.text, &self.options);
+        let lines = line_of_chars(text);
+
+        self.regex
+            .find_iter(&folded.text)
+            .filter(|m| !m.is_empty())
+            .map(|m| {
+                let range = folded.original(m.range());
+                SearchMatch {
+                    page,
+                    quads: quads(text, &lines, range.clone()),
+                    range,
+                }
+            })
+            .collect()
+    }
+
+    /// All matches in `pages`, given as page number and text.
+    pub fn find<'a>(&self, pages: impl IntoIterator<Item = (u32, &'a PageText)>) -> Vec<SearchMatch> {
+        pages
+            .into_iter()
+            .flat_map(|(page, text)| self.find_in_page(page, text))
+            .collect()
+    }
+}
+
+/// Remove diacritics unless they matter.
+fn fold(s: &str, options: &SearchOptions) -> String {
+    if options.diacritics_sensitive {
+        s.into()
+    } else {
+        s.nfd().filter(|&c| !is_combining_mark(c)).collect()
+    }
+}
+
+/// Page text with diacritics removed, and the way back to the original offsets.
+struct Folded {
+    text: String,
+    // for each byte of `text`, the byte range of the original char
+    map: Vec<Range<usize>>,
+}
+impl Folded {
+    fn new(original: &str, options: &SearchOptions) -> Folded {
+        if options.diacritics_sensitive {
+            return Folded {
+                text: original.into(),
+                map: original.char_indices().flat_map(|(i, c)| std::iter::repeat(i..i + c.len_utf8()).take(c.len_utf8())).collect(),
+            };
+        }
+        let mut text = String::with_capacity(original.len());
+        let mut map = Vec::with_capacity(original.len());
+        let mut buf = [0; 4];
+        for (i, c) in original.char_indices() {
+            let source = i..i + c.len_utf8();
+            for d in std::iter::once(c).nfd().filter(|&d| !is_combining_mark(d)) {
+                text.push(d);
+                map.extend(std::iter::repeat(source.clone()).take(d.encode_utf8(&mut buf).len()));
+            }
+        }
+        Folded { text, map }
+    }
+    fn original(&self, range: Range<usize>) -> Range<usize> {
+        self.map[range.start].start..self.map[range.end - 1].end
+    }
+}
+
+/// The line index of every char of the page.
+fn line_of_chars(text: &PageText) -> Vec<usize> {
+    let mut lines = vec![0; text.chars.len()];
+    for (nr, line) in text.lines().enumerate() {
+        for word in &line.words {
+            for idx in word.chars.clone() {
+                lines[idx] = nr;
+            }
+        }
+    }
+    lines
+}
+
+/// One quad for each line touched by `range`.
+fn quads(text: &PageText, lines: &[usize], range: Range<usize>) -> Vec<[Vector2F; 4]> {
+    let first = text.chars.partition_point(|c| c.range.end <= range.start);
+    let mut quads: Vec<[Vector2F; 4]> = vec![];
+    let mut current_line = None;
+    for (idx, c) in text.chars.iter().enumerate().skip(first) {
+        if c.range.start >= range.end {
+            break;
+        }
+        match quads.last_mut() {
+            Some(quad) if current_line == Some(lines[idx]) => {
+                quad[1] = c.quad[1];
+                quad[2] = c.quad[2];
+            }
+            _ => {
+                quads.push(c.quad);
+                current_line = Some(lines[idx]);
+            }
+        }
+    }
+    quads
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::span;
+
+    fn page(lines: &[&str]) -> PageText {
+        let items: Vec<_> = lines.iter().enumerate().map(|(i, text)| span(text, 10., 30. + 12. * i as f32, 10.)).collect();
+        PageText::new(&items)
+    }
+    fn v(x: f32, y: f32) -> Vector2F {
+        Vector2F::new(x, y)
+    }
+    fn texts<'a>(text: &'a PageText, matches: &[SearchMatch]) -> Vec<&'a str> {
+        matches.iter().map(|m| &text.text[m.range.clone()]).collect()
+    }
+
+    #[test]
+    fn one_quad_per_line() {
+        let text = page(&["Hello world", "second line"]);
+        let search = Search::new("world  second", SearchOptions::default()).unwrap();
+        let matches = search.find_in_page(3, &text);
+        std::assert_eq!(texts(&text, &matches), ["world\nsecond"]);
+        std::assert_eq!(matches[0].page, 3);
+        std::assert_eq!(matches[0].quads, [
+            [v(40., 30.), v(65., 30.), v(65., 20.), v(40., 20.)],
+            [v(10., 42.), v(40., 42.), v(40., 32.), v(10., 32.)],
+        ]);
+    }
+
+    #[test]
+    fn options() {
+        let text = page(&["Café crème, CAFE"]);
+        let find = |query: &str, options: SearchOptions| {
+            let matches = Search::new(query, options).unwrap().find_in_page(0, &text);
+            texts(&text, &matches)
+        };
+        std::assert_eq!(find("cafe", SearchOptions::default()), ["Café", "CAFE"]);
+        std::assert_eq!(find("creme", SearchOptions::default()), ["crème"]);
+        std::assert_eq!(find("cafe", SearchOptions { diacritics_sensitive: true, ..SearchOptions::default() }), ["CAFE"]);
+        std::assert_eq!(find("Caf", SearchOptions { case_sensitive: true, ..SearchOptions::default() }), ["Caf"]);
+        assert!(find("caf", SearchOptions { whole_word: true, ..SearchOptions::default() }).is_empty());
+        std::assert_eq!(find(r"c\w+e\b", SearchOptions { regex: true, ..SearchOptions::default() }), ["Café", "crème", "CAFE"]);
+        assert!(Search::new("(", SearchOptions { regex: true, ..SearchOptions::default() }).is_err());
+    }
+
+    #[test]
+    fn pages() {
+        let pages = [page(&["one two"]), page(&["three"]), page(&["two two"])];
+        let search = Search::new("two", SearchOptions::default()).unwrap();
+        let found: Vec<u32> = search.find(pages.iter().enumerate().map(|(nr, text)| (nr as u32, text))).iter().map(|m| m.page).collect();
+        std::assert_eq!(found, [0, 2, 2]);
+    }
+}
@@ -0,0 +1,455 @@
+//! The structure tree of tagged PDFs, linked to traced content through MCIDs.
+//!
+//! ```ignore
+//! let tree = StructTree::from_catalog(&catalog, &resolver)?.expect("not tagged");
+//! for idx in tree.reading_order() {
+//!     let elem = &tree.elements[idx];
+//!     if elem.standard_role == "Figure" && elem.alt.is_none() {
+//!         println!("figure without alt text");
+//!     }
+//! }
+//! ```
+
+use std::collections::{HashMap, HashSet};
+
+use pdf::error::PdfError;
+use pdf::object::{PlainRef, Resolve};
+use pdf::primitive::{Dictionary, Primitive};
+
+use crate::tracer::DrawItem;
+
+/// Structure types defined by the PDF specification. Other roles are mapped to these through `/RoleMap`.
+pub const STANDARD_ROLES: &[&str] = &[
+    "Document", "Part", "Art", "Sect", "Div", "BlockQuote", "Caption", "TOC", "TOCI", "Index",
+    "NonStruct", "Private", "P", "H", "H1", "H2", "H3", "H4", "H5", "H6", "L", "LI", "Lbl", "LBody",
+    "Table", "TR", "TH", "TD", "THead", "TBody", "TFoot", "Span", "Quote", "Note", "Reference",
+    "BibEntry", "Code", "Link", "Annot", "Ruby", "RB", "RT", "RP", "Warichu", "WT", "WP", "Figure",
+    "Formula", "Form",
+];
+
+// nesting beyond this is treated as broken
+const MAX_DEPTH: usize = 256;
+
+#[derive(Debug, Clone)]
+pub enum StructContent {
+    /// marked content with the given MCID in the content stream of `page`,
+    /// or in the form XObject `stream` if set
+    MarkedContent {
+        page: Option<PlainRef>,
+        stream: Option<PlainRef>,
+        mcid: u32,
+    },
+    /// a whole object, e.g. an annotation
+    Object { page: Option<PlainRef>, object: PlainRef },
+}
+
+/// An entry of `/K`, in the order of the file.
+#[derive(Debug, Clone)]
+pub enum StructKid {
+    /// a child element, as index into `StructTree::elements`
+    Elem(usize),
+    Content(StructContent),
+}
+
+#[derive(Debug, Clone)]
+pub struct StructElem {
+    /// `/S` as written in the file
+    pub role: String,
+    /// `role` mapped through the role map; equals `role` if it can't be mapped to a standard type
+    pub standard_role: String,
+    pub alt: Option<String>,
+    pub actual_text: Option<String>,
+    /// `/Lang` of this element only, see `StructTree::lang` for the inherited one
+    pub lang: Option<String>,
+    pub title: Option<String>,
+    /// `/E`, the expansion of an abbreviation
+    pub expansion: Option<String>,
+    /// page of the content, inherited from the parent if not set
+    pub page: Option<PlainRef>,
+    pub parent: Option<usize>,
+    /// child elements and content, in logical order
+    pub kids: Vec<StructKid>,
+}
+impl StructElem {
+    /// Indices of the child elements.
+    pub fn children(&self) -> impl Iterator<Item = usize> + '_ {
+        self.kids.iter().filter_map(|k| match *k {
+            StructKid::Elem(idx) => Some(idx),
+            StructKid::Content(_) => None,
+        })
+    }
+    /// Content directly owned by this element.
+    pub fn content(&self) -> impl Iterator<Item = &StructContent> + '_ {
+        self.kids.iter().filter_map(|k| match *k {
+            StructKid::Elem(_) => None,
+            StructKid::Content(ref c) => Some(c),
+        })
+    }
+    /// 1 to 6 for `H1` to `H6`.
+    pub fn heading_level(&self) -> Option<u8> {
+        match self.standard_role.as_str() {
+            "H1" => Some(1),
+            "H2" => Some(2),
+            "H3" => Some(3),
+            "H4" => Some(4),
+            "H5" => Some(5),
+            "H6" => Some(6),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StructTree {
+    pub elements: Vec<StructElem>,
+    /// top level elements in logical order
+    pub roots: Vec<usize>,
+    pub role_map: HashMap<String, String>,
+}
+
+impl StructTree {
+    /// The structure tree of the document with the catalog `catalog`, if it has one.
+    pub fn from_catalog(catalog: &Dictionary, resolve: &impl Resolve) -> Result<Option<StructTree>, PdfError> {
+        match catalog.get("StructTreeRoot") {
+            Some(p) => {
+                let root = p.clone().resolve(resolve)?.into_dictionary()?;
+                StructTree::new(&root, resolve).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Read the tree below the `/StructTreeRoot` dictionary `root`.
+    pub fn new(root: &Dictionary, resolve: &impl Resolve) -> Result<StructTree, PdfError> {
+        let mut tree = StructTree::default();
+        if let Some(p) = root.get("RoleMap") {
+            let map = p.clone().resolve(resolve)?.into_dictionary()?;
+            for (key, val) in map.iter() {
+                if let Ok(name) = val.as_name() {
+                    tree.role_map.insert(key.as_str().into(), name.into());
+                }
+            }
+        }
+        let mut builder = Builder {
+            tree,
+            resolve,
+            visited: HashSet::new(),
+        };
+        if let Some(k) = root.get("K") {
+            for kid in kids(k, resolve)? {
+                if let Kid::Elem(dict, r) = kid {
+                    if let Some(idx) = builder.element(&dict, r, None, None, 0)? {
+                        builder.tree.roots.push(idx);
+                    }
+                }
+            }
+        }
+        Ok(builder.tree)
+    }
+
+    /// Map `role` through the role map until it is a standard type.
+    pub fn standard_role<'a>(&'a self, role: &'a str) -> &'a str {
+        let mut role = role;
+        for _ in 0..16 {
+            if STANDARD_ROLES.contains(&role) {
+                break;
+            }
+            match self.role_map.get(role) {
+                Some(mapped) => role = mapped,
+                None => break,
+            }
+        }
+        role
+    }
+
+    /// All elements in logical reading order, parents before their children.
+    pub fn reading_order(&self) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.elements.len());
+        let mut stack: Vec<usize> = self.roots.iter().rev().cloned().collect();
+        while let Some(idx) = stack.pop() {
+            order.push(idx);
+            let children: Vec<usize> = self.elements[idx].children().collect();
+            stack.extend(children.into_iter().rev());
+        }
+        order
+    }
+
+    /// Content of the element and all its descendants, in logical order.
+    pub fn content(&self, idx: usize) -> Vec<&StructContent> {
+        let mut out = vec![];
+        let mut stack: Vec<&StructKid> = self.elements[idx].kids.iter().rev().collect();
+        while let Some(kid) = stack.pop() {
+            match *kid {
+                StructKid::Elem(child) => stack.extend(self.elements[child].kids.iter().rev()),
+                StructKid::Content(ref c) => out.push(c),
+            }
+        }
+        out
+    }
+
+    /// `/Lang` of the element or its closest ancestor that has one.
+    pub fn lang(&self, idx: usize) -> Option<&str> {
+        let mut current = Some(idx);
+        while let Some(idx) = current {
+            let elem = &self.elements[idx];
+            if let Some(ref lang) = elem.lang {
+                return Some(lang);
+            }
+            current = elem.parent;
+        }
+        None
+    }
+
+    /// Text of the element: its `/ActualText` if set, otherwise the text of the spans
+    /// that belong to it. `pages` gives the traced items of each page by page reference.
+    pub fn text(&self, idx: usize, pages: &HashMap<PlainRef, &[DrawItem]>) -> String {
+        let mut out = String::new();
+        self.collect_text(idx, pages, &mut out, 0);
+        out
+    }
+    fn collect_text(&self, idx: usize, pages: &HashMap<PlainRef, &[DrawItem]>, out: &mut String, depth: usize) {
+        let elem = &self.elements[idx];
+        if let Some(ref text) = elem.actual_text {
+            out.push_str(text);
+            return;
+        }
+        if depth > MAX_DEPTH {
+            return;
+        }
+        for kid in &elem.kids {
+            match *kid {
+                StructKid::Elem(child) => self.collect_text(child, pages, out, depth + 1),
+                StructKid::Content(StructContent::MarkedContent { page: Some(page), stream, mcid }) => {
+                    if let Some(items) = pages.get(&page) {
+                        out.push_str(&marked_text(items, stream, mcid));
+                    }
+                }
+                StructKid::Content(_) => {}
+            }
+        }
+    }
+}
+
+/// Text of all spans in `items` with the given MCID in the content stream `stream`
+/// (a form xobject, or `None` for the page).
+pub fn marked_text(items: &[DrawItem], stream: Option<PlainRef>, mcid: u32) -> String {
+    let mut out = String::new();
+    for item in items {
+        if let DrawItem::Text(ref span, _) = *item {
+            if span.mcid == Some(mcid) && span.mcid_stream == stream {
+                out.push_str(&span.text);
+            }
+        }
+    }
+    out
+}
+
+enum Kid {
+    Elem(Dictionary, Option<PlainRef>),
+    Mcid(u32),
+    Mcr(Dictionary),
+    Objr(Dictionary),
+}
+
+/// The entries of a `/K` value.
+fn kids(k: &Primitive, resolve: &impl Resolve) -> Result<Vec<Kid>, PdfError> {
+    let items = match *k {
+        Primitive::Array(ref items) => items.clone(),
+        ref p => vec![p.clone()],
+    };
+    let mut out = Vec::with_capacity(items.len());
+    for p in items {
+        let r = match p {
+            Primitive::Reference(r) => Some(r),
+            _ => None,
+        };
+        match p.resolve(resolve)? {
+            Primitive::Integer(n) => {
+                if let Ok(mcid) = u32::try_from(n) {
+                    out.push(Kid::Mcid(mcid));
+                }
+            }
+            Primitive::Dictionary(dict) => {
+                let typ = dict.get("Type").and_then(|t| t.as_name().ok()).map(|s| s.to_owned());
+                match typ.as_deref() {
+                    Some("MCR") => out.push(Kid::Mcr(dict)),
+                    Some("OBJR") => out.push(Kid::Objr(dict)),
+                    _ => out.push(Kid::Elem(dict, r)),
+                }
+            }
+            Primitive::Null => {}
+            p => warn!("unexpected {} in structure tree", p.get_debug_name()),
+        }
+    }
+    Ok(out)
+}
+
+fn string(dict: &Dictionary, key: &str) -> Option<String> {
+    dict.get(key).and_then(|p| p.as_string().ok()).and_then(|s| s.to_string().ok())
+}
+
+fn reference(dict: &Dictionary, key: &str) -> Option<PlainRef> {
+    match dict.get(key) {
+        Some(&Primitive::Reference(r)) => Some(r),
+        _ => None,
+    }
+}
+
+struct Builder<'a, R: Resolve> {
+    tree: StructTree,
+    resolve: &'a R,
+    // guards against cycles
+    visited: HashSet<PlainRef>,
+}
+impl<'a, R: Resolve> Builder<'a, R> {
+    fn element(&mut self, dict: &Dictionary, r: Option<PlainRef>, parent: Option<usize>, parent_page: Option<PlainRef>, depth: usize) -> Result<Option<usize>, PdfError> {
+        if depth > MAX_DEPTH {
+            warn!("structure tree too deep");
+            return Ok(None);
+        }
+        if let Some(r) = r {
+            if !self.visited.insert(r) {
+                warn!("cycle in structure tree at {:?}", r);
+                return Ok(None);
+            }
+        }
+        let role = match dict.get("S").and_then(|s| s.as_name().ok()) {
+            Some(s) => s.to_owned(),
+            None => return Ok(None),
+        };
+        let page = reference(dict, "Pg").or(parent_page);
+        let standard_role = self.tree.standard_role(&role).to_owned();
+        let idx = self.tree.elements.len();
+        self.tree.elements.push(StructElem {
+            role,
+            standard_role,
+            alt: string(dict, "Alt"),
+            actual_text: string(dict, "ActualText"),
+            lang: string(dict, "Lang"),
+            title: string(dict, "T"),
+            expansion: string(dict, "E"),
+            page,
+            parent,
+            kids: vec![],
+        });
+
+        let k = match dict.get("K") {
+            Some(k) => k,
+            None => return Ok(Some(idx)),
+        };
+        for kid in kids(k, self.resolve)? {
+            match kid {
+                Kid::Elem(child, r) => {
+                    if let Some(child) = self.element(&child, r, Some(idx), page, depth + 1)? {
+                        self.tree.elements[idx].kids.push(StructKid::Elem(child));
+                    }
+                }
+                Kid::Mcid(mcid) => {
+                    self.tree.elements[idx].kids.push(StructKid::Content(StructContent::MarkedContent { page, stream: None, mcid }));
+                }
+                Kid::Mcr(mcr) => {
+                    let mcid = mcr.get("MCID").and_then(|p| p.as_integer().ok()).and_then(|n| u32::try_from(n).ok());
+                    if let Some(mcid) = mcid {
+                        self.tree.elements[idx].kids.push(StructKid::Content(StructContent::MarkedContent {
+                            page: reference(&mcr, "Pg").or(page),
+                            stream: reference(&mcr, "Stm"),
+                            mcid,
+                        }));
+                    }
+                }
+                Kid::Objr(objr) => {
+                    if let Some(object) = reference(&objr, "Obj") {
+                        self.tree.elements[idx].kids.push(StructKid::Content(StructContent::Object {
+                            page: reference(&objr, "Pg").or(page),
+                            object,
+                        }));
+                    }
+                }
+            }
+        }
+        Ok(Some(idx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pdf::object::NoResolve;
+
+    use crate::testutil::span;
+
+    fn dict(entries: Vec<(&str, Primitive)>) -> Dictionary {
+        let mut dict = Dictionary::new();
+        for (key, val) in entries {
+            dict.insert(key, val);
+        }
+        dict
+    }
+    fn name(s: &str) -> Primitive {
+        Primitive::Name(s.into())
+    }
+    fn marked(text: &str, stream: Option<PlainRef>, mcid: u32) -> DrawItem {
+        let mut item = span(text, 0., 10., 10.);
+        if let DrawItem::Text(ref mut span, _) = item {
+            span.mcid = Some(mcid);
+            span.mcid_stream = stream;
+        }
+        item
+    }
+
+    const PAGE: PlainRef = PlainRef { id: 3, gen: 0 };
+    const FORM: PlainRef = PlainRef { id: 7, gen: 0 };
+
+    fn tree() -> StructTree {
+        let heading = dict(vec![("S", name("Heading")), ("K", Primitive::Integer(0))]);
+        let mcr = dict(vec![
+            ("Type", name("MCR")),
+            ("MCID", Primitive::Integer(0)),
+            ("Stm", Primitive::Reference(FORM)),
+        ]);
+        let para = dict(vec![("S", name("P")), ("K", Primitive::Array(vec![Primitive::Integer(1), mcr.into()]))]);
+        let doc = dict(vec![
+            ("S", name("Document")),
+            ("Pg", Primitive::Reference(PAGE)),
+            ("K", Primitive::Array(vec![heading.into(), para.into()])),
+        ]);
+        let root = dict(vec![
+            ("RoleMap", dict(vec![("Heading", name("H1"))]).into()),
+            ("K", doc.into()),
+        ]);
+        StructTree::new(&root, &NoResolve).unwrap()
+    }
+
+    #[test]
+    fn roles_and_order() {
+        let tree = tree();
+        let roles: Vec<_> = tree.reading_order().into_iter().map(|idx| tree.elements[idx].role.as_str()).collect();
+        std::assert_eq!(roles, ["Document", "Heading", "P"]);
+        let heading = &tree.elements[1];
+        std::assert_eq!(heading.standard_role, "H1");
+        std::assert_eq!(heading.heading_level(), Some(1));
+        std::assert_eq!(heading.parent, Some(0));
+        // inherited from the parent
+        std::assert_eq!(heading.page, Some(PAGE));
+        std::assert_eq!(tree.elements[0].children().collect::<Vec<_>>(), [1, 2]);
+        std::assert_eq!(tree.lang(1), None);
+    }
+
+    #[test]
+    fn marked_content() {
+        let tree = tree();
+        let content: Vec<_> = tree.content(0).into_iter().map(|c| match *c {
+            StructContent::MarkedContent { page, stream, mcid } => (page, stream, mcid),
+            _ => panic!("unexpected {:?}", c),
+        }).collect();
+        std::assert_eq!(content, [(Some(PAGE), None, 0), (Some(PAGE), None, 1), (Some(PAGE), Some(FORM), 0)]);
+
+        // MCID 0 of the page and of the form are different content
+        let items = vec![marked("Title", None, 0), marked("Body ", None, 1), marked("form", Some(FORM), 0)];
+        std::assert_eq!(marked_text(&items, None, 0), "Title");
+        std::assert_eq!(marked_text(&items, Some(FORM), 0), "form");
+        let pages: HashMap<PlainRef, &[DrawItem]> = [(PAGE, &items[..])].into_iter().collect();
+        std::assert_eq!(tree.text(0, &pages), "TitleBody form");
+        std::assert_eq!(tree.text(2, &pages), "Body form");
+    }
+}
@@ -0,0 +1,452 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::io::Cursor;
+use std::sync::Arc;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::{ImageBuffer, ImageFormat, Rgba};
+use pathfinder_content::{
+    fill::FillRule,
+    outline::Outline,
+    stroke::{LineCap, LineJoin},
+};
+use pathfinder_geometry::{rect::RectF, transform2d::Transform2F};
+use pdf::font::Font as PdfFont;
+use pdf::object::{ImageXObject, MaybeRef, Ref, Resolve, Resources, XObject};
+use font::Glyph;
+
+use crate::backend::{BlendMode, FillMode, Stroke};
+use crate::dump::{path, PathCommand};
+use crate::fontprovider::strip_subset;
+use crate::image::load_image_with_limits;
+use crate::tracer::ClipPathId;
+use crate::{Backend, Cache, DrawMode, Fill, FontEntry, Limits, RenderError, TextSpan};
+
+/// Writes a page as SVG.
+///
+/// Unlike going through `SceneBackend`, clip paths stay `<clipPath>`s, images are embedded
+/// as PNG once per XObject, and each distinct glyph outline becomes a `<symbol>`.
+/// The text of every span is laid over the glyphs as transparent `<text>`, so it can be
+/// selected and searched.
+///
+/// Patterns and shadings are not supported yet: the backend gets no pattern space and can't
+/// resolve the pattern, so areas painted with a pattern are drawn in black, like
+/// `SceneBackend` does.
+pub struct SvgBackend<'a> {
+    cache: &'a Cache,
+    // prepended to all ids, so several pages can share one document
+    id_prefix: String,
+    view_box: RectF,
+    defs: String,
+    body: String,
+    clip_paths: usize,
+    // clip path of the open `<g>` in `body`
+    current_clip: Option<usize>,
+    // path data -> symbol number
+    glyphs: HashMap<String, usize>,
+    images: HashMap<(Ref<XObject>, BlendMode), Option<usize>>,
+}
+
+impl<'a> SvgBackend<'a> {
+    pub fn new(cache: &'a Cache) -> Self {
+        SvgBackend::with_id_prefix(cache, "")
+    }
+    /// Like `new`, but all ids in the output start with `prefix`.
+    pub fn with_id_prefix(cache: &'a Cache, prefix: &str) -> Self {
+        SvgBackend {
+            cache,
+            id_prefix: prefix.into(),
+            view_box: RectF::default(),
+            defs: String::new(),
+            body: String::new(),
+            clip_paths: 0,
+            current_clip: None,
+            glyphs: HashMap::new(),
+            images: HashMap::new(),
+        }
+    }
+    /// The SVG document.
+    pub fn finish(self) -> String {
+        let mut out = String::new();
+        out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str(&self.finish_element());
+        out
+    }
+    /// Only the `<svg>` element, to be embedded in another document.
+    pub fn finish_element(mut self) -> String {
+        self.set_clip(None);
+        let r = self.view_box;
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}" width="{}" height="{}">"#,
+            num(r.min_x()), num(r.min_y()), num(r.width()), num(r.height()), num(r.width()), num(r.height())
+        );
+        if !self.defs.is_empty() {
+            out.push_str("<defs>\n");
+            out.push_str(&self.defs);
+            out.push_str("</defs>\n");
+        }
+        out.push_str(&self.body);
+        out.push_str("</svg>\n");
+        out
+    }
+    /// Put the following elements in a group with the given clip path.
+    fn set_clip(&mut self, clip: Option<ClipPathId>) {
+        let clip = clip.map(|id| id.0);
+        if clip == self.current_clip {
+            return;
+        }
+        if self.current_clip.is_some() {
+            self.body.push_str("</g>\n");
+        }
+        if let Some(id) = clip {
+            let _ = writeln!(self.body, r#"<g clip-path="url(#{}c{id})">"#, self.id_prefix);
+        }
+        self.current_clip = clip;
+    }
+    fn image(&mut self, xref: Ref<XObject>, im: &ImageXObject, resources: &Resources, mode: BlendMode, resolve: &impl Resolve) -> Option<usize> {
+        if let Some(&idx) = self.images.get(&(xref, mode)) {
+            return idx;
+        }
+        let idx = match *self.cache.get_image(xref, im, resources, resolve, mode).0 {
+            Ok(ref image) => {
+                let size = image.size();
+                let rgba: Vec<u8> = image.pixels().iter().flat_map(|c| [c.r, c.g, c.b, c.a]).collect();
+                png_data_uri(size.x() as u32, size.y() as u32, &rgba).map(|uri| {
+                    let idx = self.images.len();
+                    let _ = writeln!(self.defs, r#"<image id="{}i{idx}" width="1" height="1" preserveAspectRatio="none" href="{uri}"/>"#, self.id_prefix);
+                    idx
+                })
+            }
+            Err(ref e) => {
+                warn!("can't load image {:?}: {:?}", xref, e);
+                None
+            }
+        };
+        self.images.insert((xref, mode), idx);
+        idx
+    }
+}
+
+impl<'a> Backend for SvgBackend<'a> {
+    type ClipPathId = ClipPathId;
+
+    fn create_clip_path(&mut self, path: Outline, fill_rule: FillRule, parent: Option<ClipPathId>) -> ClipPathId {
+        let id = self.clip_paths;
+        self.clip_paths += 1;
+        let _ = write!(self.defs, r#"<clipPath id="{}c{id}" clipPathUnits="userSpaceOnUse""#, self.id_prefix);
+        if let Some(parent) = parent {
+            let _ = write!(self.defs, r#" clip-path="url(#{}c{})""#, self.id_prefix, parent.0);
+        }
+        let _ = write!(self.defs, r#"><path d="{}""#, path_data(&path));
+        if fill_rule == FillRule::EvenOdd {
+            self.defs.push_str(r#" clip-rule="evenodd""#);
+        }
+        self.defs.push_str("/></clipPath>\n");
+        ClipPathId(id)
+    }
+    fn set_view_box(&mut self, view_box: RectF) {
+        self.view_box = view_box;
+        let _ = writeln!(
+            self.body,
+            r##"<rect x="{}" y="{}" width="{}" height="{}" fill="#ffffff"/>"##,
+            num(view_box.min_x()), num(view_box.min_y()), num(view_box.width()), num(view_box.height())
+        );
+    }
+    fn draw(&mut self, outline: &Outline, mode: &DrawMode, fill_rule: FillRule, transform: Transform2F, clip: Option<ClipPathId>) {
+        let d = path_data(outline);
+        if d.is_empty() {
+            return;
+        }
+        self.set_clip(clip);
+        let _ = write!(self.body, r#"<path d="{d}" transform="{}""#, matrix(transform));
+        paint_attributes(&mut self.body, mode);
+        if fill_rule == FillRule::EvenOdd {
+            self.body.push_str(r#" fill-rule="evenodd""#);
+        }
+        self.body.push_str("/>\n");
+    }
+    fn draw_glyph(&mut self, glyph: &Glyph, mode: &DrawMode, transform: Transform2F, clip: Option<ClipPathId>) {
+        let d = path_data(&glyph.path);
+        if d.is_empty() {
+            return;
+        }
+        let next = self.glyphs.len();
+        let idx = match self.glyphs.get(&d) {
+            Some(&idx) => idx,
+            None => {
+                let _ = writeln!(self.defs, r#"<symbol id="{}g{next}" overflow="visible"><path d="{d}"/></symbol>"#, self.id_prefix);
+                self.glyphs.insert(d, next);
+                next
+            }
+        };
+        self.set_clip(clip);
+        let _ = write!(self.body, r##"<use href="#{}g{idx}" transform="{}""##, self.id_prefix, matrix(transform));
+        paint_attributes(&mut self.body, mode);
+        self.body.push_str("/>\n");
+    }
+    fn draw_image(&mut self, xref: Ref<XObject>, im: &ImageXObject, resources: &Resources, transform: Transform2F, mode: BlendMode, clip: Option<ClipPathId>, resolve: &impl Resolve) {
+        if let Some(idx) = self.image(xref, im, resources, mode, resolve) {
+            self.set_clip(clip);
+            let _ = write!(self.body, r##"<use href="#{}i{idx}" transform="{}""##, self.id_prefix, matrix(transform * image_flip()));
+            blend_attribute(&mut self.body, mode);
+            self.body.push_str("/>\n");
+        }
+    }
+    fn draw_inline_image(&mut self, im: &Arc<ImageXObject>, resources: &Resources, transform: Transform2F, mode: BlendMode, clip: Option<ClipPathId>, resolve: &impl Resolve) {
+        // RenderState checked the size against the limits of the render
+        let data = match load_image_with_limits(im, resources, resolve, mode, &Limits::unlimited()) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("can't load inline image: {:?}", e);
+                return;
+            }
+        };
+        if let Some(uri) = png_data_uri(data.width(), data.height(), data.rgba_data()) {
+            self.set_clip(clip);
+            let _ = write!(
+                self.body,
+                r#"<image width="1" height="1" preserveAspectRatio="none" transform="{}" href="{uri}""#,
+                matrix(transform * image_flip())
+            );
+            blend_attribute(&mut self.body, mode);
+            self.body.push_str("/>\n");
+        }
+    }
+    fn get_font(&mut self, font_ref: &MaybeRef<PdfFont>, resolve: &impl Resolve, limits: &Limits) -> Result<Option<Arc<FontEntry>>, RenderError> {
+        self.cache.get_font(font_ref, resolve, limits)
+    }
+    fn add_text(&mut self, span: TextSpan, clip: Option<ClipPathId>) {
+        let mut content = String::new();
+        let mut xs = String::new();
+        for part in span.parts() {
+            let n = part.text.chars().count().max(1) as f32;
+            for (i, c) in part.text.chars().enumerate() {
+                if c.is_control() {
+                    continue;
+                }
+                match c {
+                    '<' => content.push_str("&lt;"),
+                    '>' => content.push_str("&gt;"),
+                    '&' => content.push_str("&amp;"),
+                    c => content.push(c),
+                }
+                if !xs.is_empty() {
+                    xs.push(' ');
+                }
+                xs.push_str(&num(part.pos + part.width * i as f32 / n));
+            }
+        }
+        if content.trim().is_empty() {
+            return;
+        }
+        self.set_clip(clip);
+        let _ = write!(
+            self.body,
+            r#"<text transform="{}" x="{xs}" y="0" font-size="{}" fill-opacity="0" xml:space="preserve""#,
+            matrix(span.transform),
+            num(span.font_size)
+        );
+        if let Some(ref e) = span.font {
+            let family = e.font.name().family.clone().unwrap_or_else(|| strip_subset(&e.name).into());
+            let _ = write!(self.body, r#" font-family="{}""#, family.replace(['"', '&', '<'], ""));
+        }
+        let _ = writeln!(self.body, ">{content}</text>");
+    }
+}
+
+/// Maps the unit square of an image, top row first, to PDF image space.
+fn image_flip() -> Transform2F {
+    Transform2F::row_major(1.0, 0.0, 0.0, 0.0, -1.0, 1.0)
+}
+
+fn png_data_uri(width: u32, height: u32, rgba: &[u8]) -> Option<String> {
+    let buffer = ImageBuffer::<Rgba<u8>, &[u8]>::from_raw(width, height, rgba)?;
+    let mut png = Vec::new();
+    if let Err(e) = buffer.write_to(&mut Cursor::new(&mut png), ImageFormat::Png) {
+        warn!("can't encode image: {:?}", e);
+        return None;
+    }
+    Some(format!("data:image/png;base64,{}", STANDARD.encode(&png)))
+}
+
+fn color(fill: &FillMode) -> String {
+    match fill.color {
+        Fill::Solid(r, g, b) => {
+            let c = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+            format!("#{:02x}{:02x}{:02x}", c(r), c(g), c(b))
+        }
+        // visible, and the same as the rasterized page
+        Fill::Pattern(_) => "#000000".into(),
+    }
+}
+
+fn blend_attribute(out: &mut String, mode: BlendMode) {
+    if mode == BlendMode::Darken {
+        out.push_str(r#" style="mix-blend-mode:multiply""#);
+    }
+}
+
+fn paint_attributes(out: &mut String, mode: &DrawMode) {
+    let (fill, stroke) = match mode {
+        DrawMode::Fill { fill } => (Some(fill), None),
+        DrawMode::Stroke { stroke, stroke_mode } => (None, Some((stroke, stroke_mode))),
+        DrawMode::FillStroke { fill, stroke, stroke_mode } => (Some(fill), Some((stroke, stroke_mode))),
+    };
+    match fill {
+        Some(fill) => {
+            let _ = write!(out, r#" fill="{}""#, color(fill));
+            if fill.alpha < 1.0 {
+                let _ = write!(out, r#" fill-opacity="{}""#, num(fill.alpha));
+            }
+        }
+        None => out.push_str(r#" fill="none""#),
+    }
+    if let Some((paint, stroke)) = stroke {
+        stroke_attributes(out, paint, stroke);
+    }
+    let darken = fill.map(|f| f.mode == BlendMode::Darken).unwrap_or(false)
+        || stroke.map(|(s, _)| s.mode == BlendMode::Darken).unwrap_or(false);
+    if darken {
+        blend_attribute(out, BlendMode::Darken);
+    }
+}
+
+fn stroke_attributes(out: &mut String, paint: &FillMode, stroke: &Stroke) {
+    let style = &stroke.style;
+    let _ = write!(out, r#" stroke="{}" stroke-width="{}""#, color(paint), num(style.line_width));
+    if paint.alpha < 1.0 {
+        let _ = write!(out, r#" stroke-opacity="{}""#, num(paint.alpha));
+    }
+    match style.line_cap {
+        LineCap::Butt => {}
+        LineCap::Square => out.push_str(r#" stroke-linecap="square""#),
+        LineCap::Round => out.push_str(r#" stroke-linecap="round""#),
+    }
+    match style.line_join {
+        LineJoin::Miter(limit) => {
+            let _ = write!(out, r#" stroke-miterlimit="{}""#, num(limit));
+        }
+        LineJoin::Bevel => out.push_str(r#" stroke-linejoin="bevel""#),
+        LineJoin::Round => out.push_str(r#" stroke-linejoin="round""#),
+    }
+    if let Some((ref pattern, phase)) = stroke.dash_pattern {
+        if !pattern.is_empty() {
+            let dashes: Vec<String> = pattern.iter().map(|&d| num(d)).collect();
+            let _ = write!(out, r#" stroke-dasharray="{}""#, dashes.join(" "));
+            if phase != 0.0 {
+                let _ = write!(out, r#" stroke-dashoffset="{}""#, num(phase));
+            }
+        }
+    }
+}
+
+/// `transform` as an SVG `matrix(…)`.
+/// Uses the full precision, as glyph transforms have tiny scale factors.
+fn matrix(t: Transform2F) -> String {
+    format!("matrix({} {} {} {} {} {})", t.m11(), t.m21(), t.m12(), t.m22(), t.m13(), t.m23())
+}
+
+/// At most three decimals, without trailing zeros.
+pub(crate) fn num(v: f32) -> String {
+    let s = format!("{:.3}", v);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    match s {
+        "-0" | "" => "0".into(),
+        s => s.into(),
+    }
+}
+
+fn point(out: &mut String, x: f32, y: f32) {
+    let _ = write!(out, "{} {}", num(x), num(y));
+}
+
+/// SVG path data of `outline`.
+fn path_data(outline: &Outline) -> String {
+    let mut d = String::new();
+    for command in path(outline) {
+        if !d.is_empty() {
+            d.push(' ');
+        }
+        match command {
+            PathCommand::MoveTo { x, y } => {
+                d.push('M');
+                point(&mut d, x, y);
+            }
+            PathCommand::LineTo { x, y } => {
+                d.push('L');
+                point(&mut d, x, y);
+            }
+            PathCommand::QuadTo { x1, y1, x, y } => {
+                d.push('Q');
+                point(&mut d, x1, y1);
+                d.push(' ');
+                point(&mut d, x, y);
+            }
+            PathCommand::CubicTo { x1, y1, x2, y2, x, y } => {
+                d.push('C');
+                point(&mut d, x1, y1);
+                d.push(' ');
+                point(&mut d, x2, y2);
+                d.push(' ');
+                point(&mut d, x, y);
+            }
+            PathCommand::Close => d.push('Z'),
+        }
+    }
+    d
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pathfinder_geometry::vector::Vector2F;
+    use pdf::object::Ref;
+
+    use crate::testutil::span;
+    use crate::tracer::DrawItem;
+    use crate::StaticFontProvider;
+
+    fn fill(color: Fill) -> DrawMode {
+        DrawMode::Fill { fill: FillMode { color, alpha: 1.0, mode: BlendMode::Overlay } }
+    }
+
+    #[test]
+    fn page() {
+        let cache = Cache::with_font_provider(Arc::new(StaticFontProvider::new(&[])));
+        let mut svg = SvgBackend::with_id_prefix(&cache, "p1");
+        let square = Outline::from_rect(RectF::new(Vector2F::zero(), Vector2F::new(10., 10.)));
+        svg.set_view_box(RectF::new(Vector2F::zero(), Vector2F::new(200., 100.)));
+        let clip = svg.create_clip_path(square.clone(), FillRule::EvenOdd, None);
+        svg.draw(&square, &fill(Fill::Solid(1., 0., 0.)), FillRule::Winding, Transform2F::default(), Some(clip));
+        svg.draw(&square, &fill(Fill::Pattern(Ref::from_id(5))), FillRule::EvenOdd, Transform2F::default(), None);
+        let text = match span("a<b", 10., 30., 10.) {
+            DrawItem::Text(span, _) => span,
+            _ => unreachable!(),
+        };
+        svg.add_text(text, None);
+        let out = svg.finish();
+
+        assert!(out.starts_with("<?xml"));
+        assert!(out.contains(r#"viewBox="0 0 200 100" width="200" height="100""#));
+        assert!(out.contains(r#"<clipPath id="p1c0" clipPathUnits="userSpaceOnUse"><path d="M0 0 "#));
+        assert!(out.contains(r#" clip-rule="evenodd"/></clipPath>"#));
+        assert!(out.contains(r#"<g clip-path="url(#p1c0)">"#));
+        assert!(out.contains(r##"transform="matrix(1 0 0 1 0 0)" fill="#ff0000"/>"##));
+        // patterns are approximated, not left out
+        assert!(out.contains(r##" fill="#000000" fill-rule="evenodd"/>"##));
+        assert!(out.contains(r#"<text transform="matrix(1 0 0 1 10 30)" x="0 5 10" y="0" font-size="10" fill-opacity="0" xml:space="preserve">a&lt;b</text>"#));
+        std::assert_eq!(out.matches("<g ").count(), out.matches("</g>").count());
+    }
+
+    #[test]
+    fn numbers() {
+        std::assert_eq!(num(1.0), "1");
+        std::assert_eq!(num(0.1234), "0.123");
+        std::assert_eq!(num(2.5), "2.5");
+        std::assert_eq!(num(-0.0001), "0");
+        std::assert_eq!(matrix(Transform2F::row_major(1., 2., 3., 4., 5., 6.)), "matrix(1 4 2 5 3 6)");
+    }
+}
@@ -0,0 +1,59 @@
+//! Traced items and small documents for the unit tests.
+
+use std::sync::Arc;
+
+use pathfinder_geometry::{rect::RectF, transform2d::Transform2F, vector::Vector2F};
+use pdf::content::TextMode;
+use pdf::file::FileOptions;
+
+use crate::tracer::{DrawItem, TraceCache, Tracer};
+use crate::{render_page, Fill, FontProvider, StaticFontProvider, TextChar, TextSpan};
+
+#[allow(dead_code)]
+#[path = "../tests/reference/builder.rs"]
+mod builder;
+pub use builder::PdfBuilder;
+
+/// A span of `text` with the start of the baseline at `(x, y)` of the output.
+/// Every char is half of `size` wide.
+pub fn span(text: &str, x: f32, y: f32, size: f32) -> DrawItem {
+    let chars: Vec<TextChar> = text
+        .char_indices()
+        .enumerate()
+        .map(|(i, (offset, _))| TextChar { offset, pos: i as f32 * 0.5 * size, width: 0.5 * size })
+        .collect();
+    let width = chars.len() as f32 * 0.5 * size;
+    DrawItem::Text(TextSpan {
+        rect: RectF::new(Vector2F::new(x, y - size), Vector2F::new(width, size)),
+        width,
+        bbox: None,
+        font_size: size,
+        font: None,
+        text: text.into(),
+        chars,
+        color: Fill::black(),
+        alpha: 1.0,
+        transform: Transform2F::from_translation(Vector2F::new(x, y)),
+        mode: TextMode::Fill,
+        op_nr: 0,
+        mcid: None,
+        mcid_stream: None,
+    }, None)
+}
+
+/// Trace the first page of `pdf`. Fonts that are not embedded are not available.
+pub fn trace(pdf: Vec<u8>) -> Vec<DrawItem> {
+    trace_with_fonts(pdf, Arc::new(StaticFontProvider::new(&[])))
+}
+
+/// Trace the first page of `pdf`, with `fonts` for the fonts that are not embedded.
+pub fn trace_with_fonts(pdf: Vec<u8>, fonts: Arc<dyn FontProvider>) -> Vec<DrawItem> {
+    let file = FileOptions::cached().load(pdf).expect("invalid PDF");
+    let resolver = file.resolver();
+    let page = file.get_page(0).expect("no page");
+    let cache = TraceCache::with_font_provider(fonts);
+    let mut clip_paths = vec![];
+    let mut backend = Tracer::new(&cache, &mut clip_paths);
+    render_page(&mut backend, &resolver, &page, Transform2F::default()).expect("can't trace page");
+    backend.finish()
+}
@@ -0,0 +1,509 @@
+//! Groups the text of a traced page into words, lines, blocks and columns.
+//!
+//! ```ignore
+//! let items = tracer.finish();
+//! let text = PageText::new(&items);
+//! println!("{}", text.text());
+//! ```
+
+use std::ops::Range;
+
+use pathfinder_geometry::{rect::RectF, vector::Vector2F};
+
+use crate::tracer::DrawItem;
+
+#[derive(Debug, Clone, Copy)]
+pub struct LayoutOptions {
+    /// gaps wider than this start a new word, in units of the font size
+    pub word_gap: f32,
+    /// lines whose baselines are closer than this can be in the same block, in units of the font size
+    pub line_gap: f32,
+    /// gaps wider than this split a line into two, in units of the font size
+    pub column_gap: f32,
+}
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        LayoutOptions {
+            word_gap: 0.15,
+            line_gap: 1.6,
+            column_gap: 1.5,
+        }
+    }
+}
+
+/// One glyph of a `TextSpan`, as given by `TextSpan::parts`.
+#[derive(Debug, Clone)]
+pub struct LayoutChar {
+    /// byte range in `PageText::text`
+    pub range: Range<usize>,
+    /// baseline start, baseline end, top end, top start
+    pub quad: [Vector2F; 4],
+    /// bounding box of `quad`
+    pub rect: RectF,
+    /// index of the `DrawItem::Text` in the traced items
+    pub item: usize,
+    /// byte offset of the glyph in the text of the span
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Word {
+    /// indices into `PageText::chars`
+    pub chars: Range<usize>,
+    /// byte range in `PageText::text`
+    pub range: Range<usize>,
+    pub rect: RectF,
+}
+
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub words: Vec<Word>,
+    pub range: Range<usize>,
+    pub rect: RectF,
+    /// unit vector along the baseline, in the output space of the trace
+    pub direction: Vector2F,
+}
+
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub lines: Vec<Line>,
+    pub range: Range<usize>,
+    pub rect: RectF,
+}
+
+#[derive(Debug, Clone)]
+pub struct Column {
+    pub blocks: Vec<Block>,
+    pub range: Range<usize>,
+    pub rect: RectF,
+}
+
+/// The text of a page in reading order.
+///
+/// Words are separated by a space, lines by a newline and blocks by an empty line.
+#[derive(Debug, Clone, Default)]
+pub struct PageText {
+    pub text: String,
+    pub chars: Vec<LayoutChar>,
+    pub columns: Vec<Column>,
+}
+impl PageText {
+    pub fn new(items: &[DrawItem]) -> Self {
+        PageText::with_options(items, &LayoutOptions::default())
+    }
+    pub fn with_options(items: &[DrawItem], options: &LayoutOptions) -> Self {
+        let glyphs = collect_glyphs(items);
+
+        let mut groups: Vec<Group> = vec![];
+        for (idx, g) in glyphs.iter().enumerate() {
+            match groups.iter_mut().find(|group| group.key == g.key) {
+                Some(group) => group.glyphs.push(idx),
+                None => groups.push(Group {
+                    key: g.key,
+                    dir: g.dir,
+                    down: g.down,
+                    glyphs: vec![idx],
+                }),
+            }
+        }
+        // upright text first
+        groups.sort_by_key(|group| (group.key.0 != 0, group.key));
+
+        let mut page = PageText::default();
+        for group in &groups {
+            let projected = group.glyphs.iter().map(|&idx| group.project(&glyphs[idx], idx)).collect();
+            let lines = build_lines(projected, options);
+            let blocks = build_blocks(lines, options);
+            for column in build_columns(blocks) {
+                page.push_column(&column, &glyphs, group.dir);
+            }
+        }
+        page
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    pub fn blocks(&self) -> impl Iterator<Item = &Block> + '_ {
+        self.columns.iter().flat_map(|c| c.blocks.iter())
+    }
+    pub fn lines(&self) -> impl Iterator<Item = &Line> + '_ {
+        self.blocks().flat_map(|b| b.lines.iter())
+    }
+    pub fn words(&self) -> impl Iterator<Item = &Word> + '_ {
+        self.lines().flat_map(|l| l.words.iter())
+    }
+    /// The characters of `word`.
+    pub fn word_chars(&self, word: &Word) -> &[LayoutChar] {
+        &self.chars[word.chars.clone()]
+    }
+
+    fn push_column(&mut self, column: &[BlockBuild], glyphs: &[Glyph], direction: Vector2F) {
+        if !self.text.is_empty() {
+            self.text.push_str("\n\n");
+        }
+        let column_start = self.text.len();
+        let mut blocks = Vec::with_capacity(column.len());
+        for (i, block) in column.iter().enumerate() {
+            if i > 0 {
+                self.text.push_str("\n\n");
+            }
+            let block_start = self.text.len();
+            let mut lines = Vec::with_capacity(block.lines.len());
+            for (j, line) in block.lines.iter().enumerate() {
+                if j > 0 {
+                    self.text.push('\n');
+                }
+                let line_start = self.text.len();
+                let mut words = Vec::with_capacity(line.words.len());
+                for (k, word) in line.words.iter().enumerate() {
+                    if k > 0 {
+                        self.text.push(' ');
+                    }
+                    let word_start = self.text.len();
+                    let first_char = self.chars.len();
+                    for &idx in word {
+                        let g = &glyphs[idx];
+                        let start = self.text.len();
+                        self.text.push_str(g.text);
+                        self.chars.push(LayoutChar {
+                            range: start..self.text.len(),
+                            quad: g.quad,
+                            rect: g.rect,
+                            item: g.item,
+                            offset: g.offset,
+                        });
+                    }
+                    words.push(Word {
+                        rect: union(self.chars[first_char..].iter().map(|c| c.rect)),
+                        chars: first_char..self.chars.len(),
+                        range: word_start..self.text.len(),
+                    });
+                }
+                lines.push(Line {
+                    rect: union(words.iter().map(|w| w.rect)),
+                    words,
+                    range: line_start..self.text.len(),
+                    direction,
+                });
+            }
+            blocks.push(Block {
+                rect: union(lines.iter().map(|l| l.rect)),
+                lines,
+                range: block_start..self.text.len(),
+            });
+        }
+        self.columns.push(Column {
+            rect: union(blocks.iter().map(|b| b.rect)),
+            blocks,
+            range: column_start..self.text.len(),
+        });
+    }
+}
+
+fn union(rects: impl Iterator<Item = RectF>) -> RectF {
+    rects.reduce(|a, b| a.union_rect(b)).unwrap_or_default()
+}
+
+struct Glyph<'a> {
+    text: &'a str,
+    item: usize,
+    offset: usize,
+    quad: [Vector2F; 4],
+    rect: RectF,
+    // (angle in degrees, mirrored)
+    key: (i32, bool),
+    dir: Vector2F,
+    down: Vector2F,
+    size: f32,
+    space: bool,
+}
+
+fn collect_glyphs(items: &[DrawItem]) -> Vec<Glyph> {
+    let mut glyphs = vec![];
+    for (item, draw) in items.iter().enumerate() {
+        let span = match draw {
+            DrawItem::Text(span, _) => span,
+            _ => continue,
+        };
+        let tr = span.transform;
+        // the transform flips y, so up in text space is -y
+        let advance = tr.matrix * Vector2F::new(1.0, 0.0);
+        let up = tr.matrix * Vector2F::new(0.0, -span.font_size);
+        if advance.length() == 0.0 || up.length() == 0.0 {
+            continue;
+        }
+        let dir = advance.normalize();
+        let down = -up.normalize();
+        let angle = (dir.y().atan2(dir.x()).to_degrees().round() as i32).rem_euclid(360);
+        let mirrored = dir.x() * down.y() - dir.y() * down.x() < 0.0;
+
+        for part in span.parts() {
+            if part.text.is_empty() {
+                continue;
+            }
+            let p0 = tr * Vector2F::new(part.pos, 0.0);
+            let p1 = tr * Vector2F::new(part.pos + part.width, 0.0);
+            let quad = [p0, p1, p1 + up, p0 + up];
+            let rect = RectF::from_points(
+                quad.iter().fold(quad[0], |a, &b| a.min(b)),
+                quad.iter().fold(quad[0], |a, &b| a.max(b)),
+            );
+            glyphs.push(Glyph {
+                text: part.text,
+                item,
+                offset: part.offset,
+                quad,
+                rect,
+                key: (angle, mirrored),
+                dir,
+                down,
+                size: up.length(),
+                space: part.text.chars().all(char::is_whitespace),
+            });
+        }
+    }
+    glyphs
+}
+
+/// Glyphs with the same orientation.
+struct Group {
+    key: (i32, bool),
+    dir: Vector2F,
+    down: Vector2F,
+    glyphs: Vec<usize>,
+}
+impl Group {
+    fn project(&self, g: &Glyph, idx: usize) -> Projected {
+        Projected {
+            idx,
+            u0: g.quad[0].dot(self.dir),
+            u1: g.quad[1].dot(self.dir),
+            v: g.quad[0].dot(self.down),
+            size: g.size,
+            space: g.space,
+        }
+    }
+}
+
+/// A glyph in the coordinates of its group: u along the baseline, v down the page.
+#[derive(Clone, Copy)]
+struct Projected {
+    idx: usize,
+    u0: f32,
+    u1: f32,
+    v: f32,
+    size: f32,
+    space: bool,
+}
+
+struct LineBuild {
+    // glyph indices per word
+    words: Vec<Vec<usize>>,
+    u0: f32,
+    u1: f32,
+    v: f32,
+    size: f32,
+}
+
+struct BlockBuild {
+    lines: Vec<LineBuild>,
+    u0: f32,
+    u1: f32,
+}
+
+fn build_lines(mut glyphs: Vec<Projected>, options: &LayoutOptions) -> Vec<LineBuild> {
+    glyphs.sort_by(|a, b| a.v.total_cmp(&b.v));
+
+    // glyphs on the same baseline, allowing for sub- and superscripts
+    let mut rows: Vec<Vec<Projected>> = vec![];
+    for g in glyphs {
+        match rows.last_mut() {
+            Some(row) if (g.v - row[0].v).abs() < 0.5 * g.size.min(row[0].size) => row.push(g),
+            _ => rows.push(vec![g]),
+        }
+    }
+
+    let mut lines = vec![];
+    for mut row in rows {
+        row.sort_by(|a, b| a.u0.total_cmp(&b.u0));
+        let mut line: Option<LineBuild> = None;
+        let mut word: Vec<usize> = vec![];
+        for g in row {
+            let mut split = false;
+            if let Some(ref mut l) = line {
+                let gap = g.u0 - l.u1;
+                let size = g.size.max(l.size);
+                if gap > options.word_gap * size && !word.is_empty() {
+                    l.words.push(std::mem::take(&mut word));
+                }
+                split = gap > options.column_gap * size;
+            }
+            if split {
+                lines.extend(line.take().filter(|l| !l.words.is_empty()));
+            }
+            let l = line.get_or_insert_with(|| LineBuild {
+                words: vec![],
+                u0: g.u0,
+                u1: g.u1,
+                v: g.v,
+                size: g.size,
+            });
+            l.u1 = l.u1.max(g.u1);
+            if g.space {
+                if !word.is_empty() {
+                    l.words.push(std::mem::take(&mut word));
+                }
+            } else {
+                l.size = l.size.max(g.size);
+                word.push(g.idx);
+            }
+        }
+        if let Some(mut l) = line {
+            if !word.is_empty() {
+                l.words.push(word);
+            }
+            if !l.words.is_empty() {
+                lines.push(l);
+            }
+        }
+    }
+    lines
+}
+
+fn build_blocks(lines: Vec<LineBuild>, options: &LayoutOptions) -> Vec<BlockBuild> {
+    let mut blocks: Vec<BlockBuild> = vec![];
+    for line in lines {
+        let fits = |b: &BlockBuild| {
+            let last = b.lines.last().unwrap();
+            let dv = line.v - last.v;
+            let ratio = line.size / last.size;
+            dv > 0.0
+                && dv <= options.line_gap * line.size.max(last.size)
+                && (0.5..=2.0).contains(&ratio)
+                && line.u0 < b.u1
+                && line.u1 > b.u0
+        };
+        match blocks.iter_mut().rev().find(|b| fits(b)) {
+            Some(b) => {
+                b.u0 = b.u0.min(line.u0);
+                b.u1 = b.u1.max(line.u1);
+                b.lines.push(line);
+            }
+            None => blocks.push(BlockBuild {
+                u0: line.u0,
+                u1: line.u1,
+                lines: vec![line],
+            }),
+        }
+    }
+    blocks
+}
+
+fn v_range(b: &BlockBuild) -> (f32, f32) {
+    let first = &b.lines[0];
+    let last = b.lines.last().unwrap();
+    (first.v - first.size, last.v)
+}
+
+/// Split the blocks into columns in reading order.
+///
+/// The blocks are cut into horizontal bands first. Bands that split into several columns
+/// are read column by column; consecutive bands with a single column are joined.
+fn build_columns(blocks: Vec<BlockBuild>) -> Vec<Vec<BlockBuild>> {
+    let mut columns: Vec<Vec<BlockBuild>> = vec![];
+    let mut open = false;
+    for band in cut(blocks, |b| v_range(b)) {
+        let mut parts = cut(band, |b| (b.u0, b.u1));
+        if parts.len() == 1 {
+            let mut part = parts.pop().unwrap();
+            part.sort_by(|a, b| a.lines[0].v.total_cmp(&b.lines[0].v));
+            match columns.last_mut() {
+                Some(column) if open => column.extend(part),
+                _ => columns.push(part),
+            }
+            open = true;
+        } else {
+            for part in parts {
+                columns.extend(build_columns(part));
+            }
+            open = false;
+        }
+    }
+    columns
+}
+
+/// Split at gaps in the projection given by `range`, in increasing order.
+fn cut(mut blocks: Vec<BlockBuild>, range: impl Fn(&BlockBuild) -> (f32, f32)) -> Vec<Vec<BlockBuild>> {
+    blocks.sort_by(|a, b| range(a).0.total_cmp(&range(b).0));
+    let mut parts: Vec<Vec<BlockBuild>> = vec![];
+    let mut end = f32::NEG_INFINITY;
+    for b in blocks {
+        let (start, stop) = range(&b);
+        match parts.last_mut() {
+            Some(part) if start < end => part.push(b),
+            _ => parts.push(vec![b]),
+        }
+        end = end.max(stop);
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::span;
+
+    #[test]
+    fn words_lines_and_columns() {
+        let items = [
+            span("right one", 150., 30., 10.),
+            span("right two", 150., 42., 10.),
+            span("second line", 10., 42., 10.),
+            span("Hello world", 10., 30., 10.),
+            span("Title", 10., 0., 10.),
+        ];
+        let page = PageText::new(&items);
+        std::assert_eq!(page.text(), "Title\n\nHello world\nsecond line\n\nright one\nright two");
+        std::assert_eq!(page.columns.len(), 3);
+        std::assert_eq!(page.lines().count(), 5);
+
+        let words: Vec<&str> = page.words().map(|w| &page.text[w.range.clone()]).collect();
+        std::assert_eq!(words, ["Title", "Hello", "world", "second", "line", "right", "one", "right", "two"]);
+
+        let hello = page.words().nth(1).unwrap();
+        let chars = page.word_chars(hello);
+        std::assert_eq!(chars.len(), 5);
+        assert!(chars.iter().all(|c| c.item == 3));
+        std::assert_eq!(chars[1].offset, 1);
+        std::assert_eq!(hello.rect, RectF::new(Vector2F::new(10., 20.), Vector2F::new(25., 10.)));
+    }
+
+    #[test]
+    fn word_gaps() {
+        // the second span starts right after the first one, the third one after a gap
+        let items = [
+            span("foo", 0., 10., 10.),
+            span("bar", 15., 10., 10.),
+            span("baz", 32., 10., 10.),
+        ];
+        std::assert_eq!(PageText::new(&items).text(), "foobar baz");
+
+        let options = LayoutOptions { word_gap: 0.5, ..LayoutOptions::default() };
+        std::assert_eq!(PageText::with_options(&items, &options).text(), "foobarbaz");
+    }
+
+    #[test]
+    fn lines_and_blocks() {
+        let items = [
+            span("one", 0., 10., 10.),
+            span("two", 0., 22., 10.),
+            // too far below to be in the same block
+            span("three", 0., 60., 10.),
+        ];
+        let page = PageText::new(&items);
+        std::assert_eq!(page.text(), "one\ntwo\n\nthree");
+        std::assert_eq!(page.blocks().count(), 2);
+        std::assert_eq!(page.lines().next().unwrap().direction, Vector2F::new(1., 0.));
+    }
+}
@@ -1,9 +1,10 @@
 use crate::{
     backend::FillMode,
+    budget::visible,
     BlendMode,
 };
 use font::GlyphId;
-use pathfinder_geometry::{transform2d::Transform2F, vector::Vector2F};
+use pathfinder_geometry::{rect::RectF, transform2d::Transform2F, vector::Vector2F};
 
 use super::{
     fontentry::FontEntry, graphicsstate::GraphicsState, BBox, Backend, DrawMode, TextChar,
@@ -12,6 +13,12 @@ use itertools::Either;
 use pdf::content::TextMode;
 use std::convert::TryInto;
 use std::sync::Arc;
+use pdf_encoding::FontMetrics;
+
+/// horizontal shift per unit of height of synthesized oblique glyphs (about 12°)
+const SYNTHETIC_SLANT: f32 = 0.21;
+/// outline dilation of synthesized bold glyphs in em
+const SYNTHETIC_BOLD: f32 = 0.015;
 
 #[derive(Clone)]
 pub struct TextState {
@@ -26,6 +33,8 @@ pub struct TextState {
     pub mode: TextMode,                     // Text rendering mode
     pub rise: f32,                          // Text rise
     pub knockout: f32,                      //Text knockout
+    pub fit_substituted_widths: bool,       // scale substituted glyphs to /Widths or the built-in metrics
+    pub region: Option<RectF>,              // glyphs outside of it are not drawn
 }
 impl TextState {
     pub fn new() -> TextState {
@@ -41,6 +50,8 @@ impl TextState {
             mode: TextMode::Fill,
             rise: 0.,
             knockout: 0.,
+            fit_substituted_widths: false,
+            region: None,
         }
     }
     pub fn reset_matrix(&mut self) {
@@ -134,6 +145,21 @@ impl TextState {
             self.rise,
         ) * e.font.font_matrix();
 
+        let synthetic_bold = e.substitution.as_ref().map(|s| s.synthetic_bold).unwrap_or(false);
+        let synthetic_oblique = e.substitution.as_ref().map(|s| s.synthetic_oblique).unwrap_or(false);
+        let fit_width = self.fit_substituted_widths
+            && e.substitution.is_some()
+            && (e.widths.is_some() || e.metrics.is_some());
+        // in glyph space
+        let oblique = if synthetic_oblique {
+            Transform2F::row_major(1., SYNTHETIC_SLANT, 0., 0., 1., 0.)
+        } else {
+            Transform2F::default()
+        };
+        let em = 1.0 / e.font.font_matrix().m11().abs().max(f32::EPSILON);
+        // previous char in the built-in metrics, for kerning
+        let mut prev_char = None;
+
         for (cid, t) in glyphs {
             let (gid, unicode, is_space) = match t {
                 Some((gid, unicode)) => {
@@ -148,10 +174,23 @@ impl TextState {
             let width: f32 = e
                 .widths
                 .as_ref()
-                .map(|w| w.get(cid as usize) * 0.001 * self.horiz_scale * self.font_size)
+                .map(|w| w.get(cid as usize))
+                .or_else(|| e.metrics.and_then(|m| standard_width(m, cid, unicode.as_deref())))
+                .map(|w| w * 0.001 * self.horiz_scale * self.font_size)
                 .or_else(|| glyph.as_ref().map(|g| tr.m11() * g.metrics.advance))
                 .unwrap_or(0.0);
 
+            if let Some(m) = e.metrics {
+                let c = standard_char(m, cid, unicode.as_deref());
+                if let (Some(left), Some(right)) = (prev_char, c) {
+                    let kern = m.kern(left, right) * 0.001 * self.horiz_scale * self.font_size;
+                    self.text_matrix =
+                        self.text_matrix * Transform2F::from_translation(Vector2F::new(kern, 0.));
+                    span.width += kern;
+                }
+                prev_char = c;
+            }
+
             if is_space {
                 let advance = (self.char_space + self.word_space) * self.horiz_scale + width;
                 self.text_matrix =
@@ -167,13 +206,25 @@ impl TextState {
                 span.width += advance;
                 continue;
             }
-            if let Some(glyph) = glyph {
-                let transform = gs.transform * self.text_matrix * tr;
+            if let Some(mut glyph) = glyph {
+                let mut transform = gs.transform * self.text_matrix * tr;
+                if fit_width {
+                    let glyph_width = tr.m11() * glyph.metrics.advance;
+                    if glyph_width > 0. && width > 0. {
+                        transform = transform * Transform2F::from_scale(Vector2F::new(width / glyph_width, 1.));
+                    }
+                }
+                transform = transform * oblique;
+                if synthetic_bold && glyph.path.len() != 0 {
+                    glyph.path.dilate(Vector2F::splat(SYNTHETIC_BOLD * em));
+                }
                 if glyph.path.len() != 0 {
                     span.bbox
                         .add(gs.transform * transform * glyph.path.bounds());
                     if let Some(ref draw_mode) = draw_mode {
-                        backend.draw_glyph(&glyph, draw_mode, transform, gs.clip_path_id);
+                        if visible(self.region, transform * glyph.path.bounds()) {
+                            backend.draw_glyph(&glyph, draw_mode, transform, gs.clip_path_id);
+                        }
                     }
                 }
             } else {
@@ -211,3 +262,74 @@ pub struct Span {
     pub width: f32,
     pub bbox: BBox,
 }
+
+/// Width from the built-in metrics, by the unicode of the glyph or by the code in the built-in encoding.
+fn standard_width(metrics: &FontMetrics, cid: u16, unicode: Option<&str>) -> Option<f32> {
+    standard_char(metrics, cid, unicode).and_then(|c| metrics.char_width(c))
+}
+
+/// The char of the glyph in the built-in metrics, like `standard_width`.
+fn standard_char(metrics: &FontMetrics, cid: u16, unicode: Option<&str>) -> Option<char> {
+    let mut chars = unicode.into_iter().flat_map(|s| s.chars());
+    let by_unicode = match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c).filter(|&c| metrics.char_width(c).is_some()),
+        _ => None,
+    };
+    by_unicode.or_else(|| {
+        let code: u8 = cid.try_into().ok()?;
+        metrics.encoding.forward_map()?.get(code).filter(|&c| metrics.char_width(c).is_some())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::testutil::{trace_with_fonts, PdfBuilder};
+    use crate::tracer::DrawItem;
+    use crate::StaticFontProvider;
+
+    /// Substitute for Helvetica, with different advances than the built-in metrics.
+    const FONT: &[u8] = include_bytes!("../../svg/resources/latinmodern-math.otf");
+
+    /// Width of `text` in text space, drawn at 10pt with a non-embedded Helvetica with the entries `dict`.
+    fn width(dict: &str, text: &str) -> f32 {
+        let mut pdf = PdfBuilder::new();
+        let font = pdf.add(format!("<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica {} >>", dict));
+        pdf.page((100., 100.), &format!("/Font << /F1 {} 0 R >>", font), &format!("BT /F1 10 Tf ({}) Tj ET", text));
+        trace_with_fonts(pdf.finish(), Arc::new(StaticFontProvider::new(&[("Helvetica", FONT)])))
+            .into_iter()
+            .map(|item| match item {
+                DrawItem::Text(span, _) => span.width,
+                _ => 0.,
+            })
+            .sum()
+    }
+
+    fn assert_width(dict: &str, text: &str, expected: f32) {
+        let width = width(dict, text);
+        assert!((width - expected).abs() < 1e-3, "{}: {} != {}", text, width, expected);
+    }
+
+    #[test]
+    fn standard_widths() {
+        // A and V are 667 wide in Helvetica
+        assert_width("", "AA", 13.34);
+        assert_width("", "VV", 13.34);
+    }
+
+    #[test]
+    fn standard_kerning() {
+        // KPX A V -70 and KPX V A -80
+        assert_width("", "AV", 13.34 - 0.7);
+        assert_width("", "AVA", 20.01 - 0.7 - 0.8);
+        // only within one string
+        assert_width("", "A) Tj (V", 13.34);
+    }
+
+    #[test]
+    fn widths_are_not_kerned() {
+        let widths = format!("/FirstChar 65 /LastChar 86 /Widths [{}]", "500 ".repeat(22));
+        assert_width(&widths, "AV", 10.);
+    }
+}
@@ -0,0 +1,248 @@
+//! Small previews of pages.
+//!
+//! ```ignore
+//! let cache = Cache::new()?;
+//! let thumbnailer = Thumbnailer::new(&cache, Vector2F::new(128., 128.)).quality(Quality::Fast);
+//! for path in files {
+//!     let file = FileOptions::cached().open(path)?;
+//!     let page = file.get_page(0)?;
+//!     let scene = thumbnailer.scene(&page, &file.resolver())?;
+//!     // ...
+//!     thumbnailer.next_document();
+//! }
+//! ```
+
+use std::sync::Arc;
+
+use font::Glyph;
+use pathfinder_content::{fill::FillRule, outline::Outline};
+use pathfinder_geometry::{rect::RectF, transform2d::Transform2F, vector::Vector2F};
+use pathfinder_renderer::scene::Scene;
+use pdf::content::Op;
+use pdf::error::PdfError;
+use pdf::font::Font as PdfFont;
+use pdf::object::{ImageXObject, MaybeRef, Page, PlainRef, Ref, Resolve, Resources, XObject};
+
+use crate::parallel::PageTrace;
+use crate::tracer::{TraceCache, Tracer};
+use crate::{page_size, render_page_with, Backend, PageBox, BlendMode, Cache, DrawMode, FillMode, FontEntry, Limits, RenderError, RenderOptions, SceneBackend, TextSpan};
+
+/// Text smaller than this (in output pixels) is drawn as a box with `Quality::Fast`.
+const MIN_TEXT_SIZE: f32 = 2.5;
+/// Paths smaller than this in both directions are skipped with `Quality::Fast`.
+const MIN_PATH_SIZE: f32 = 0.25;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Quality {
+    /// draw everything, like `render_page`
+    Full,
+    /// draw tiny text as boxes, skip tiny paths and scale images down to the size they are drawn at
+    #[default]
+    Fast,
+}
+
+/// The transform that fits `page_box` of `page` into `max_size`, keeping the aspect ratio.
+/// The output of `render_page_with` for `page_box` with this transform starts at the origin.
+pub fn thumbnail_transform(page: &Page, page_box: PageBox, max_size: Vector2F) -> Transform2F {
+    let size = page_size(page, page_box);
+    let scale = (max_size.x() / size.x()).min(max_size.y() / size.y());
+    Transform2F::from_scale(Vector2F::splat(scale))
+}
+
+/// Render a preview of `page` that fits into `max_size`. The view box of the scene is the size of the preview.
+pub fn thumbnail(page: &Page, resolve: &impl Resolve, cache: &Cache, max_size: Vector2F, quality: Quality) -> Result<Scene, PdfError> {
+    Thumbnailer::new(cache, max_size).quality(quality).scene(page, resolve)
+}
+
+/// Renders previews of many pages, possibly of many documents, with one cache.
+pub struct Thumbnailer<'a> {
+    cache: &'a Cache,
+    max_size: Vector2F,
+    quality: Quality,
+    options: RenderOptions,
+}
+
+impl<'a> Thumbnailer<'a> {
+    pub fn new(cache: &'a Cache, max_size: Vector2F) -> Self {
+        Thumbnailer {
+            cache,
+            max_size,
+            quality: Quality::default(),
+            options: RenderOptions::default(),
+        }
+    }
+    pub fn quality(mut self, quality: Quality) -> Self {
+        self.quality = quality;
+        self
+    }
+    /// Budgets for each page. A page that runs out of budget gives a partial preview.
+    pub fn options(mut self, options: RenderOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Render the preview of `page` into a scene.
+    pub fn scene(&self, page: &Page, resolve: &impl Resolve) -> Result<Scene, PdfError> {
+        let mut backend = SceneBackend::new(self.cache);
+        backend.set_downsample_images(self.quality == Quality::Fast);
+        let mut backend = ThumbnailBackend::new(backend, self.quality);
+        render_page_with(&mut backend, resolve, page, thumbnail_transform(page, self.options.page_box, self.max_size), &self.options)?;
+        Ok(backend.inner.finish())
+    }
+
+    /// Trace the preview of `page`. Fonts are taken from `cache` instead of the cache of the thumbnailer,
+    /// so clear it with `TraceCache::clear` before tracing pages of another document.
+    pub fn trace(&self, page: &Page, resolve: &impl Resolve, cache: &TraceCache) -> Result<PageTrace, PdfError> {
+        let mut clip_paths = vec![];
+        let mut backend = ThumbnailBackend::new(Tracer::new(cache, &mut clip_paths), self.quality);
+        let result = render_page_with(&mut backend, resolve, page, thumbnail_transform(page, self.options.page_box, self.max_size), &self.options)?;
+        let view_box = backend.inner.view_box();
+        let items = backend.inner.finish();
+        Ok(PageTrace { items, clip_paths, view_box, status: result.status })
+    }
+
+    /// Call before rendering pages of another document.
+    ///
+    /// Cached fonts and images are identified by their object in the current document,
+    /// so they are dropped. The standard fonts stay loaded.
+    pub fn next_document(&self) {
+        self.cache.clear();
+    }
+}
+
+/// Passes everything on to `inner`, except for content that does not show in a preview.
+struct ThumbnailBackend<B> {
+    inner: B,
+    quality: Quality,
+}
+impl<B: Backend> ThumbnailBackend<B> {
+    fn new(inner: B, quality: Quality) -> Self {
+        ThumbnailBackend { inner, quality }
+    }
+}
+
+impl<B: Backend> Backend for ThumbnailBackend<B> {
+    type ClipPathId = B::ClipPathId;
+
+    fn create_clip_path(&mut self, path: Outline, fill_rule: FillRule, parent: Option<Self::ClipPathId>) -> Self::ClipPathId {
+        self.inner.create_clip_path(path, fill_rule, parent)
+    }
+    fn draw(&mut self, outline: &Outline, mode: &DrawMode, fill_rule: FillRule, transform: Transform2F, clip: Option<Self::ClipPathId>) {
+        if self.quality == Quality::Fast {
+            let bounds = transform * outline.bounds();
+            if bounds.width() < MIN_PATH_SIZE && bounds.height() < MIN_PATH_SIZE {
+                return;
+            }
+        }
+        self.inner.draw(outline, mode, fill_rule, transform, clip);
+    }
+    fn set_view_box(&mut self, r: RectF) {
+        self.inner.set_view_box(r);
+    }
+    fn draw_image(&mut self, xref: Ref<XObject>, im: &ImageXObject, resources: &Resources, transform: Transform2F, mode: BlendMode, clip: Option<Self::ClipPathId>, resolve: &impl Resolve) {
+        self.inner.draw_image(xref, im, resources, transform, mode, clip, resolve);
+    }
+    fn draw_inline_image(&mut self, im: &Arc<ImageXObject>, resources: &Resources, transform: Transform2F, mode: BlendMode, clip: Option<Self::ClipPathId>, resolve: &impl Resolve) {
+        self.inner.draw_inline_image(im, resources, transform, mode, clip, resolve);
+    }
+    fn draw_glyph(&mut self, glyph: &Glyph, mode: &DrawMode, transform: Transform2F, clip: Option<Self::ClipPathId>) {
+        if self.quality == Quality::Fast {
+            let bounds = glyph.path.bounds();
+            let size = transform * bounds;
+            if size.width().max(size.height()) < MIN_TEXT_SIZE {
+                // a light box instead of the outline
+                let fill = match *mode {
+                    DrawMode::Fill { ref fill } | DrawMode::FillStroke { ref fill, .. } => fill,
+                    DrawMode::Stroke { ref stroke, .. } => stroke,
+                };
+                let mode = DrawMode::Fill {
+                    fill: FillMode { alpha: fill.alpha * 0.4, ..fill.clone() },
+                };
+                self.inner.draw(&Outline::from_rect(bounds), &mode, FillRule::Winding, transform, clip);
+                return;
+            }
+        }
+        self.inner.draw_glyph(glyph, mode, transform, clip);
+    }
+    fn get_font(&mut self, font_ref: &MaybeRef<PdfFont>, resolve: &impl Resolve, limits: &Limits) -> Result<Option<Arc<FontEntry>>, RenderError> {
+        self.inner.get_font(font_ref, resolve, limits)
+    }
+    fn add_text(&mut self, span: TextSpan, clip: Option<Self::ClipPathId>) {
+        self.inner.add_text(span, clip);
+    }
+    fn set_mcid(&mut self, mcid: Option<u32>, stream: Option<PlainRef>) {
+        self.inner.set_mcid(mcid, stream);
+    }
+    fn bug_text_no_font(&mut self, data: &[u8]) {
+        self.inner.bug_text_no_font(data);
+    }
+    fn bug_text_invisible(&mut self, text: &str) {
+        self.inner.bug_text_invisible(text);
+    }
+    fn bug_postscript(&mut self, data: &[u8]) {
+        self.inner.bug_postscript(data);
+    }
+    fn bug_op(&mut self, op_nr: usize) {
+        self.inner.bug_op(op_nr);
+    }
+    fn inspect_op(&mut self, op: &Op) {
+        self.inner.inspect_op(op);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pdf::file::FileOptions;
+
+    use crate::testutil::PdfBuilder;
+    use crate::tracer::DrawItem;
+    use crate::StaticFontProvider;
+
+    fn close(a: Vector2F, b: Vector2F) -> bool {
+        (a - b).length() < 1e-3
+    }
+
+    /// Trace a 200×100 pt page with a tiny and a large square into a 50×50 preview.
+    fn trace(quality: Quality) -> PageTrace {
+        trace_with(quality, RenderOptions::default())
+    }
+    /// Like `trace`; the crop box of the page is its left half.
+    fn trace_with(quality: Quality, options: RenderOptions) -> PageTrace {
+        let mut pdf = PdfBuilder::new();
+        pdf.page_with((200., 100.), "/CropBox [0 0 100 100]", "", "0 0 0.1 0.1 re f 10 10 80 80 re f");
+        let file = FileOptions::cached().load(pdf.finish()).unwrap();
+        let page = file.get_page(0).unwrap();
+        let provider = Arc::new(StaticFontProvider::new(&[]));
+        let cache = Cache::with_font_provider(provider.clone());
+        let trace_cache = TraceCache::with_font_provider(provider);
+        Thumbnailer::new(&cache, Vector2F::new(50., 50.))
+            .quality(quality)
+            .options(options)
+            .trace(&page, &file.resolver(), &trace_cache)
+            .unwrap()
+    }
+
+    #[test]
+    fn fits_into_max_size() {
+        let trace = trace(Quality::Full);
+        // keeps the aspect ratio and starts at the origin
+        assert!(close(trace.view_box.origin(), Vector2F::zero()));
+        assert!(close(trace.view_box.size(), Vector2F::new(50., 25.)));
+    }
+
+    #[test]
+    fn fits_page_box() {
+        let options = RenderOptions::default().page_box(PageBox::CropBox);
+        let trace = trace_with(Quality::Full, options);
+        assert!(close(trace.view_box.origin(), Vector2F::zero()));
+        assert!(close(trace.view_box.size(), Vector2F::new(50., 50.)));
+    }
+
+    #[test]
+    fn fast_skips_tiny_paths() {
+        let vectors = |trace: PageTrace| trace.items.iter().filter(|item| matches!(item, DrawItem::Vector(_))).count();
+        std::assert_eq!(vectors(trace(Quality::Full)) - vectors(trace(Quality::Fast)), 1);
+        std::assert_eq!(Quality::default(), Quality::Fast);
+    }
+}
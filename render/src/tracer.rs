@@ -1,16 +1,19 @@
 use crate::backend::Stroke;
-use crate::font::{load_font, StandardCache};
+use crate::font::{load_font, FontRc, StandardCache};
+use crate::fontprovider::{DirectoryFontProvider, FontProvider, FontSubstitution};
+use crate::cache::DEFAULT_MEMORY_LIMIT;
+use crate::limits::{Limits, RenderError};
+use crate::lru::{CacheStats, LruCache};
 use crate::{
     backend::{BlendMode, FillMode}, Backend, DrawMode, FontEntry, TextSpan,
 };
 use font::Glyph;
-use globalcache::sync::SyncCache;
+use globalcache::ValueSize;
 use pathfinder_content::{fill::FillRule, outline::Outline};
 use pathfinder_geometry::{rect::RectF, transform2d::Transform2F, vector::Vector2F};
 use pdf::error::PdfError;
 use pdf::font::Font as PdfFont;
-use pdf::object::{ImageXObject, MaybeRef, Ref, Resolve, Resources, XObject};
-use std::path::PathBuf;
+use pdf::object::{ImageXObject, MaybeRef, PlainRef, Ref, Resolve, Resources, XObject};
 use std::sync::Arc;
 
 pub struct ClipPath {
@@ -28,11 +31,33 @@ pub struct Tracer<'a> {
     pub view_box: RectF,
     cache: &'a TraceCache,
     op_nr: usize,
+    mcid: Option<u32>,
+    mcid_stream: Option<PlainRef>,
 }
 pub struct TraceCache {
-    fonts: Arc<SyncCache<u64, Option<Arc<FontEntry>>>>,
+    fonts: LruCache<FontKey, FontValue>,
     std: StandardCache,
 }
+#[derive(Clone, Hash, PartialEq, Eq)]
+enum FontKey {
+    Pdf(u64),
+    /// a font of the provider, by key
+    Standard(String),
+}
+#[derive(Clone)]
+enum FontValue {
+    Pdf(Option<Arc<FontEntry>>),
+    Standard(Option<FontRc>),
+}
+impl ValueSize for FontValue {
+    fn size(&self) -> usize {
+        match self {
+            FontValue::Pdf(Some(f)) => f.size(),
+            FontValue::Standard(Some(f)) => f.size(),
+            FontValue::Pdf(None) | FontValue::Standard(None) => std::mem::size_of::<Self>(),
+        }
+    }
+}
 fn font_key(font_ref: &MaybeRef<PdfFont>) -> u64 {
     match font_ref {
         MaybeRef::Direct(ref shared) => shared.as_ref() as *const PdfFont as _,
@@ -40,38 +65,74 @@ fn font_key(font_ref: &MaybeRef<PdfFont>) -> u64 {
     }
 }
 impl TraceCache {
-    pub fn new() -> Self {
-        let standard_fonts = PathBuf::from(std::env::var_os("STANDARD_FONTS").expect("STANDARD_FONTS is not set. Please check https://github.com/pdf-rs/pdf_render/#fonts for instructions."));
-
+    /// Use the fonts in `STANDARD_FONTS` (or `fonts/`) for fonts that are not embedded.
+    pub fn new() -> Result<Self, PdfError> {
+        Ok(TraceCache::with_font_provider(Arc::new(DirectoryFontProvider::from_env()?)))
+    }
+    pub fn with_font_provider(provider: Arc<dyn FontProvider>) -> Self {
         TraceCache {
-            fonts: SyncCache::new(),
-            std: StandardCache::new(standard_fonts),
+            fonts: LruCache::new(DEFAULT_MEMORY_LIMIT),
+            std: StandardCache::new(provider),
         }
     }
+    /// The font for `font_ref`, which has to be within `limits`.
     pub fn get_font(
         &self,
         font_ref: &MaybeRef<PdfFont>,
         resolve: &impl Resolve,
-    ) -> Result<Option<Arc<FontEntry>>, PdfError> {
+        limits: &Limits,
+    ) -> Result<Option<Arc<FontEntry>>, RenderError> {
         let mut error = None;
-        let val = self.fonts.get(font_key(font_ref), |_| {
-            match load_font(font_ref, resolve, &self.std) {
+        let val = self.fonts.try_get(FontKey::Pdf(font_key(font_ref)), || {
+            Ok(FontValue::Pdf(match load_font(font_ref, resolve, &self.std, |key| self.standard_font(key), limits) {
                 Ok(Some(f)) => Some(Arc::new(f)),
                 Ok(None) => None,
-                Err(e) => {
+                // not cached, another trace may allow it
+                Err(RenderError::Limit(e)) => return Err(e),
+                Err(RenderError::Pdf(e)) => {
                     error = Some(e);
                     None
                 }
-            }
-        });
+            }))
+        })?;
+        let val = match val {
+            FontValue::Pdf(f) => f,
+            _ => unreachable!(),
+        };
+        if let Some(ref entry) = val {
+            limits.check_font(entry)?;
+        }
         match error {
             None => Ok(val),
-            Some(e) => Err(e),
+            Some(e) => Err(e.into()),
+        }
+    }
+    /// The font `key` of the font provider, loaded on first use.
+    fn standard_font(&self, key: &str) -> Option<FontRc> {
+        let val = self.fonts.get(FontKey::Standard(key.into()), || FontValue::Standard(self.std.load(key)));
+        match val {
+            FontValue::Standard(f) => f,
+            _ => unreachable!(),
         }
     }
     pub fn require_unique_unicode(&mut self, require_unique_unicode: bool) {
         self.std.require_unique_unicode(require_unique_unicode);
     }
+    /// Set the memory limit in bytes and evict fonts until the cache fits.
+    pub fn set_memory_limit(&self, bytes: usize) {
+        self.fonts.set_capacity(bytes);
+    }
+    pub fn stats(&self) -> CacheStats {
+        self.fonts.stats()
+    }
+    /// Fonts that were not embedded and replaced by a different font.
+    pub fn substitutions(&self) -> Vec<FontSubstitution> {
+        self.std.substitutions()
+    }
+    /// Drop all cached fonts. The standard fonts and the statistics are kept.
+    pub fn clear(&self) {
+        self.fonts.retain(|key| matches!(key, FontKey::Standard(_)));
+    }
 }
 impl<'a> Tracer<'a> {
     pub fn new(cache: &'a TraceCache, clip_paths: &'a mut Vec<ClipPath>) -> Self {
@@ -80,6 +141,8 @@ impl<'a> Tracer<'a> {
             view_box: RectF::new(Vector2F::zero(), Vector2F::zero()),
             cache,
             op_nr: 0,
+            mcid: None,
+            mcid_stream: None,
             clip_paths,
         }
     }
@@ -111,7 +174,7 @@ impl<'a> Backend for Tracer<'a> {
         &mut self,
         outline: &Outline,
         mode: &DrawMode,
-        _fill_rule: FillRule,
+        fill_rule: FillRule,
         transform: Transform2F,
         clip: Option<ClipPathId>,
     ) {
@@ -134,9 +197,12 @@ impl<'a> Backend for Tracer<'a> {
                 _ => None,
             },
             stroke,
+            fill_rule,
             transform,
             clip,
             op_nr: self.op_nr,
+            mcid: self.mcid,
+            mcid_stream: self.mcid_stream,
         }));
     }
     fn set_view_box(&mut self, r: RectF) {
@@ -160,6 +226,8 @@ impl<'a> Backend for Tracer<'a> {
             op_nr: self.op_nr,
             mode,
             clip,
+            mcid: self.mcid,
+            mcid_stream: self.mcid_stream,
         }));
     }
     fn draw_inline_image(
@@ -180,6 +248,8 @@ impl<'a> Backend for Tracer<'a> {
             op_nr: self.op_nr,
             mode,
             clip,
+            mcid: self.mcid,
+            mcid_stream: self.mcid_stream,
         }));
     }
     fn draw_glyph(
@@ -194,8 +264,9 @@ impl<'a> Backend for Tracer<'a> {
         &mut self,
         font_ref: &MaybeRef<PdfFont>,
         resolve: &impl Resolve,
-    ) -> Result<Option<Arc<FontEntry>>, PdfError> {
-        self.cache.get_font(font_ref, resolve)
+        limits: &Limits,
+    ) -> Result<Option<Arc<FontEntry>>, RenderError> {
+        self.cache.get_font(font_ref, resolve, limits)
     }
     fn add_text(&mut self, span: TextSpan, clip: Option<Self::ClipPathId>) {
         self.items.push(DrawItem::Text(span, clip));
@@ -203,6 +274,10 @@ impl<'a> Backend for Tracer<'a> {
     fn bug_op(&mut self, op_nr: usize) {
         self.op_nr = op_nr;
     }
+    fn set_mcid(&mut self, mcid: Option<u32>, stream: Option<PlainRef>) {
+        self.mcid = mcid;
+        self.mcid_stream = stream;
+    }
 }
 
 #[derive(Debug)]
@@ -213,6 +288,9 @@ pub struct ImageObject {
     pub op_nr: usize,
     pub mode: BlendMode,
     pub clip: Option<ClipPathId>,
+    pub mcid: Option<u32>,
+    /// the form xobject whose content stream `mcid` belongs to, `None` for the page
+    pub mcid_stream: Option<PlainRef>,
 }
 #[derive(Debug)]
 pub struct InlineImageObject {
@@ -222,6 +300,9 @@ pub struct InlineImageObject {
     pub op_nr: usize,
     pub mode: BlendMode,
     pub clip: Option<ClipPathId>,
+    pub mcid: Option<u32>,
+    /// the form xobject whose content stream `mcid` belongs to, `None` for the page
+    pub mcid_stream: Option<PlainRef>,
 }
 
 #[derive(Debug)]
@@ -231,13 +312,37 @@ pub enum DrawItem {
     InlineImage(InlineImageObject),
     Text(TextSpan, Option<ClipPathId>),
 }
+impl DrawItem {
+    /// The marked-content identifier the item was drawn in.
+    pub fn mcid(&self) -> Option<u32> {
+        match *self {
+            DrawItem::Vector(ref v) => v.mcid,
+            DrawItem::Image(ref im) => im.mcid,
+            DrawItem::InlineImage(ref im) => im.mcid,
+            DrawItem::Text(ref span, _) => span.mcid,
+        }
+    }
+    /// The form xobject whose content stream `mcid` belongs to, `None` for the page.
+    pub fn mcid_stream(&self) -> Option<PlainRef> {
+        match *self {
+            DrawItem::Vector(ref v) => v.mcid_stream,
+            DrawItem::Image(ref im) => im.mcid_stream,
+            DrawItem::InlineImage(ref im) => im.mcid_stream,
+            DrawItem::Text(ref span, _) => span.mcid_stream,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct VectorPath {
     pub outline: Outline,
     pub fill: Option<FillMode>,
     pub stroke: Option<(FillMode, Stroke)>,
+    pub fill_rule: FillRule,
     pub transform: Transform2F,
     pub op_nr: usize,
     pub clip: Option<ClipPathId>,
+    pub mcid: Option<u32>,
+    /// the form xobject whose content stream `mcid` belongs to, `None` for the page
+    pub mcid_stream: Option<PlainRef>,
 }
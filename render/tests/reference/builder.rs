@@ -0,0 +1,102 @@
+//! Just enough of a PDF writer to build one-page test documents.
+
+use std::fmt::Write;
+
+#[derive(Default)]
+pub struct PdfBuilder {
+    // object `n` is at `n - 3`; the catalog (1) and the page tree (2) are added by `finish`
+    objects: Vec<Vec<u8>>,
+    pages: Vec<u32>,
+}
+
+const CATALOG: u32 = 1;
+const PAGES: u32 = 2;
+
+impl PdfBuilder {
+    pub fn new() -> Self {
+        PdfBuilder::default()
+    }
+
+    /// Add an object and return its number.
+    pub fn add(&mut self, body: impl Into<Vec<u8>>) -> u32 {
+        self.objects.push(body.into());
+        self.objects.len() as u32 + PAGES
+    }
+
+    /// Add a stream. `dict` is the content of the dictionary, without `/Length`.
+    pub fn stream(&mut self, dict: &str, data: &[u8]) -> u32 {
+        let mut body = format!("<< {} /Length {} >>\nstream\n", dict, data.len()).into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\nendstream");
+        self.add(body)
+    }
+
+    /// Add a page of `size` points. `resources` is the content of the resource dictionary.
+    pub fn page(&mut self, size: (f32, f32), resources: &str, content: &str) -> u32 {
+        self.page_with(size, "", resources, content)
+    }
+
+    /// Like `page`, with more entries `dict` in the page dictionary.
+    pub fn page_with(&mut self, size: (f32, f32), dict: &str, resources: &str, content: &str) -> u32 {
+        let content = self.stream("", content.as_bytes());
+        let page = self.add(format!(
+            "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] {} /Resources << {} >> /Contents {} 0 R >>",
+            PAGES, size.0, size.1, dict, resources, content
+        ));
+        self.pages.push(page);
+        page
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        let mut kids = String::new();
+        for page in &self.pages {
+            write!(kids, "{} 0 R ", page).unwrap();
+        }
+        let mut objects = vec![
+            format!("<< /Type /Catalog /Pages {} 0 R >>", PAGES).into_bytes(),
+            format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids, self.pages.len()).into_bytes(),
+        ];
+        objects.extend(self.objects);
+
+        let mut out = b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (idx, body) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n", idx + 1).as_bytes());
+            out.extend_from_slice(body);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = out.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            writeln!(table, "{:010} 00000 n ", offset).unwrap();
+        }
+        write!(
+            table,
+            "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1, CATALOG, xref
+        ).unwrap();
+        out.extend_from_slice(table.as_bytes());
+        out
+    }
+}
+
+/// `data` as a hex string, for inline strings and lookup tables.
+pub fn hex(data: &[u8]) -> String {
+    let mut s = String::with_capacity(data.len() * 2 + 2);
+    s.push('<');
+    for b in data {
+        write!(s, "{:02X}", b).unwrap();
+    }
+    s.push('>');
+    s
+}
+
+/// The table `tag` of an OpenType font.
+pub fn sfnt_table<'a>(font: &'a [u8], tag: &[u8; 4]) -> Option<&'a [u8]> {
+    let u16_at = |i: usize| u16::from_be_bytes([font[i], font[i + 1]]) as usize;
+    let u32_at = |i: usize| u32::from_be_bytes([font[i], font[i + 1], font[i + 2], font[i + 3]]) as usize;
+    (0..u16_at(4)).map(|n| 12 + 16 * n).find_map(|entry| {
+        (&font[entry..entry + 4] == tag).then(|| &font[u32_at(entry + 8)..][..u32_at(entry + 12)])
+    })
+}
@@ -0,0 +1,96 @@
+//! Perceptual comparison of rendered pages with reference images.
+//!
+//! Colors are compared in CIELAB after compositing onto white, so the tolerance is roughly in
+//! "just noticeable differences". Anti-aliasing moves edges by a fraction of a pixel between
+//! rasterizers and versions, so a pixel only counts as different if no pixel next to it in the
+//! reference is close enough either.
+
+use image::{Rgba, RgbaImage};
+
+#[derive(Debug, Copy, Clone)]
+pub struct Tolerance {
+    /// largest color difference (CIE76 ΔE) that is ignored
+    pub delta_e: f32,
+    /// fraction of pixels that may differ by more than `delta_e`
+    pub pixels: f32,
+}
+
+impl Tolerance {
+    pub const DEFAULT: Tolerance = Tolerance { delta_e: 3.0, pixels: 0.001 };
+    /// for text, where hinting and curve flattening move more pixels
+    pub const TEXT: Tolerance = Tolerance { delta_e: 6.0, pixels: 0.005 };
+}
+
+pub struct Mismatch {
+    pub message: String,
+    /// the reference, faded, with differing pixels in red
+    pub diff: RgbaImage,
+}
+
+pub fn compare(actual: &RgbaImage, reference: &RgbaImage, tolerance: Tolerance) -> Result<(), Mismatch> {
+    if actual.dimensions() != reference.dimensions() {
+        return Err(Mismatch {
+            message: format!("size is {:?}, expected {:?}", actual.dimensions(), reference.dimensions()),
+            diff: actual.clone(),
+        });
+    }
+    let (width, height) = actual.dimensions();
+    let mut diff = RgbaImage::new(width, height);
+    let mut differing = 0;
+    let mut max_delta: f32 = 0.;
+    for (x, y, &pixel) in actual.enumerate_pixels() {
+        let color = lab(pixel);
+        let expected = lab(*reference.get_pixel(x, y));
+        // the closest match in the neighbourhood
+        let closest = neighbours(x, y, width, height)
+            .map(|(nx, ny)| delta_e(color, lab(*reference.get_pixel(nx, ny))))
+            .fold(delta_e(color, expected), f32::min);
+
+        if closest > tolerance.delta_e {
+            differing += 1;
+            max_delta = max_delta.max(closest);
+            diff.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+        } else {
+            let l = (expected[0] / 100. * 64. + 191.) as u8;
+            diff.put_pixel(x, y, Rgba([l, l, l, 255]));
+        }
+    }
+
+    let allowed = (tolerance.pixels * (width * height) as f32) as usize;
+    if differing > allowed {
+        return Err(Mismatch {
+            message: format!(
+                "{} pixels differ (at most {} allowed), by up to ΔE {:.1} (tolerance {:.1})",
+                differing, allowed, max_delta, tolerance.delta_e
+            ),
+            diff,
+        });
+    }
+    Ok(())
+}
+
+fn neighbours(x: u32, y: u32, width: u32, height: u32) -> impl Iterator<Item = (u32, u32)> {
+    let xs = x.saturating_sub(1)..=(x + 1).min(width - 1);
+    let ys = y.saturating_sub(1)..=(y + 1).min(height - 1);
+    ys.flat_map(move |ny| xs.clone().map(move |nx| (nx, ny)))
+}
+
+fn delta_e(a: [f32; 3], b: [f32; 3]) -> f32 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+/// sRGB over white to CIELAB (D65)
+fn lab(Rgba([r, g, b, a]): Rgba<u8>) -> [f32; 3] {
+    let alpha = a as f32 / 255.;
+    let linear = |c: u8| {
+        let c = (c as f32 / 255.) * alpha + (1. - alpha);
+        if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+    };
+    let (r, g, b) = (linear(r), linear(g), linear(b));
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+    let f = |t: f32| if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16. / 116. };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116. * fy - 16., 500. * (fx - fy), 200. * (fy - fz)]
+}
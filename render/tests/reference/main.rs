@@ -0,0 +1,391 @@
+//! Renders small generated documents, one per feature, and compares them with the images in `images/`.
+//!
+//! Pages are rasterized on the CPU, so no GPU is needed:
+//! `cargo test -p pdf_render --test reference`
+//!
+//! A missing reference image is an error. To create the images for new tests, or after an intended
+//! change in the output, run with `UPDATE_REFERENCES=1`, look at the new images and check them in.
+//! When a page does not match, the output and a diff image are written to the target directory and
+//! their paths are printed.
+
+mod builder;
+mod compare;
+
+use std::path::Path;
+use std::sync::Arc;
+
+use image::RgbaImage;
+use pathfinder_geometry::transform2d::Transform2F;
+use pdf::file::FileOptions;
+use pdf_render::{render_page, Cache, SceneBackend, StaticFontProvider};
+use rasterize::CpuRasterizer;
+
+use builder::{hex, sfnt_table, PdfBuilder};
+use compare::{compare, Tolerance};
+
+/// pixels per inch of the rendered pages
+const DPI: f32 = 96.;
+/// size of the test pages in points
+const PAGE: (f32, f32) = (72., 72.);
+
+const LATIN_MODERN: &[u8] = include_bytes!("../../../svg/resources/latinmodern-math.otf");
+const NOTO_BENGALI: &[u8] = include_bytes!("../../../svg/resources/NotoSerifBengali-Regular.ttf");
+
+// every font that is not embedded falls back to this one, so the output does not depend on the machine
+static FONTS: &[(&str, &[u8])] = &[("Arial", LATIN_MODERN)];
+
+fn render(pdf: Vec<u8>) -> RgbaImage {
+    let file = FileOptions::cached().load(pdf).expect("invalid PDF");
+    let resolver = file.resolver();
+    let page = file.get_page(0).expect("no page");
+    let cache = Cache::with_font_provider(Arc::new(StaticFontProvider::new(FONTS)));
+    let mut backend = SceneBackend::new(&cache);
+    render_page(&mut backend, &resolver, &page, Transform2F::from_scale(DPI / 25.4)).expect("can't render page");
+    CpuRasterizer::new().rasterize(backend.finish(), None)
+}
+
+fn check(name: &str, pdf: Vec<u8>, tolerance: Tolerance) {
+    let actual = render(pdf);
+    let reference_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/reference/images").join(format!("{}.png", name));
+
+    if std::env::var_os("UPDATE_REFERENCES").is_some() {
+        std::fs::create_dir_all(reference_path.parent().unwrap()).unwrap();
+        actual.save(&reference_path).unwrap();
+        eprintln!("{}: wrote reference image {}", name, reference_path.display());
+        return;
+    }
+    if !reference_path.exists() {
+        panic!("{}: no reference image {}, run with UPDATE_REFERENCES=1 to create it", name, reference_path.display());
+    }
+
+    let reference = image::open(&reference_path).expect("can't read reference image").to_rgba8();
+    if let Err(mismatch) = compare(&actual, &reference, tolerance) {
+        let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("reference");
+        std::fs::create_dir_all(&dir).unwrap();
+        let actual_path = dir.join(format!("{}.png", name));
+        let diff_path = dir.join(format!("{}-diff.png", name));
+        actual.save(&actual_path).unwrap();
+        mismatch.diff.save(&diff_path).unwrap();
+        panic!(
+            "{} does not match {}: {}\n  output: {}\n  diff:   {}",
+            name,
+            reference_path.display(),
+            mismatch.message,
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+/// A document with one page.
+fn page(resources: &str, content: &str) -> Vec<u8> {
+    let mut pdf = PdfBuilder::new();
+    pdf.page(PAGE, resources, content);
+    pdf.finish()
+}
+
+/// A document with one page that shows the font `/F1` after `setup` added it.
+fn font_page(setup: impl FnOnce(&mut PdfBuilder) -> String, text: &str) -> Vec<u8> {
+    let mut pdf = PdfBuilder::new();
+    let font = setup(&mut pdf);
+    let content = format!("BT /F1 18 Tf 4 44 Td {} Tj 0 -24 Td 1 Tr 0.5 w {} Tj ET", text, text);
+    pdf.page(PAGE, &format!("/Font << /F1 {} 0 R >>", font), &content);
+    pdf.finish()
+}
+
+/// A document with one page showing a 4×4 image. `dict` describes `data`, without the size.
+fn image_page(dict: &str, data: &[u8]) -> Vec<u8> {
+    image_page_sized(dict, 4, data)
+}
+
+fn image_page_sized(dict: &str, size: u32, data: &[u8]) -> Vec<u8> {
+    let mut pdf = PdfBuilder::new();
+    let image = pdf.stream(&format!("/Type /XObject /Subtype /Image /Width {} /Height {} {}", size, size, dict), data);
+    pdf.page(PAGE, &format!("/XObject << /Im0 {} 0 R >>", image), "q 56 0 0 56 8 8 cm /Im0 Do Q");
+    pdf.finish()
+}
+
+/// A gradient from red to blue in the 0..1 range.
+const RED_TO_BLUE: &str = "<< /FunctionType 2 /Domain [0 1] /C0 [1 0 0] /C1 [0 0 1] /N 1 >>";
+
+// four pixels per row: red, green, blue, white, shifted by one in each row
+fn rgb_pixels() -> Vec<u8> {
+    let colors = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 255, 255]];
+    (0..4).flat_map(|y| (0..4).flat_map(move |x| colors[(x + y) % 4])).collect()
+}
+
+// paths
+
+#[test]
+fn fill_rules() {
+    check("fill_rules", page("", "\
+        0 0.5 0 rg 6 40 20 20 re 16 46 20 20 re f \
+        0 0 0.5 rg 36 6 20 20 re 46 16 20 20 re f*"), Tolerance::DEFAULT);
+}
+
+#[test]
+fn curves() {
+    check("curves", page("", "\
+        1 0.5 0 rg 36 8 m 55 8 64 17 64 36 c 64 55 55 64 36 64 c 17 64 8 55 8 36 c 8 17 17 8 36 8 c f \
+        0 0 1 RG 2 w 12 12 m 60 60 12 60 v 36 20 y S"), Tolerance::DEFAULT);
+}
+
+#[test]
+fn strokes() {
+    check("strokes", page("", "\
+        6 w 0 J 10 60 m 62 60 l S \
+        1 J 10 48 m 62 48 l S \
+        2 J 10 36 m 62 36 l S \
+        0.8 0 0 RG 4 w 0 j 8 6 m 20 24 l 32 6 l S \
+        1 j 38 6 m 50 24 l 62 6 l S \
+        0 0 0.8 RG 1 w [4 2] 0 d 8 28 m 64 28 l S"), Tolerance::DEFAULT);
+}
+
+#[test]
+fn clipping() {
+    check("clipping", page("", "\
+        q 8 8 56 56 re W n 36 36 m 60 36 l 36 60 l h 12 12 m 36 12 l 12 36 l h W* n \
+        0 0.6 0.6 rg 0 0 72 72 re f Q \
+        0 0 0 RG 0 0 m 72 72 l S"), Tolerance::DEFAULT);
+}
+
+#[test]
+fn transparency() {
+    check("transparency", page(
+        "/ExtGState << /A << /ca 0.5 /CA 0.5 >> /M << /BM /Multiply >> >>",
+        "1 0 0 rg 8 8 40 40 re f \
+        q /A gs 0 0 1 rg 24 24 40 40 re f 4 w 0 1 0 RG 8 64 m 64 8 l S Q \
+        q /M gs 1 1 0 rg 16 4 20 64 re f Q"), Tolerance::DEFAULT);
+}
+
+#[test]
+fn color_spaces() {
+    let mut pdf = PdfBuilder::new();
+    let icc = pdf.stream("/N 3 /Alternate /DeviceRGB", &[0; 128]);
+    pdf.page(PAGE, &format!(
+        "/ColorSpace << /ICC [/ICCBased {} 0 R] /Spot [/Separation /Spot /DeviceCMYK \
+            << /FunctionType 2 /Domain [0 1] /C0 [0 0 0 0] /C1 [0 1 1 0] /N 1 >>] >>", icc),
+        "0.5 g 4 40 30 28 re f \
+        0 1 1 0 k 38 40 30 28 re f \
+        /ICC cs 0 0.5 1 sc 4 4 30 28 re f \
+        /Spot cs 0.7 sc 38 4 30 28 re f");
+    check("color_spaces", pdf.finish(), Tolerance::DEFAULT);
+}
+
+// shadings and patterns
+
+#[test]
+#[ignore = "the sh operator is not implemented"]
+fn shading_axial() {
+    let mut pdf = PdfBuilder::new();
+    let shading = pdf.add(format!("<< /ShadingType 2 /ColorSpace /DeviceRGB /Coords [8 8 64 64] /Function {} /Extend [true true] >>", RED_TO_BLUE));
+    pdf.page(PAGE, &format!("/Shading << /Sh0 {} 0 R >>", shading), "q 4 4 64 64 re W n /Sh0 sh Q");
+    check("shading_axial", pdf.finish(), Tolerance::DEFAULT);
+}
+
+#[test]
+#[ignore = "the sh operator is not implemented"]
+fn shading_radial() {
+    let mut pdf = PdfBuilder::new();
+    let shading = pdf.add(format!("<< /ShadingType 3 /ColorSpace /DeviceRGB /Coords [30 30 4 36 36 32] /Function {} /Extend [false true] >>", RED_TO_BLUE));
+    pdf.page(PAGE, &format!("/Shading << /Sh0 {} 0 R >>", shading), "/Sh0 sh");
+    check("shading_radial", pdf.finish(), Tolerance::DEFAULT);
+}
+
+#[test]
+#[ignore = "patterns are painted black (SceneBackend)"]
+fn pattern_shading() {
+    let mut pdf = PdfBuilder::new();
+    let pattern = pdf.add(format!(
+        "<< /Type /Pattern /PatternType 2 /Shading << /ShadingType 2 /ColorSpace /DeviceRGB /Coords [0 0 72 0] /Function {} >> >>",
+        RED_TO_BLUE
+    ));
+    pdf.page(PAGE, &format!("/Pattern << /P0 {} 0 R >>", pattern), "/Pattern cs /P0 scn 8 8 56 56 re f");
+    check("pattern_shading", pdf.finish(), Tolerance::DEFAULT);
+}
+
+#[test]
+#[ignore = "patterns are painted black (SceneBackend)"]
+fn pattern_tiling_colored() {
+    let mut pdf = PdfBuilder::new();
+    let pattern = pdf.stream(
+        "/Type /Pattern /PatternType 1 /PaintType 1 /TilingType 1 /BBox [0 0 12 12] /XStep 12 /YStep 12 /Resources << >>",
+        b"1 0 0 rg 0 0 6 6 re f 0 0 1 rg 6 6 6 6 re f",
+    );
+    pdf.page(PAGE, &format!("/Pattern << /P0 {} 0 R >>", pattern), "/Pattern cs /P0 scn 8 8 56 56 re f");
+    check("pattern_tiling_colored", pdf.finish(), Tolerance::DEFAULT);
+}
+
+// fonts
+
+fn font_descriptor(pdf: &mut PdfBuilder, name: &str, file: &str) -> u32 {
+    pdf.add(format!(
+        "<< /Type /FontDescriptor /FontName /{} /Flags 32 /FontBBox [-500 -300 1500 1100] \
+        /ItalicAngle 0 /Ascent 800 /Descent -200 /CapHeight 700 /StemV 80 {} >>",
+        name, file
+    ))
+}
+
+#[test]
+fn font_type1_standard() {
+    let pdf = font_page(|pdf| {
+        pdf.add("<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>")
+            .to_string()
+    }, "(Abg)");
+    check("font_type1_standard", pdf, Tolerance::TEXT);
+}
+
+#[test]
+fn font_type1c() {
+    let pdf = font_page(|pdf| {
+        let cff = sfnt_table(LATIN_MODERN, b"CFF ").expect("no CFF table");
+        let file = pdf.stream("/Subtype /Type1C", cff);
+        let descriptor = font_descriptor(pdf, "LatinModernMath", &format!("/FontFile3 {} 0 R", file));
+        pdf.add(format!(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /LatinModernMath /Encoding /WinAnsiEncoding /FontDescriptor {} 0 R >>",
+            descriptor
+        )).to_string()
+    }, "(Abg)");
+    check("font_type1c", pdf, Tolerance::TEXT);
+}
+
+#[test]
+fn font_opentype() {
+    let pdf = font_page(|pdf| {
+        let file = pdf.stream("/Subtype /OpenType", LATIN_MODERN);
+        let descriptor = font_descriptor(pdf, "LatinModernMath", &format!("/FontFile3 {} 0 R", file));
+        pdf.add(format!(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /LatinModernMath /Encoding /WinAnsiEncoding /FontDescriptor {} 0 R >>",
+            descriptor
+        )).to_string()
+    }, "(Abg)");
+    check("font_opentype", pdf, Tolerance::TEXT);
+}
+
+#[test]
+fn font_truetype_cid() {
+    let font = font::parse(NOTO_BENGALI).expect("can't parse font");
+    let text: Vec<u8> = "কখগ".chars()
+        .map(|c| font.gid_for_unicode_codepoint(c as u32).expect("glyph missing").0 as u16)
+        .flat_map(u16::to_be_bytes)
+        .collect();
+    let pdf = font_page(|pdf| {
+        let file = pdf.stream("", NOTO_BENGALI);
+        let descriptor = font_descriptor(pdf, "NotoSerifBengali", &format!("/FontFile2 {} 0 R", file));
+        let cid_font = pdf.add(format!(
+            "<< /Type /Font /Subtype /CIDFontType2 /BaseFont /NotoSerifBengali \
+            /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> \
+            /FontDescriptor {} 0 R /CIDToGIDMap /Identity /DW 600 >>",
+            descriptor
+        ));
+        pdf.add(format!(
+            "<< /Type /Font /Subtype /Type0 /BaseFont /NotoSerifBengali /Encoding /Identity-H /DescendantFonts [{} 0 R] >>",
+            cid_font
+        )).to_string()
+    }, &hex(&text));
+    check("font_truetype_cid", pdf, Tolerance::TEXT);
+}
+
+#[test]
+fn font_type3() {
+    let pdf = font_page(|pdf| {
+        let square = pdf.stream("", b"1000 0 0 0 1000 1000 d1 100 100 800 800 re f");
+        // d0 glyphs bring their own color
+        let triangle = pdf.stream("", b"1000 0 d0 0 0.6 0 rg 100 100 m 900 100 l 500 900 l f");
+        pdf.add(format!(
+            "<< /Type /Font /Subtype /Type3 /FontBBox [0 0 1000 1000] /FontMatrix [0.001 0 0 0.001 0 0] \
+            /CharProcs << /square {} 0 R /triangle {} 0 R >> \
+            /Encoding << /Type /Encoding /Differences [65 /square /triangle] >> \
+            /FirstChar 65 /LastChar 66 /Widths [1000 1000] /Resources << >> >>",
+            square, triangle
+        )).to_string()
+    }, "(ABA)");
+    check("font_type3", pdf, Tolerance::TEXT);
+}
+
+// images
+
+#[test]
+fn image_gray8() {
+    let data: Vec<u8> = (0..16).map(|i| i * 17).collect();
+    check("image_gray8", image_page("/ColorSpace /DeviceGray /BitsPerComponent 8", &data), Tolerance::DEFAULT);
+}
+
+#[test]
+fn image_gray1() {
+    // a checker board with 8×8 pixels, one byte per row
+    let data = [0xAA, 0x55, 0xAA, 0x55, 0xAA, 0x55, 0xAA, 0x55];
+    check("image_gray1", image_page_sized("/ColorSpace /DeviceGray /BitsPerComponent 1", 8, &data), Tolerance::DEFAULT);
+}
+
+#[test]
+fn image_rgb8() {
+    check("image_rgb8", image_page("/ColorSpace /DeviceRGB /BitsPerComponent 8", &rgb_pixels()), Tolerance::DEFAULT);
+}
+
+#[test]
+fn image_cmyk8() {
+    let colors = [[255, 0, 0, 0], [0, 255, 0, 0], [0, 0, 255, 0], [0, 0, 0, 128]];
+    let data: Vec<u8> = (0..4).flat_map(|y| (0..4).flat_map(move |x| colors[(x + y) % 4])).collect();
+    check("image_cmyk8", image_page("/ColorSpace /DeviceCMYK /BitsPerComponent 8", &data), Tolerance::DEFAULT);
+}
+
+#[test]
+fn image_indexed_rgb() {
+    let lookup = hex(&[255, 0, 0, 0, 128, 0, 0, 0, 255, 255, 255, 0]);
+    let data: Vec<u8> = (0..16).map(|i| (i % 4) as u8).collect();
+    let dict = format!("/ColorSpace [/Indexed /DeviceRGB 3 {}] /BitsPerComponent 8", lookup);
+    check("image_indexed_rgb", image_page(&dict, &data), Tolerance::DEFAULT);
+}
+
+#[test]
+fn image_indexed_cmyk() {
+    let lookup = hex(&[255, 0, 0, 0, 0, 255, 0, 0, 0, 0, 255, 0, 0, 0, 0, 255]);
+    let data: Vec<u8> = (0..16).map(|i| (i / 4) as u8).collect();
+    let dict = format!("/ColorSpace [/Indexed /DeviceCMYK 3 {}] /BitsPerComponent 8", lookup);
+    check("image_indexed_cmyk", image_page(&dict, &data), Tolerance::DEFAULT);
+}
+
+#[test]
+fn image_separation() {
+    let data: Vec<u8> = (0..16).map(|i| i * 17).collect();
+    let dict = "/ColorSpace [/Separation /Spot /DeviceRGB << /FunctionType 2 /Domain [0 1] /C0 [1 1 1] /C1 [0.5 0 0.5] /N 1 >>] /BitsPerComponent 8";
+    check("image_separation", image_page(dict, &data), Tolerance::DEFAULT);
+}
+
+#[test]
+fn image_icc_based() {
+    let mut pdf = PdfBuilder::new();
+    let icc = pdf.stream("/N 3 /Alternate /DeviceRGB", &[0; 128]);
+    let image = pdf.stream(
+        &format!("/Type /XObject /Subtype /Image /Width 4 /Height 4 /ColorSpace [/ICCBased {} 0 R] /BitsPerComponent 8", icc),
+        &rgb_pixels(),
+    );
+    pdf.page(PAGE, &format!("/XObject << /Im0 {} 0 R >>", image), "q 56 0 0 56 8 8 cm /Im0 Do Q");
+    check("image_icc_based", pdf.finish(), Tolerance::DEFAULT);
+}
+
+#[test]
+fn image_soft_mask() {
+    let mut pdf = PdfBuilder::new();
+    let alpha: Vec<u8> = (0..16).map(|i| if i % 5 == 0 { 0 } else { 255 - i * 10 }).collect();
+    let mask = pdf.stream("/Type /XObject /Subtype /Image /Width 4 /Height 4 /ColorSpace /DeviceGray /BitsPerComponent 8", &alpha);
+    let image = pdf.stream(
+        &format!("/Type /XObject /Subtype /Image /Width 4 /Height 4 /ColorSpace /DeviceRGB /BitsPerComponent 8 /SMask {} 0 R", mask),
+        &rgb_pixels(),
+    );
+    pdf.page(PAGE, &format!("/XObject << /Im0 {} 0 R >>", image), "0 0 0 rg 0 30 72 12 re f q 56 0 0 56 8 8 cm /Im0 Do Q");
+    check("image_soft_mask", pdf.finish(), Tolerance::DEFAULT);
+}
+
+#[test]
+fn image_dct() {
+    // a smooth gradient, so compression artifacts stay small
+    let pixels: Vec<u8> = (0..16u32).flat_map(|y| (0..16u32).flat_map(move |x| [(x * 16) as u8, (y * 16) as u8, 128])).collect();
+    let mut jpeg = vec![];
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, 95)
+        .encode(&pixels, 16, 16, image::ExtendedColorType::Rgb8)
+        .unwrap();
+    let dict = "/ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /DCTDecode";
+    check("image_dct", image_page_sized(dict, 16, &jpeg), Tolerance::DEFAULT);
+}
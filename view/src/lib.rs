@@ -1,5 +1,6 @@
 mod context;
 pub mod config;
+pub mod outline;
 pub mod native;
 pub mod wasm;
 
@@ -11,6 +12,7 @@ use winit::{
     keyboard::{KeyCode, ModifiersState, PhysicalKey},
 };
 use crate::context::{ViewBackend, Context, DEFAULT_SCALE};
+use pdf_render::outline::OutlineTree;
 
 
 pub struct Emitter<E> {
@@ -59,6 +61,14 @@ pub trait Interactive: 'static {
     fn cursor_moved(&mut self, ctx: &mut Context<Self::Backend>, pos: Vector2F) {}
     
     fn exit(&mut self, ctx: &mut Context<Self::Backend>) {}
+
+    /// The document outline, for UIs that show it themselves.
+    fn outline(&self) -> Option<&OutlineTree> {
+        None
+    }
+
+    /// Go to the outline item at `path`, the index at each level starting at the top.
+    fn goto_outline_item(&mut self, ctx: &mut Context<Self::Backend>, path: &[usize]) {}
     
     fn title(&self) -> String {
         "A fantastic window!".into()
@@ -0,0 +1,234 @@
+//! A panel showing the document outline on the left side of the window.
+
+use std::collections::HashSet;
+
+use font::Font;
+use pathfinder_color::ColorU;
+use pathfinder_content::outline::{Contour, Outline};
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::Vector2F;
+use pathfinder_renderer::paint::Paint;
+use pathfinder_renderer::scene::{ClipPath, ClipPathId, DrawPath, Scene};
+use pdf_render::outline::{OutlineItem, OutlineTree};
+use winit::keyboard::KeyCode;
+
+// sizes in logical pixels
+const WIDTH: f32 = 320.;
+const ROW_HEIGHT: f32 = 22.;
+const FONT_SIZE: f32 = 14.;
+const INDENT: f32 = 16.;
+const PADDING: f32 = 8.;
+
+/// What the viewer should do after an input to the panel.
+#[derive(Debug, PartialEq)]
+pub enum PanelAction {
+    /// the input was not for the panel
+    Ignored,
+    Redraw,
+    /// go to the item with this path
+    Goto(Vec<usize>),
+    Close,
+}
+
+pub struct OutlinePanel {
+    tree: OutlineTree,
+    // paths of the items whose children are shown
+    expanded: HashSet<Vec<usize>>,
+    // index into the rows
+    selected: usize,
+    font: Option<Box<dyn Font + Send + Sync>>,
+}
+
+impl OutlinePanel {
+    /// `font` is used for the titles; without one, only the rows are drawn.
+    pub fn new(tree: OutlineTree, font: Option<Box<dyn Font + Send + Sync>>) -> Self {
+        let mut expanded = HashSet::new();
+        for (path, item) in tree.rows(|_, item| item.open) {
+            if item.open {
+                expanded.insert(path);
+            }
+        }
+        OutlinePanel {
+            tree,
+            expanded,
+            selected: 0,
+            font,
+        }
+    }
+
+    pub fn tree(&self) -> &OutlineTree {
+        &self.tree
+    }
+
+    fn rows(&self) -> Vec<(Vec<usize>, &OutlineItem)> {
+        self.tree.rows(|path, _| self.expanded.contains(path))
+    }
+
+    /// Path of the selected item.
+    pub fn selected(&self) -> Option<Vec<usize>> {
+        self.rows().into_iter().nth(self.selected).map(|(path, _)| path)
+    }
+
+    /// Arrow keys move the selection and open or close items, Enter goes to the selected item.
+    pub fn key(&mut self, key: KeyCode) -> PanelAction {
+        let rows = self.rows();
+        let (path, item) = match rows.get(self.selected) {
+            Some((path, item)) => (path.clone(), *item),
+            None => return match key {
+                KeyCode::Escape => PanelAction::Close,
+                _ => PanelAction::Ignored,
+            },
+        };
+        let num_rows = rows.len();
+        match key {
+            KeyCode::ArrowDown => self.selected = (self.selected + 1).min(num_rows - 1),
+            KeyCode::ArrowUp => self.selected = self.selected.saturating_sub(1),
+            KeyCode::PageDown => self.selected = (self.selected + 10).min(num_rows - 1),
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(10),
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => self.selected = num_rows - 1,
+            KeyCode::ArrowRight if !item.children.is_empty() => {
+                self.expanded.insert(path);
+            }
+            KeyCode::ArrowLeft => {
+                if !self.expanded.remove(&path) && path.len() > 1 {
+                    // select the parent
+                    let parent = &path[..path.len() - 1];
+                    if let Some(idx) = rows.iter().position(|(p, _)| p == parent) {
+                        self.selected = idx;
+                    }
+                }
+            }
+            KeyCode::Enter | KeyCode::NumpadEnter | KeyCode::Space => return PanelAction::Goto(path),
+            KeyCode::Escape => return PanelAction::Close,
+            _ => return PanelAction::Ignored,
+        }
+        PanelAction::Redraw
+    }
+
+    /// A click at `pos` in window pixels. Clicking the marker of an item opens or closes it.
+    pub fn click(&mut self, pos: Vector2F, window_size: Vector2F, scale_factor: f32) -> PanelAction {
+        if pos.x() > WIDTH * scale_factor {
+            return PanelAction::Ignored;
+        }
+        let rows = self.rows();
+        let first = self.first_row(rows.len(), window_size, scale_factor);
+        let idx = first + ((pos.y() / scale_factor - PADDING) / ROW_HEIGHT).floor().max(0.) as usize;
+        let (path, item) = match rows.get(idx) {
+            Some((path, item)) => (path.clone(), *item),
+            None => return PanelAction::Redraw,
+        };
+        self.selected = idx;
+        let marker_end = PADDING + INDENT * path.len() as f32;
+        if !item.children.is_empty() && pos.x() / scale_factor < marker_end {
+            if !self.expanded.remove(&path) {
+                self.expanded.insert(path);
+            }
+            return PanelAction::Redraw;
+        }
+        PanelAction::Goto(path)
+    }
+
+    // first visible row, so that the selection is on screen
+    fn first_row(&self, num_rows: usize, window_size: Vector2F, scale_factor: f32) -> usize {
+        let visible = (((window_size.y() / scale_factor) - 2. * PADDING) / ROW_HEIGHT).floor().max(1.) as usize;
+        (self.selected + 1).saturating_sub(visible).min(num_rows.saturating_sub(1))
+    }
+
+    /// Draw the panel on top of `scene`, which is in window pixels.
+    pub fn draw(&self, scene: &mut Scene, window_size: Vector2F, scale_factor: f32) {
+        let s = scale_factor;
+        let panel = RectF::new(Vector2F::zero(), Vector2F::new(WIDTH * s, window_size.y()));
+        let background = scene.push_paint(&Paint::from_color(ColorU::new(245, 245, 245, 240)));
+        scene.push_draw_path(DrawPath::new(Outline::from_rect(panel), background));
+        let clip = scene.push_clip_path(ClipPath::new(Outline::from_rect(panel)));
+
+        let rows = self.rows();
+        let first = self.first_row(rows.len(), window_size, s);
+        for (n, (path, item)) in rows.iter().enumerate().skip(first) {
+            let top = (PADDING + (n - first) as f32 * ROW_HEIGHT) * s;
+            if top > window_size.y() {
+                break;
+            }
+            if n == self.selected {
+                let paint = scene.push_paint(&Paint::from_color(ColorU::new(200, 215, 240, 255)));
+                let row = RectF::new(Vector2F::new(0., top), Vector2F::new(WIDTH * s, ROW_HEIGHT * s));
+                let mut draw_path = DrawPath::new(Outline::from_rect(row), paint);
+                draw_path.set_clip_path(Some(clip));
+                scene.push_draw_path(draw_path);
+            }
+
+            let left = (PADDING + INDENT * (path.len() - 1) as f32) * s;
+            let color = match item.color {
+                Some((r, g, b)) => {
+                    let c = |v: f32| (v.clamp(0., 1.) * 255.).round() as u8;
+                    ColorU::new(c(r), c(g), c(b), 255)
+                }
+                None => ColorU::black(),
+            };
+            let paint = scene.push_paint(&Paint::from_color(color));
+            if !item.children.is_empty() {
+                let open = self.expanded.contains(path);
+                let marker = marker(open).transformed(
+                    &(Transform2F::from_translation(Vector2F::new(left + 4. * s, top + ROW_HEIGHT * 0.5 * s))
+                        * Transform2F::from_scale(Vector2F::splat(4. * s))),
+                );
+                let mut draw_path = DrawPath::new(marker, paint);
+                draw_path.set_clip_path(Some(clip));
+                scene.push_draw_path(draw_path);
+            }
+            if let Some(ref font) = self.font {
+                let baseline = Vector2F::new(left + INDENT * s, top + (ROW_HEIGHT * 0.5 + FONT_SIZE * 0.35) * s);
+                draw_text(scene, &**font, &item.title, baseline, FONT_SIZE * s, item.italic, item.bold, paint, clip);
+            }
+        }
+    }
+}
+
+// triangle pointing right (closed) or down (open), around the origin with a size of about 2
+fn marker(open: bool) -> Outline {
+    let points = if open {
+        [Vector2F::new(-1., -0.6), Vector2F::new(1., -0.6), Vector2F::new(0., 0.8)]
+    } else {
+        [Vector2F::new(-0.6, -1.), Vector2F::new(0.8, 0.), Vector2F::new(-0.6, 1.)]
+    };
+    let mut contour = Contour::new();
+    for p in points {
+        contour.push_endpoint(p);
+    }
+    contour.close();
+    let mut outline = Outline::new();
+    outline.push_contour(contour);
+    outline
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_text(scene: &mut Scene, font: &dyn Font, text: &str, baseline: Vector2F, size: f32, italic: bool, bold: bool, paint: pathfinder_renderer::paint::PaintId, clip: ClipPathId) {
+    let font_matrix = font.font_matrix();
+    // glyph space has y up
+    let mut tr = Transform2F::from_translation(baseline) * Transform2F::from_scale(Vector2F::new(size, -size));
+    if italic {
+        tr = tr * Transform2F::row_major(1., 0.2, 0., 0., 1., 0.);
+    }
+    let mut x = 0.;
+    for c in text.chars() {
+        let c = if c.is_control() { ' ' } else { c };
+        let glyph = font
+            .gid_for_unicode_codepoint(c as u32)
+            .and_then(|gid| font.glyph(gid));
+        let glyph = match glyph {
+            Some(glyph) => glyph,
+            None => continue,
+        };
+        let transform = tr * Transform2F::from_translation(Vector2F::new(x, 0.)) * font_matrix;
+        let offsets: &[f32] = if bold { &[0., 0.04] } else { &[0.] };
+        for &dx in offsets {
+            let t = Transform2F::from_translation(Vector2F::new(dx * size, 0.)) * transform;
+            let mut draw_path = DrawPath::new(glyph.path.clone().transformed(&t), paint);
+            draw_path.set_clip_path(Some(clip));
+            scene.push_draw_path(draw_path);
+        }
+        x += font_matrix.m11() * glyph.metrics.advance;
+    }
+}
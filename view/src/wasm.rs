@@ -9,8 +9,13 @@ use pathfinder_renderer::scene::Scene;
 use pdf::any::AnySync;
 use pdf::backend::Backend;
 use pdf::file::{Cache as PdfCache, File as PdfFile, FileOptions, Log};
+use pdf::object::{Page, Resolve};
 use pdf::PdfError;
-use pdf_render::{page_bounds, render_page, Cache, SceneBackend};
+use pdf_render::links::{Destination, Link, LinkResolver, LinkTarget, View};
+use pdf_render::outline::OutlineTree;
+use pdf_render::{
+    page_bounds, page_transform, Cache, DirectoryFontProvider, FontProvider, MemoryFontProvider,
+};
 use std::path::Path;
 use std::rc::Rc;
 use std::sync::Arc;
@@ -34,7 +39,8 @@ use pathfinder_renderer::gpu::{
 use pathfinder_renderer::options::{BuildOptions, RenderTransform};
 
 use crate::config::{view_box, Config, Icon};
-use crate::context::{Context, ViewBackend};
+use crate::context::{Context, ViewBackend, DEFAULT_SCALE};
+use crate::outline::{OutlinePanel, PanelAction};
 use crate::round_v_to_16;
 use crate::{Emitter, Interactive};
 
@@ -42,6 +48,16 @@ pub struct PdfView<B: Backend, OC, SC, L> {
     file: PdfFile<B, OC, SC, L>,
     num_pages: usize,
     cache: Cache,
+    link_resolver: Option<LinkResolver>,
+    // links of the page `links_page`, without the view transform
+    links: Vec<Link>,
+    links_page: Option<usize>,
+    // the transform of the last scene
+    view_transform: Transform2F,
+    outline: Option<OutlinePanel>,
+    show_outline: bool,
+    // scroll position to apply once the target page is drawn
+    pending_view: Option<Destination>,
 }
 
 impl<B, OC, SC, L> PdfView<B, OC, SC, L>
@@ -51,13 +67,102 @@ where
     SC: PdfCache<Result<Arc<[u8]>, Arc<PdfError>>> + 'static,
     L: Log,
 {
+    /// Uses the fonts in `STANDARD_FONTS`, if available. Fonts that are not embedded are skipped otherwise.
     pub fn new(file: PdfFile<B, OC, SC, L>) -> Self {
+        let cache = Cache::new().unwrap_or_else(|e| {
+            warn!("no standard fonts: {}", e);
+            Cache::with_font_provider(Arc::new(MemoryFontProvider::new()))
+        });
+        PdfView::with_cache(file, cache)
+    }
+    pub fn with_cache(file: PdfFile<B, OC, SC, L>, cache: Cache) -> Self {
         PdfView {
             num_pages: file.num_pages() as usize,
             file,
-            cache: Cache::new(),
+            cache,
+            link_resolver: None,
+            links: vec![],
+            links_page: None,
+            view_transform: Transform2F::default(),
+            outline: None,
+            show_outline: false,
+            pending_view: None,
+        }
+    }
+
+    fn follow_link(&mut self, ctx: &mut Context<impl ViewBackend>, target: &LinkTarget) {
+        match *target {
+            LinkTarget::Dest(ref dest) | LinkTarget::Named { dest: Some(ref dest), .. } => {
+                self.goto_destination(ctx, dest)
+            }
+            LinkTarget::Action(ref action) => match action.as_str() {
+                "NextPage" => ctx.next_page(),
+                "PrevPage" => ctx.prev_page(),
+                "FirstPage" => ctx.goto_page(0),
+                "LastPage" => ctx.goto_page(self.num_pages.saturating_sub(1)),
+                _ => info!("unsupported action {}", action),
+            },
+            ref target => info!("link to {:?}", target),
+        }
+    }
+
+    fn goto_destination(&mut self, ctx: &mut Context<impl ViewBackend>, dest: &Destination) {
+        ctx.goto_page(dest.page as usize);
+        self.pending_view = Some(dest.clone());
+        ctx.request_redraw();
+    }
+
+    // scroll so that the position of `view` is at the top left of the window
+    fn apply_view(&self, ctx: &mut Context<impl ViewBackend>, page: &Page, view: &View) {
+        if let View::XYZ { zoom: Some(zoom), .. } = *view {
+            if ctx.config.zoom {
+                ctx.set_zoom(DEFAULT_SCALE * zoom);
+            }
+        }
+        let tr = page_transform(page);
+        let visible = ctx.window_size * (1.0 / ctx.scale);
+        let mut center = ctx.view_center;
+        if let Some(top) = view.top() {
+            center.set_y((tr * Vector2F::new(0., top)).y() + visible.y() * 0.5);
         }
+        if let Some(left) = view.left() {
+            center.set_x((tr * Vector2F::new(left, 0.)).x() + visible.x() * 0.5);
+        }
+        ctx.move_to(center);
     }
+
+    fn outline_action(&mut self, ctx: &mut Context<impl ViewBackend>, action: PanelAction) {
+        match action {
+            PanelAction::Ignored => {}
+            PanelAction::Redraw => ctx.request_redraw(),
+            PanelAction::Goto(path) => {
+                let target = self
+                    .outline
+                    .as_ref()
+                    .and_then(|panel| panel.tree().get(&path))
+                    .and_then(|item| item.target.clone());
+                match target {
+                    Some(target) => self.follow_link(ctx, &target),
+                    None => info!("outline item {:?} has no target", path),
+                }
+                ctx.request_redraw();
+            }
+            PanelAction::Close => {
+                self.show_outline = false;
+                ctx.request_redraw();
+            }
+        }
+    }
+}
+
+/// Helvetica from the standard fonts, for the outline panel.
+fn panel_font() -> Option<Box<dyn font::Font + Send + Sync>> {
+    let provider = DirectoryFontProvider::from_env().ok()?;
+    let key = provider.resolve("Helvetica")?;
+    let data = provider.load(&key).ok()?;
+    font::parse(&data)
+        .map_err(|e| warn!("can't parse the outline font: {:?}", e))
+        .ok()
 }
 
 impl<B, OC, SC, L> Interactive for PdfView<B, OC, SC, L>
@@ -82,6 +187,25 @@ where
 
     fn init(&mut self, ctx: &mut Context<Self::Backend>, sender: Emitter<Self::Event>) {
         ctx.num_pages = self.num_pages;
+        let resolver = self.file.resolver();
+        let catalog = resolver
+            .resolve(self.file.trailer.root.get_ref().get_inner())
+            .and_then(|p| p.into_dictionary());
+        match catalog {
+            Ok(catalog) => {
+                self.link_resolver = LinkResolver::new(&catalog, &resolver)
+                    .map_err(|e| warn!("links are not available: {:?}", e))
+                    .ok();
+                if let Some(ref links) = self.link_resolver {
+                    match OutlineTree::from_catalog(&catalog, links, &resolver) {
+                        Ok(Some(tree)) => self.outline = Some(OutlinePanel::new(tree, panel_font())),
+                        Ok(None) => {}
+                        Err(e) => warn!("can't read the outline: {:?}", e),
+                    }
+                }
+            }
+            Err(e) => warn!("can't read the catalog: {:?}", e),
+        }
         ctx.set_icon(
             image::load_from_memory_with_format(
                 include_bytes!("../../logo.png"),
@@ -99,11 +223,52 @@ where
         let page = self.file.get_page(ctx.page_nr as u32).unwrap();
 
         ctx.set_bounds(page_bounds(&page));
+        if let Some(dest) = self.pending_view.take() {
+            if dest.page as usize == ctx.page_nr {
+                self.apply_view(ctx, &page, &dest.view);
+            }
+        }
 
-        let mut backend = SceneBackend::new(&mut self.cache);
         let resolver = self.file.resolver();
-        render_page(&mut backend, &resolver, &page, ctx.view_transform()).unwrap();
-        backend.finish()
+        // recorded once per page, zooming and panning only replays it
+        let list = match self.cache.get_display_list(&page, &resolver) {
+            Ok(list) => list,
+            Err(e) => {
+                warn!("can't render page {}: {:?}", ctx.page_nr, e);
+                return Scene::new();
+            }
+        };
+        if self.links_page != Some(ctx.page_nr) {
+            if let Some(ref links) = self.link_resolver {
+                self.links = links
+                    .page_links(page.get_ref().get_inner(), list.transform(), &resolver)
+                    .unwrap_or_else(|e| {
+                        warn!("can't read links: {:?}", e);
+                        vec![]
+                    });
+            }
+            self.links_page = Some(ctx.page_nr);
+        }
+
+        self.view_transform = ctx.view_transform();
+        // when panning, only the part of the page in the window is drawn
+        let region = match ctx.config.pan {
+            true => Some(RectF::new(Vector2F::zero(), ctx.window_size)),
+            false => None,
+        };
+        let mut scene = list.scene_region(self.view_transform, region);
+        if let (true, Some(panel)) = (self.show_outline, self.outline.as_ref()) {
+            panel.draw(&mut scene, ctx.window_size, ctx.scale_factor);
+        }
+        scene
+    }
+
+    fn outline(&self) -> Option<&OutlineTree> {
+        self.outline.as_ref().map(|panel| panel.tree())
+    }
+
+    fn goto_outline_item(&mut self, ctx: &mut Context<Self::Backend>, path: &[usize]) {
+        self.outline_action(ctx, PanelAction::Goto(path.to_vec()));
     }
 
     fn mouse_input(
@@ -117,6 +282,24 @@ where
             return;
         }
         info!("x={}, y={}", pos.x(), pos.y());
+        if self.show_outline {
+            if let Some(ref mut panel) = self.outline {
+                let action = panel.click(pos, ctx.window_size, ctx.scale_factor);
+                if action != PanelAction::Ignored {
+                    self.outline_action(ctx, action);
+                    return;
+                }
+            }
+        }
+        let page_pos = self.view_transform.inverse() * pos;
+        let target = self
+            .links
+            .iter()
+            .find(|link| link.contains(page_pos))
+            .and_then(|link| link.target.clone());
+        if let Some(target) = target {
+            self.follow_link(ctx, &target);
+        }
     }
 
     fn keyboard_input(
@@ -128,6 +311,22 @@ where
         if event.state == ElementState::Released {
             return;
         }
+        if let PhysicalKey::Code(code) = event.physical_key {
+            if code == KeyCode::KeyO && self.outline.is_some() {
+                self.show_outline = !self.show_outline;
+                ctx.request_redraw();
+                return;
+            }
+            if self.show_outline {
+                if let Some(ref mut panel) = self.outline {
+                    let action = panel.key(code);
+                    if action != PanelAction::Ignored {
+                        self.outline_action(ctx, action);
+                        return;
+                    }
+                }
+            }
+        }
         if state.shift_key() {
             let page = ctx.page_nr();
             match event.physical_key {